@@ -2,9 +2,10 @@ use anyhow::Result;
 use indicatif::ProgressIterator;
 use muscat::distinguishers::cpa::CpaProcessor;
 use muscat::leakage_model::{aes::sbox, hw};
+use muscat::npy::NpzExport;
 use muscat::util::progress_bar;
 use ndarray::{Array2, s};
-use ndarray_npy::{read_npy, write_npy};
+use ndarray_npy::read_npy;
 use rayon::prelude::{ParallelBridge, ParallelIterator};
 
 // traces format
@@ -50,16 +51,16 @@ fn rank() -> Result<()> {
                 )
                 .reduce(
                     || CpaProcessor::new(size, guess_range),
-                    |lhs, rhs| lhs + rhs,
+                    |lhs, rhs| lhs.combine(rhs),
                 );
-            rank = rank + x;
+            rank = rank.combine(x);
         }
     }
 
     let rank = rank.finalize(leakage_model);
 
-    // save rank key curves in npy
-    write_npy("../results/rank.npy", &rank.rank().map(|&x| x as u64))?;
+    // save correlations and key ranks in npz
+    rank.save_npz("../results/rank.npz")?;
 
     Ok(())
 }
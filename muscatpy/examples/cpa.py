@@ -1,4 +1,3 @@
-import functools
 import os
 from pathlib import Path
 
@@ -8,16 +7,6 @@ import numpy as np
 import muscatpy
 
 
-@functools.cache
-def hamming_weight(x: int) -> int:
-    weight = 0
-    while x != 0:
-        if x & 1 == 1:
-            weight += 1
-        x = x >> 1
-    return weight
-
-
 TRACES_DIR = Path(os.environ["TRACES_DIR"])
 
 traces = np.load(TRACES_DIR / "traces.npy")
@@ -29,22 +18,19 @@ assert traces.shape[0] == plaintexts.shape[0]
 traces = traces.astype(np.float32)
 plaintexts = plaintexts.astype(np.uint64)
 
-# Compute the CPA using the `compute_cpa` helper
+# Compute the CPA using the `compute_cpa` helper and a built-in leakage model
 cpa = muscatpy.distinguishers.compute_cpa(
     traces,
     plaintexts,
     256,
     0,
-    lambda plaintext, guess: hamming_weight(
-        muscatpy.leakage_model.aes.sbox(plaintext ^ guess)
-    ),
+    "aes_sbox_hw",
     200,
 )
 
-# Or using the lower level `CpaProcessor` class
-leakage_model = lambda plaintext, guess: hamming_weight(
-    muscatpy.leakage_model.aes.sbox(plaintext ^ guess)
-)
+# Or using the lower level `CpaProcessor` class and a leakage model lookup table, indexed by
+# `[plaintext, guess]`. Leakage models can also be Python callables taking a plaintext and a guess.
+leakage_model = muscatpy.leakage_model.leakage_table("aes_sbox_hw")
 
 cpa_processor = muscatpy.distinguishers.CpaProcessor(traces.shape[1], 256, traces.dtype)
 for i in range(0, traces.shape[0], 200):
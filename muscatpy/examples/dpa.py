@@ -27,13 +27,12 @@ plaintexts = np.load(TRACES_DIR / "plaintexts.npy")
 traces = traces.astype(np.float32)
 plaintexts = plaintexts.astype(np.uint64)
 
-# Compute the DPA using the `compute_dpa` helper
+# Compute the DPA using the `compute_dpa` helper and a built-in selection function
 dpa = muscatpy.distinguishers.compute_dpa(
     traces,
     plaintexts[:, 0],
     256,
-    lambda plaintext, guess: (muscatpy.leakage_model.aes.sbox(plaintext ^ guess) & 1)
-    == 1,
+    "aes_sbox_bit0",
     200,
 )
 
@@ -1,7 +1,14 @@
 from typing import TYPE_CHECKING
 
 if TYPE_CHECKING:
-    from . import distinguishers, leakage_detection, leakage_model, processors
+    from . import (
+        distinguishers,
+        leakage_detection,
+        leakage_model,
+        preprocessors,
+        processors,
+        quicklog,
+    )
 else:
     from .muscatpy import *
 
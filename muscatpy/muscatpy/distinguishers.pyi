@@ -1,5 +1,5 @@
 from collections.abc import Callable
-from typing import Union
+from typing import Optional, Union
 
 import numpy as np
 import numpy.typing as npt
@@ -16,13 +16,29 @@ Trace = Union[
     npt.NDArray[np.float32],
 ]
 
+Progress = Callable[[int, int], object]
+"""A progress callback, periodically called with the number of traces processed and the total
+number of traces. `compute_*` functions run with the GIL released and can be interrupted with
+Ctrl-C. Exceptions raised by the callback also cancel the computation and are propagated."""
+
+LeakageModel = Union[str, npt.NDArray[np.integer], Callable[[int, int], int]]
+"""A leakage model of a plaintext and a guess: the name of a built-in model (see
+`leakage_model.leakage_models`), a lookup table indexed by `[plaintext, guess]`, or a callable.
+Exceptions raised by callables are propagated."""
+
+SelectionFunction = Union[str, npt.NDArray[np.bool_], Callable[[int, int], bool]]
+"""A selection function of a plaintext and a guess: the name of a built-in selection function (see
+`leakage_model.selection_functions`), a lookup table indexed by `[plaintext, guess]`, or a callable.
+Exceptions raised by callables are propagated."""
+
 def compute_cpa(
     traces: Trace,
     plaintexts: npt.NDArray[np.uint64],
     guess_range: int,
     target_byte: int,
-    leakage_model: Callable[[int, int], int],
+    leakage_model: LeakageModel,
     batch_size: int,
+    progress: Optional[Progress] = None,
 ) -> Cpa:
     """Compute the [`Cpa`] of the given traces."""
 
@@ -31,8 +47,9 @@ def compute_cpa_normal(
     plaintexts: npt.NDArray[np.uint64],
     guess_range: int,
     target_byte: int,
-    leakage_model: Callable[[int, int], int],
+    leakage_model: LeakageModel,
     batch_size: int,
+    progress: Optional[Progress] = None,
 ) -> Cpa:
     """Compute the [`Cpa`] of the given traces."""
 
@@ -55,18 +72,38 @@ class Cpa:
         """Return the maximum Pearson correlation coefficient for each guess."""
 
 class CpaProcessor:
+    """Streaming computation of the CPA.
+
+    Instances can be pickled, to save the state of a computation or send it to another process.
+    """
+
     def __init__(self, num_samples: int, guess_range: int, dtype: np.dtype):
         """Create a new CPA processor."""
 
+    def update(
+        self,
+        trace: Trace,
+        plaintext: int,
+        leakage_model: LeakageModel,
+    ):
+        """Update the processor with a trace and its plaintext."""
+
+    def combine(self, other: CpaProcessor) -> CpaProcessor:
+        """Return a new processor combining the traces processed by this one and `other`.
+
+        Raise `TypeError` if they have different dtypes, and `ValueError` if they have different
+        `num_samples` or `guess_range`.
+        """
+
     def batch_update(
         self,
         trace_batch: Trace,
         plaintext_batch: npt.NDArray[np.uint64],
-        leakage_model: Callable[[int, int], int],
+        leakage_model: LeakageModel,
     ):
         """Update the processor with a batch of traces and plaintexts."""
 
-    def finalize(self, leakage_model: Callable[[int, int], int]) -> Cpa:
+    def finalize(self, leakage_model: LeakageModel) -> Cpa:
         """Finalize the computation and return the CPA result."""
 
 class CpaNormalProcessor:
@@ -79,9 +116,14 @@ class CpaNormalProcessor:
         self,
         trace_batch: Trace,
         plaintext_batch: npt.NDArray[np.uint64],
-        leakage_model: Callable[[npt.NDArray[np.uint64], int], int],
+        leakage_model: Union[LeakageModel, Callable[[npt.NDArray[np.uint64], int], int]],
+        target_byte: Optional[int] = None,
     ):
-        """Update the processor with a batch of traces and plaintexts."""
+        """Update the processor with a batch of traces and plaintexts.
+
+        If `target_byte` is given, `leakage_model` is a leakage model of the target plaintext byte.
+        Otherwise, it must be a callable taking the whole plaintext and a guess.
+        """
 
     def finalize(self) -> Cpa:
         """Finalize the computation and return the CPA result."""
@@ -90,8 +132,9 @@ def compute_dpa(
     traces: Trace,
     plaintexts: npt.NDArray[np.uint64],
     guess_range: int,
-    selection_function: Callable[[int, int], bool],
+    selection_function: SelectionFunction,
     batch_size: int,
+    progress: Optional[Progress] = None,
 ) -> Dpa:
     """Compute the [`Dpa`] of the given traces."""
 
@@ -114,14 +157,34 @@ class Dpa:
         """Return the maximum differential peak for each guess."""
 
 class DpaProcessor:
+    """Streaming computation of the DPA.
+
+    Instances can be pickled, to save the state of a computation or send it to another process.
+    """
+
     def __init__(self, num_samples: int, guess_range: int, dtype: np.dtype):
         """Create a new DPA processor."""
 
+    def update(
+        self,
+        trace: Trace,
+        plaintext: int,
+        selection_function: SelectionFunction,
+    ):
+        """Update the processor with a trace and its plaintext."""
+
+    def combine(self, other: DpaProcessor) -> DpaProcessor:
+        """Return a new processor combining the traces processed by this one and `other`.
+
+        Raise `TypeError` if they have different dtypes, and `ValueError` if they have different
+        `num_samples` or `guess_range`.
+        """
+
     def batch_update(
         self,
         trace_batch: Trace,
         plaintext_batch: npt.NDArray[np.uint64],
-        selection_function: Callable[[int, int], bool],
+        selection_function: SelectionFunction,
     ):
         """Update the processor with a batch of traces and plaintexts."""
 
@@ -1,5 +1,5 @@
 from collections.abc import Callable
-from typing import Union
+from typing import Optional, Union
 
 import numpy as np
 import numpy.typing as npt
@@ -16,11 +16,17 @@ Trace = Union[
     npt.NDArray[np.float32],
 ]
 
+Progress = Callable[[int, int], object]
+"""A progress callback, periodically called with the number of traces processed and the total
+number of traces. `compute_*` functions run with the GIL released and can be interrupted with
+Ctrl-C. Exceptions raised by the callback also cancel the computation and are propagated."""
+
 def compute_snr(
     traces: Trace,
     classes: int,
     get_class: Callable[[int], int],
     batch_size: int,
+    progress: Optional[Progress] = None,
 ) -> npt.NDArray[np.float32]:
     """Compute the SNR of the given traces."""
 
@@ -29,6 +35,7 @@ def compute_nicv(
     classes: int,
     get_class: Callable[[int], int],
     batch_size: int,
+    progress: Optional[Progress] = None,
 ) -> npt.NDArray[np.float32]:
     """Compute the NICV of the given traces."""
 
@@ -36,5 +43,137 @@ def compute_ttest(
     traces: Trace,
     trace_classes: npt.NDArray[np.bool_],
     batch_size: int,
+    progress: Optional[Progress] = None,
 ) -> npt.NDArray[np.float32]:
     """Compute the Welch's T-test of the given traces."""
+
+class SnrProcessor:
+    """Streaming computation of the SNR of traces split into classes.
+
+    Instances can be pickled, to save the state of a computation or send it to another process.
+    """
+
+    def __init__(
+        self,
+        trace_length: int,
+        num_classes: int,
+        dtype: np.dtype,
+        classes_var: bool = False,
+    ):
+        """Create a new processor for traces of the given dtype.
+
+        If `classes_var` is set, the variance of each class is also computed.
+        """
+
+    @property
+    def trace_length(self) -> int:
+        """Number of samples per trace."""
+
+    @property
+    def num_classes(self) -> int:
+        """Number of classes."""
+
+    def update(self, trace: Trace, class: int):
+        """Update the processor with a trace of the given class."""
+
+    def batch_update(self, trace_batch: Trace, class_batch: npt.NDArray[np.uint64]):
+        """Update the processor with a batch of traces and their classes."""
+
+    def combine(self, other: SnrProcessor) -> SnrProcessor:
+        """Return a new processor combining the traces processed by this one and `other`.
+
+        Raise `TypeError` if they have different dtypes, and `ValueError` if they have different
+        `trace_length` or `num_classes`, or if only one computes `classes_var`.
+        """
+
+    def snr(self) -> npt.NDArray[np.float32]:
+        """Return the SNR of the traces processed."""
+
+    def classes_mean(self) -> npt.NDArray[np.float32]:
+        """Return the mean trace of each class."""
+
+    def classes_var(self) -> Optional[npt.NDArray[np.float32]]:
+        """Return the variance of each class, if enabled at creation."""
+
+    def classes_count(self) -> npt.NDArray[np.uint64]:
+        """Return the number of traces processed for each class."""
+
+class NicvProcessor:
+    """Streaming computation of the NICV of traces split into classes.
+
+    Instances can be pickled, to save the state of a computation or send it to another process.
+    """
+
+    def __init__(
+        self,
+        trace_length: int,
+        num_classes: int,
+        dtype: np.dtype,
+        classes_var: bool = False,
+    ):
+        """Create a new processor for traces of the given dtype.
+
+        If `classes_var` is set, the variance of each class is also computed.
+        """
+
+    @property
+    def trace_length(self) -> int:
+        """Number of samples per trace."""
+
+    @property
+    def num_classes(self) -> int:
+        """Number of classes."""
+
+    def update(self, trace: Trace, class: int):
+        """Update the processor with a trace of the given class."""
+
+    def batch_update(self, trace_batch: Trace, class_batch: npt.NDArray[np.uint64]):
+        """Update the processor with a batch of traces and their classes."""
+
+    def combine(self, other: NicvProcessor) -> NicvProcessor:
+        """Return a new processor combining the traces processed by this one and `other`.
+
+        Raise `TypeError` if they have different dtypes, and `ValueError` if they have different
+        `trace_length` or `num_classes`, or if only one computes `classes_var`.
+        """
+
+    def nicv(self) -> npt.NDArray[np.float32]:
+        """Return the NICV of the traces processed."""
+
+    def classes_mean(self) -> npt.NDArray[np.float32]:
+        """Return the mean trace of each class."""
+
+    def classes_var(self) -> Optional[npt.NDArray[np.float32]]:
+        """Return the variance of each class, if enabled at creation."""
+
+    def classes_count(self) -> npt.NDArray[np.uint64]:
+        """Return the number of traces processed for each class."""
+
+class TTestProcessor:
+    """Streaming computation of the Welch's T-test of traces split into two classes.
+
+    Instances can be pickled, to save the state of a computation or send it to another process.
+    """
+
+    def __init__(self, trace_length: int, dtype: np.dtype):
+        """Create a new processor for traces of the given dtype."""
+
+    @property
+    def trace_length(self) -> int:
+        """Number of samples per trace."""
+
+    def update(self, trace: Trace, class: bool):
+        """Update the processor with a trace of the given class."""
+
+    def batch_update(self, trace_batch: Trace, class_batch: npt.NDArray[np.bool_]):
+        """Update the processor with a batch of traces and their classes."""
+
+    def combine(self, other: TTestProcessor) -> TTestProcessor:
+        """Return a new processor combining the traces processed by this one and `other`.
+
+        Raise `TypeError` if they have different dtypes, and `ValueError` if they have different
+        `trace_length`.
+        """
+
+    def ttest(self) -> npt.NDArray[np.float32]:
+        """Return the Welch's T-test of the traces processed."""
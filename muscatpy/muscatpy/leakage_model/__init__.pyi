@@ -0,0 +1,20 @@
+import numpy as np
+import numpy.typing as npt
+
+from . import aes
+
+def leakage_models() -> list[str]:
+    """Return the names of the built-in leakage models."""
+
+def selection_functions() -> list[str]:
+    """Return the names of the built-in selection functions."""
+
+def leakage_table(
+    name: str, plaintext_range: int = 256, guess_range: int = 256
+) -> npt.NDArray[np.uint64]:
+    """Return the lookup table of a built-in leakage model, indexed by `[plaintext, guess]`."""
+
+def selection_table(
+    name: str, plaintext_range: int = 256, guess_range: int = 256
+) -> npt.NDArray[np.bool_]:
+    """Return the lookup table of a built-in selection function, indexed by `[plaintext, guess]`."""
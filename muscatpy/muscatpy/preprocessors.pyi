@@ -0,0 +1,112 @@
+from typing import Union
+
+import numpy as np
+import numpy.typing as npt
+
+Trace = Union[
+    npt.NDArray[np.uint8],
+    npt.NDArray[np.uint16],
+    npt.NDArray[np.uint32],
+    npt.NDArray[np.uint64],
+    npt.NDArray[np.int8],
+    npt.NDArray[np.int16],
+    npt.NDArray[np.int32],
+    npt.NDArray[np.int64],
+    npt.NDArray[np.float32],
+]
+
+
+class StandardScaler:
+    """Standardization of traces by removing the mean and scaling to unit variance."""
+
+    def __init__(self, trace_length: int, dtype: np.dtype):
+        """Create a new standard scaler for traces of the given dtype."""
+
+    @property
+    def trace_length(self) -> int:
+        """Number of samples per trace."""
+
+    def process(self, trace: Trace):
+        """Update the mean and variance with a trace."""
+
+    def batch_process(self, traces: Trace):
+        """Update the mean and variance with a batch of traces."""
+
+    def finalize(self):
+        """Compute the mean and variance, to be called before applying the scaler."""
+
+    def apply(self, trace: Trace) -> npt.NDArray[np.float32]:
+        """Return the standardized trace."""
+
+    def batch_apply(self, traces: Trace) -> npt.NDArray[np.float32]:
+        """Return the standardized traces."""
+
+class CenteredProduct:
+    """Centered product of the samples of the given intervals, used in high-order attacks."""
+
+    def __init__(self, trace_length: int, intervals: list[tuple[int, int]]):
+        """Create a new centered product combining the samples of the given `(start, end)`
+        intervals."""
+
+    @property
+    def trace_length(self) -> int:
+        """Number of samples per trace."""
+
+    def process(self, trace: Trace):
+        """Update the mean with a trace."""
+
+    def batch_process(self, traces: Trace):
+        """Update the mean with a batch of traces."""
+
+    def finalize(self):
+        """Compute the mean, to be called before applying the centered product."""
+
+    def apply(self, trace: Trace) -> npt.NDArray[np.float32]:
+        """Return the centered product of the samples of a trace."""
+
+    def batch_apply(self, traces: Trace) -> npt.NDArray[np.float32]:
+        """Return the centered product of the samples of each trace."""
+
+class Power:
+    """Elevates the samples of the given intervals to a power."""
+
+    def __init__(self, intervals: list[tuple[int, int]], power: int):
+        """Create a new power preprocessor for the given `(start, end)` intervals."""
+
+    def process(self, trace: Trace) -> npt.NDArray[np.float32]:
+        """Return the samples of the intervals of a trace elevated to the power."""
+
+    def batch_process(self, traces: Trace) -> npt.NDArray[np.float32]:
+        """Return the samples of the intervals of each trace elevated to the power."""
+
+class ElasticAlignment:
+    """Elastic alignment of traces on a reference trace, based on FastDTW with the euclidean
+    distance."""
+
+    def __init__(self, reference_trace: Trace, radius: int):
+        """Create a new elastic alignment. Traces must have the dtype and length of the reference
+        trace."""
+
+    @property
+    def trace_length(self) -> int:
+        """Number of samples per trace."""
+
+    @property
+    def reference_trace(self) -> Trace:
+        """Reference trace."""
+
+    def align(self, trace: Trace) -> Trace:
+        """Return the trace aligned on the reference trace."""
+
+    def batch_align(self, traces: Trace) -> Trace:
+        """Return the traces aligned on the reference trace, in parallel."""
+
+    def warp(self, trace: Trace) -> tuple[Trace, npt.NDArray[np.uint64], float]:
+        """Return the aligned trace, the warp path as an array of (reference trace index, trace
+        index) pairs and the DTW distance."""
+
+    def refine_reference(self, traces: Trace, num_iterations: int) -> list[float]:
+        """Iteratively refine the reference trace with the mean of the aligned traces.
+
+        Return the mean DTW distance of the traces to the reference trace at each iteration.
+        """
@@ -24,3 +24,39 @@ def compute_var(
     traces: Trace,
 ) -> npt.NDArray[np.float32]:
     """Compute the variance of the given traces."""
+
+class MeanVar:
+    """Streaming computation of the mean and variance of traces.
+
+    Instances can be pickled, to save the state of a computation or send it to another process.
+    """
+
+    def __init__(self, trace_length: int, dtype: np.dtype):
+        """Create a new mean and variance processor for traces of the given dtype."""
+
+    @property
+    def trace_length(self) -> int:
+        """Number of samples per trace."""
+
+    @property
+    def count(self) -> int:
+        """Number of traces processed."""
+
+    def update(self, trace: Trace):
+        """Update the processor with a trace."""
+
+    def batch_update(self, trace_batch: Trace):
+        """Update the processor with a batch of traces."""
+
+    def combine(self, other: MeanVar) -> MeanVar:
+        """Return a new processor combining the traces processed by this one and `other`.
+
+        Raise `TypeError` if they have different dtypes, and `ValueError` if they have different
+        `trace_length`.
+        """
+
+    def mean(self) -> npt.NDArray[np.float32]:
+        """Return the mean of the traces processed."""
+
+    def var(self) -> npt.NDArray[np.float32]:
+        """Return the variance of the traces processed."""
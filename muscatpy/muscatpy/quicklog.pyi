@@ -0,0 +1,34 @@
+from typing import Optional, Union
+
+import numpy as np
+import numpy.typing as npt
+
+Trace = Union[
+    npt.NDArray[np.uint8],
+    npt.NDArray[np.uint16],
+    npt.NDArray[np.uint32],
+    npt.NDArray[np.uint64],
+    npt.NDArray[np.int8],
+    npt.NDArray[np.int16],
+    npt.NDArray[np.int32],
+    npt.NDArray[np.int64],
+    npt.NDArray[np.float32],
+]
+
+
+def load(
+    path: str,
+    dtype: np.dtype,
+    traces_dir: Optional[str] = None,
+    bytes_columns: Optional[dict[str, str]] = None,
+    bool_columns: Optional[dict[str, str]] = None,
+    integer_columns: Optional[dict[str, str]] = None,
+) -> tuple[Trace, dict[str, npt.NDArray]]:
+    """Load the traces of a quicklog log, along with record fields as columns.
+
+    Traces are loaded from `traces_dir`, or from the `TRACESDIR` environment variable if not
+    given. Columns map a column name to the key of a record field, holding hex-encoded bytes,
+    booleans or integers.
+
+    Return the traces and a dictionary of the columns.
+    """
@@ -1,14 +1,81 @@
 use std::any::Any;
 
+use muscat::distinguishers::{
+    cpa::{CpaProcessor as CoreCpaProcessor, cpa_with_progress},
+    cpa_normal::cpa_with_progress as cpa_normal_with_progress,
+    dpa::{DpaProcessor as CoreDpaProcessor, dpa_with_progress},
+};
 use numpy::{
     IntoPyArray, PyArrayDescr, PyArrayDescrMethods, PyArrayMethods, PyUntypedArray,
     PyUntypedArrayMethods, ToPyArray,
     array::{PyArray1, PyArray2},
     dtype,
-    ndarray::{Array2, ArrayView1},
+    ndarray::{Array2, ArrayView1, Axis},
+};
+use pyo3::types::PyBytes;
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
 };
-use pyo3::types::PyFunction;
-use pyo3::{exceptions::PyTypeError, prelude::*};
+
+use crate::{
+    check_array, check_same_dtype, check_same_param, check_state_param, check_trace_length,
+    deserialize_state, leakage_model::tabulate, run_detached, serialize_state,
+};
+
+/// Check that there are as many traces as plaintexts, and that `batch_size` is not zero.
+fn check_num_traces(
+    traces: &Bound<PyUntypedArray>,
+    num_plaintexts: usize,
+    batch_size: usize,
+) -> PyResult<()> {
+    let num_traces = traces.shape()[0];
+    if num_traces != num_plaintexts {
+        return Err(PyValueError::new_err(format!(
+            "Invalid number of plaintexts: {num_plaintexts}. It should be equal to the number of traces {num_traces}."
+        )));
+    }
+
+    if batch_size == 0 {
+        return Err(PyValueError::new_err("batch_size should not be zero."));
+    }
+
+    Ok(())
+}
+
+/// Check that `target_byte` is a valid column index of plaintexts of `plaintext_length` bytes.
+fn check_target_byte(target_byte: usize, plaintext_length: usize) -> PyResult<()> {
+    if target_byte >= plaintext_length {
+        return Err(PyValueError::new_err(format!(
+            "Invalid target_byte: {target_byte}. target_byte should be lower than the plaintext length {plaintext_length}."
+        )));
+    }
+
+    Ok(())
+}
+
+/// Check that all plaintexts are lower than `guess_range`, as CPA processors index their sums by
+/// plaintext.
+fn check_plaintexts<'a>(
+    plaintexts: impl IntoIterator<Item = &'a usize>,
+    guess_range: usize,
+) -> PyResult<()> {
+    if let Some(plaintext) = plaintexts.into_iter().find(|&&p| p >= guess_range) {
+        return Err(PyValueError::new_err(format!(
+            "Invalid plaintext: {plaintext}. plaintexts should be lower than guess_range {guess_range}."
+        )));
+    }
+
+    Ok(())
+}
+
+/// Returns the exclusive upper bound of the given plaintexts.
+fn plaintext_range(plaintexts: ArrayView1<usize>) -> usize {
+    plaintexts
+        .iter()
+        .max()
+        .map_or(0, |&plaintext| plaintext + 1)
+}
 
 #[pyclass]
 pub struct Cpa(muscat::distinguishers::cpa::Cpa);
@@ -33,24 +100,25 @@ impl Cpa {
 }
 
 #[pyfunction]
+#[pyo3(signature = (
+    traces,
+    plaintexts,
+    guess_range,
+    target_byte,
+    leakage_model,
+    batch_size,
+    progress=None,
+))]
 pub fn compute_cpa<'py>(
     traces: &Bound<'py, PyUntypedArray>,
     plaintexts: &Bound<'py, PyArray2<usize>>,
     guess_range: usize,
     target_byte: usize,
-    leakage_model: &Bound<'py, PyFunction>,
+    leakage_model: &Bound<'py, PyAny>,
     batch_size: usize,
+    progress: Option<&Bound<'py, PyAny>>,
 ) -> PyResult<Cpa> {
-    let mut modeled_leakages = Array2::zeros((guess_range, 256));
-    for guess in 0..guess_range {
-        for plaintext in 0..256 {
-            modeled_leakages[[guess, plaintext]] = leakage_model
-                .call((plaintext, guess), None)
-                .unwrap()
-                .extract::<usize>()
-                .unwrap();
-        }
-    }
+    let py = traces.py();
 
     if traces.ndim() != 2 {
         return Err(PyTypeError::new_err(format!(
@@ -59,22 +127,37 @@ pub fn compute_cpa<'py>(
         )));
     }
 
+    let plaintexts = plaintexts.readonly();
+    let plaintexts = plaintexts.as_array();
+    check_num_traces(traces, plaintexts.shape()[0], batch_size)?;
+    check_target_byte(target_byte, plaintexts.shape()[1])?;
+    check_plaintexts(plaintexts.column(target_byte), guess_range)?;
+
+    // Leakages of all plaintexts lower than guess_range are needed to compute the correlations
+    let modeled_leakages =
+        tabulate::<usize>(leakage_model, guess_range, guess_range, 0..guess_range)?;
+
     let sample_dtype = traces.dtype();
 
     macro_rules! type_dispatch {
         ($($ty:ty),*) => {
             $(
                 if sample_dtype.is_equiv_to(&dtype::<$ty>(traces.py())) {
-                    let traces = traces.cast::<PyArray2<$ty>>()?;
-
-                    return Ok(Cpa(muscat::distinguishers::cpa::cpa(
-                        traces.readonly().as_array(),
-                        plaintexts.readonly().as_array(),
-                        guess_range,
-                        target_byte,
-                        |guess, plaintext| modeled_leakages[[guess, plaintext]],
-                        batch_size,
-                    )));
+                    let traces = traces.cast::<PyArray2<$ty>>()?.readonly();
+                    let traces = traces.as_array();
+
+                    return run_detached(py, progress, |progress| {
+                        cpa_with_progress(
+                            traces,
+                            plaintexts,
+                            guess_range,
+                            target_byte,
+                            |plaintext, guess| modeled_leakages[[plaintext, guess]],
+                            batch_size,
+                            progress,
+                        )
+                    })
+                    .map(Cpa);
                 }
             )*
         };
@@ -87,9 +170,11 @@ pub fn compute_cpa<'py>(
     )))
 }
 
-#[pyclass]
+#[pyclass(module = "muscatpy.distinguishers")]
 struct CpaProcessor {
     inner: Box<dyn Any + Send + Sync>,
+    num_samples: usize,
+    guess_range: usize,
     dtype: Py<PyArrayDescr>,
 }
 
@@ -109,6 +194,8 @@ impl CpaProcessor {
                             inner: Box::new(
                                 ::muscat::distinguishers::cpa::CpaProcessor::<$ty>::new(num_samples, guess_range)
                             ),
+                            num_samples,
+                            guess_range,
                             dtype: dtype.clone().into(),
                         });
                     }
@@ -125,101 +212,135 @@ impl CpaProcessor {
         &mut self,
         trace_batch: &Bound<'py, PyUntypedArray>,
         plaintext_batch: &Bound<'py, PyArray1<usize>>,
-        leakage_model: &Bound<'py, PyFunction>,
+        leakage_model: &Bound<'py, PyAny>,
     ) -> PyResult<()> {
         let py = trace_batch.py();
+        check_array(py, "trace_batch", trace_batch, 2, &self.dtype)?;
+        check_trace_length("trace_batch", trace_batch, self.num_samples)?;
+
+        let plaintext_batch = plaintext_batch.readonly();
+        let plaintext_batch = plaintext_batch.as_array();
+        check_num_traces(trace_batch, plaintext_batch.len(), 1)?;
+        check_plaintexts(plaintext_batch, self.guess_range)?;
+
+        let modeled_leakages = tabulate::<usize>(
+            leakage_model,
+            self.guess_range,
+            self.guess_range,
+            plaintext_batch.iter().copied(),
+        )?;
+
+        dispatch!(self, py, |cpa_processor: &mut CoreCpaProcessor<T>| {
+            cpa_processor.batch_update(
+                trace_batch.cast::<PyArray2<T>>()?.readonly().as_array(),
+                plaintext_batch,
+                &|plaintext, guess| modeled_leakages[[plaintext, guess]],
+            );
+            Ok(())
+        })
+    }
 
-        if trace_batch.ndim() != 2 {
-            return Err(PyTypeError::new_err(format!(
-                "Invalid trace batch ndim: {}. trace_batch ndim should be equal to 2.",
-                trace_batch.ndim()
-            )));
-        }
-
-        if !trace_batch.dtype().is_equiv_to(self.dtype.bind(py)) {
-            return Err(PyTypeError::new_err(format!(
-                "Invalid trace batch dtype: {}. trace_batch dtype should be {}.",
-                trace_batch.dtype(),
-                self.dtype
-            )));
-        }
-
-        macro_rules! type_dispatch {
-            ($($ty:ty),*) => {
-                $(
-                    if self.dtype.bind(py).is_equiv_to(&::numpy::dtype::<$ty>(py)) {
-                        let cpa_processor =
-                            self
-                                .inner
-                                .downcast_mut::<::muscat::distinguishers::cpa::CpaProcessor<$ty>>()
-                                .unwrap();
-
-                        let trace_batch = trace_batch.cast::<PyArray2<$ty>>()?;
+    fn update<'py>(
+        &mut self,
+        trace: &Bound<'py, PyUntypedArray>,
+        plaintext: usize,
+        leakage_model: &Bound<'py, PyAny>,
+    ) -> PyResult<()> {
+        let py = trace.py();
+        check_array(py, "trace", trace, 1, &self.dtype)?;
+        check_trace_length("trace", trace, self.num_samples)?;
+        check_plaintexts([plaintext].iter(), self.guess_range)?;
+
+        let modeled_leakages = tabulate::<usize>(
+            leakage_model,
+            self.guess_range,
+            self.guess_range,
+            [plaintext],
+        )?;
+
+        dispatch!(self, py, |cpa_processor: &mut CoreCpaProcessor<T>| {
+            cpa_processor.update(
+                trace.cast::<PyArray1<T>>()?.readonly().as_array(),
+                plaintext,
+                |plaintext, guess| modeled_leakages[[plaintext, guess]],
+            );
+            Ok(())
+        })
+    }
 
-                        cpa_processor.batch_update(
-                            trace_batch.readonly().as_array(),
-                            plaintext_batch.readonly().as_array(),
-                            &|plaintext, guess| leakage_model.call((plaintext, guess), None).unwrap().extract::<usize>().unwrap()
-                        );
+    fn combine(&self, py: Python, other: &Self) -> PyResult<Self> {
+        check_same_dtype(py, &self.dtype, &other.dtype)?;
+        check_same_param("num_samples", self.num_samples, other.num_samples)?;
+        check_same_param("guess_range", self.guess_range, other.guess_range)?;
+
+        dispatch!(self, py, |cpa_processor: &CoreCpaProcessor<T>| {
+            let other = other.inner.downcast_ref::<CoreCpaProcessor<T>>().unwrap();
+            Ok(Self {
+                inner: Box::new(cpa_processor.clone().combine(other.clone())),
+                num_samples: self.num_samples,
+                guess_range: self.guess_range,
+                dtype: self.dtype.clone_ref(py),
+            })
+        })
+    }
 
-                        return Ok(());
-                    }
-                )*
-            };
-        }
+    fn __getnewargs__(&self, py: Python) -> (usize, usize, Py<PyArrayDescr>) {
+        (self.num_samples, self.guess_range, self.dtype.clone_ref(py))
+    }
 
-        type_dispatch! { u8, u16, u32, u64, i8, i16, i32, i64, f32 }
+    fn __getstate__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        dispatch!(self, py, |cpa_processor: &CoreCpaProcessor<T>| {
+            serialize_state(py, cpa_processor)
+        })
+    }
 
-        unreachable!()
+    fn __setstate__(&mut self, py: Python, state: &[u8]) -> PyResult<()> {
+        dispatch!(self, py, |cpa_processor: &mut CoreCpaProcessor<T>| {
+            let state: CoreCpaProcessor<T> = deserialize_state(state)?;
+            check_state_param("num_samples", state.num_samples(), self.num_samples)?;
+            check_state_param("guess_range", state.guess_range(), self.guess_range)?;
+            *cpa_processor = state;
+            Ok(())
+        })
     }
 
-    fn finalize<'py>(&self, leakage_model: &Bound<'py, PyFunction>) -> Cpa {
+    fn finalize<'py>(&self, leakage_model: &Bound<'py, PyAny>) -> PyResult<Cpa> {
         let py = leakage_model.py();
 
-        macro_rules! type_dispatch {
-            ($($ty:ty),*) => {
-                $(
-                    if self.dtype.bind(py).is_equiv_to(&::numpy::dtype::<$ty>(py)) {
-                        let cpa_processor =
-                            self
-                                .inner
-                                .downcast_ref::<::muscat::distinguishers::cpa::CpaProcessor<$ty>>()
-                                .unwrap();
-
-                        return Cpa(
-                            cpa_processor
-                                .finalize(|plaintext, guess| leakage_model.call((plaintext, guess), None).unwrap().extract::<usize>().unwrap())
-                        );
-                    }
-                )*
-            };
-        }
-
-        type_dispatch! { u8, u16, u32, u64, i8, i16, i32, i64, f32 }
-
-        unreachable!()
+        // Leakages of all plaintexts lower than guess_range are needed to compute the correlations
+        let modeled_leakages = tabulate::<usize>(
+            leakage_model,
+            self.guess_range,
+            self.guess_range,
+            0..self.guess_range,
+        )?;
+
+        dispatch!(self, py, |cpa_processor: &CoreCpaProcessor<T>| Ok(Cpa(
+            cpa_processor.finalize(|plaintext, guess| modeled_leakages[[plaintext, guess]])
+        )))
     }
 }
 
 #[pyfunction]
+#[pyo3(signature = (
+    traces,
+    plaintexts,
+    guess_range,
+    target_byte,
+    leakage_model,
+    batch_size,
+    progress=None,
+))]
 pub fn compute_cpa_normal<'py>(
     traces: &Bound<'py, PyUntypedArray>,
     plaintexts: &Bound<'py, PyArray2<usize>>,
     guess_range: usize,
     target_byte: usize,
-    leakage_model: &Bound<'py, PyFunction>,
+    leakage_model: &Bound<'py, PyAny>,
     batch_size: usize,
+    progress: Option<&Bound<'py, PyAny>>,
 ) -> PyResult<Cpa> {
-    let mut modeled_leakages = Array2::zeros((guess_range, 256));
-    for guess in 0..guess_range {
-        for plaintext in 0..256 {
-            modeled_leakages[[guess, plaintext]] = leakage_model
-                .call((plaintext, guess), None)
-                .unwrap()
-                .extract::<usize>()
-                .unwrap();
-        }
-    }
+    let py = traces.py();
 
     if traces.ndim() != 2 {
         return Err(PyTypeError::new_err(format!(
@@ -228,21 +349,39 @@ pub fn compute_cpa_normal<'py>(
         )));
     }
 
+    let plaintexts = plaintexts.readonly();
+    let plaintexts = plaintexts.as_array();
+    check_num_traces(traces, plaintexts.shape()[0], batch_size)?;
+    check_target_byte(target_byte, plaintexts.shape()[1])?;
+
+    let target_plaintexts = plaintexts.column(target_byte);
+    let modeled_leakages = tabulate::<usize>(
+        leakage_model,
+        plaintext_range(target_plaintexts),
+        guess_range,
+        target_plaintexts.iter().copied(),
+    )?;
+
     let sample_dtype = traces.dtype();
 
     macro_rules! type_dispatch {
         ($($ty:ty),*) => {
             $(
                 if sample_dtype.is_equiv_to(&dtype::<$ty>(traces.py())) {
-                    let traces = traces.cast::<PyArray2<$ty>>()?;
-
-                    return Ok(Cpa(muscat::distinguishers::cpa_normal::cpa(
-                        traces.readonly().as_array(),
-                        plaintexts.readonly().as_array(),
-                        guess_range,
-                        |plaintext, guess| modeled_leakages[[guess, plaintext[target_byte]]],
-                        batch_size,
-                    )));
+                    let traces = traces.cast::<PyArray2<$ty>>()?.readonly();
+                    let traces = traces.as_array();
+
+                    return run_detached(py, progress, |progress| {
+                        cpa_normal_with_progress(
+                            traces,
+                            plaintexts,
+                            guess_range,
+                            |plaintext, guess| modeled_leakages[[plaintext[target_byte], guess]],
+                            batch_size,
+                            progress,
+                        )
+                    })
+                    .map(Cpa);
                 }
             )*
         };
@@ -258,6 +397,8 @@ pub fn compute_cpa_normal<'py>(
 #[pyclass]
 pub struct CpaNormalProcessor {
     inner: Box<dyn Any + Send + Sync>,
+    num_samples: usize,
+    guess_range: usize,
     dtype: Py<PyArrayDescr>,
 }
 
@@ -278,6 +419,8 @@ impl CpaNormalProcessor {
                             inner: Box::new(
                                 ::muscat::distinguishers::cpa_normal::CpaProcessor::<$ty>::new(num_samples, batch_size, guess_range)
                             ),
+                            num_samples,
+                            guess_range,
                             dtype: dtype.clone().into(),
                         });
                     }
@@ -290,28 +433,67 @@ impl CpaNormalProcessor {
         Err(PyTypeError::new_err(format!("Unsupported dtype {dtype}")))
     }
 
+    /// Update the processor with a batch of traces.
+    ///
+    /// If `target_byte` is given, `leakage_model` is a leakage model of the target plaintext
+    /// byte. Otherwise, it must be a callable taking the whole plaintext and a guess.
+    #[pyo3(signature = (trace_batch, plaintext_batch, leakage_model, target_byte=None))]
     fn batch_update<'py>(
         &mut self,
         trace_batch: &Bound<'py, PyUntypedArray>,
         plaintext_batch: &Bound<'py, PyArray2<usize>>,
-        leakage_model: &Bound<'py, PyFunction>,
+        leakage_model: &Bound<'py, PyAny>,
+        target_byte: Option<usize>,
     ) -> PyResult<()> {
         let py = trace_batch.py();
+        check_array(py, "trace_batch", trace_batch, 2, &self.dtype)?;
+        check_trace_length("trace_batch", trace_batch, self.num_samples)?;
+
+        let plaintext_batch = plaintext_batch.readonly();
+        let plaintext_batch = plaintext_batch.as_array();
+        check_num_traces(trace_batch, plaintext_batch.shape()[0], 1)?;
+
+        let (plaintext_batch, modeled_leakages) = match target_byte {
+            Some(target_byte) => {
+                check_target_byte(target_byte, plaintext_batch.shape()[1])?;
+
+                let target_plaintexts = plaintext_batch.column(target_byte);
+                let modeled_leakages = tabulate::<usize>(
+                    leakage_model,
+                    plaintext_range(target_plaintexts),
+                    self.guess_range,
+                    target_plaintexts.iter().copied(),
+                )?;
+
+                (
+                    target_plaintexts.insert_axis(Axis(1)).to_owned(),
+                    modeled_leakages,
+                )
+            }
+            None => {
+                if !leakage_model.is_callable() {
+                    return Err(PyValueError::new_err(
+                        "target_byte is required for leakage models that are not callable.",
+                    ));
+                }
 
-        if trace_batch.ndim() != 2 {
-            return Err(PyTypeError::new_err(format!(
-                "Invalid trace batch ndim: {}. trace_batch ndim should be equal to 2.",
-                trace_batch.ndim()
-            )));
-        }
+                // Call the leakage model once per trace and guess, then let the processor look
+                // the leakages up by trace index
+                let mut modeled_leakages =
+                    Array2::zeros((plaintext_batch.shape()[0], self.guess_range));
+                for (i, plaintext) in plaintext_batch.rows().into_iter().enumerate() {
+                    let plaintext = plaintext.to_pyarray(py);
+                    for guess in 0..self.guess_range {
+                        modeled_leakages[[i, guess]] = leakage_model
+                            .call1((&plaintext, guess))?
+                            .extract::<usize>()?;
+                    }
+                }
 
-        if !trace_batch.dtype().is_equiv_to(self.dtype.bind(py)) {
-            return Err(PyTypeError::new_err(format!(
-                "Invalid trace batch dtype: {}. trace_batch dtype should be {}.",
-                trace_batch.dtype(),
-                self.dtype
-            )));
-        }
+                let indices = Array2::from_shape_fn((plaintext_batch.shape()[0], 1), |(i, _)| i);
+                (indices, modeled_leakages)
+            }
+        };
 
         macro_rules! type_dispatch {
             ($($ty:ty),*) => {
@@ -327,8 +509,8 @@ impl CpaNormalProcessor {
 
                         cpa_processor.batch_update(
                             trace_batch.readonly().as_array(),
-                            plaintext_batch.readonly().as_array(),
-                            &|plaintext: ArrayView1<usize>, guess| leakage_model.call((plaintext.to_pyarray(py), guess), None).unwrap().extract::<usize>().unwrap()
+                            plaintext_batch.view(),
+                            |plaintext: ArrayView1<usize>, guess| modeled_leakages[[plaintext[0], guess]],
                         );
 
                         return Ok(());
@@ -388,28 +570,23 @@ impl Dpa {
 }
 
 #[pyfunction]
+#[pyo3(signature = (
+    traces,
+    plaintexts,
+    guess_range,
+    selection_function,
+    batch_size,
+    progress=None,
+))]
 pub fn compute_dpa<'py>(
     traces: &Bound<'py, PyUntypedArray>,
     plaintexts: &Bound<'py, PyArray1<usize>>,
     guess_range: usize,
-    selection_function: &Bound<'py, PyFunction>,
+    selection_function: &Bound<'py, PyAny>,
     batch_size: usize,
+    progress: Option<&Bound<'py, PyAny>>,
 ) -> PyResult<Dpa> {
-    let mut selections = Array2::zeros((256, guess_range));
-    for p in 0..selections.shape()[0] {
-        for guess in 0..selections.shape()[1] {
-            if selection_function
-                .call((p, guess), None)
-                .unwrap()
-                .extract::<bool>()
-                .unwrap()
-            {
-                selections[[p, guess]] = 1;
-            } else {
-                selections[[p, guess]] = 0;
-            }
-        }
-    }
+    let py = traces.py();
 
     if traces.ndim() != 2 {
         return Err(PyTypeError::new_err(format!(
@@ -418,21 +595,37 @@ pub fn compute_dpa<'py>(
         )));
     }
 
+    let plaintexts = plaintexts.readonly();
+    let plaintexts = plaintexts.as_array();
+    check_num_traces(traces, plaintexts.len(), batch_size)?;
+
+    let selections = tabulate::<bool>(
+        selection_function,
+        plaintext_range(plaintexts),
+        guess_range,
+        plaintexts.iter().copied(),
+    )?;
+
     let sample_dtype = traces.dtype();
 
     macro_rules! type_dispatch {
         ($($ty:ty),*) => {
             $(
                 if sample_dtype.is_equiv_to(&dtype::<$ty>(traces.py())) {
-                    let traces = traces.cast::<PyArray2<$ty>>()?;
-
-                    return Ok(Dpa(muscat::distinguishers::dpa::dpa(
-                        traces.readonly().as_array(),
-                        plaintexts.readonly().as_array(),
-                        guess_range,
-                        |plaintext, guess| selections[[plaintext, guess]] == 1,
-                        batch_size,
-                    )));
+                    let traces = traces.cast::<PyArray2<$ty>>()?.readonly();
+                    let traces = traces.as_array();
+
+                    return run_detached(py, progress, |progress| {
+                        dpa_with_progress(
+                            traces,
+                            plaintexts,
+                            guess_range,
+                            |plaintext, guess| selections[[plaintext, guess]],
+                            batch_size,
+                            progress,
+                        )
+                    })
+                    .map(Dpa);
                 }
             )*
         };
@@ -445,9 +638,14 @@ pub fn compute_dpa<'py>(
     )))
 }
 
-#[pyclass]
+/// DPA processor with plaintexts as metadata.
+type UsizeDpaProcessor<T> = CoreDpaProcessor<T, usize>;
+
+#[pyclass(module = "muscatpy.distinguishers")]
 pub struct DpaProcessor {
     inner: Box<dyn Any + Send + Sync>,
+    num_samples: usize,
+    guess_range: usize,
     dtype: Py<PyArrayDescr>,
 }
 
@@ -467,6 +665,8 @@ impl DpaProcessor {
                             inner: Box::new(
                                 ::muscat::distinguishers::dpa::DpaProcessor::<$ty, usize>::new(num_samples, guess_range)
                             ),
+                            num_samples,
+                            guess_range,
                             dtype: dtype.clone().into(),
                         });
                     }
@@ -483,52 +683,94 @@ impl DpaProcessor {
         &mut self,
         trace_batch: &Bound<'py, PyUntypedArray>,
         plaintext_batch: &Bound<'py, PyArray1<usize>>,
-        selection_function: &Bound<'py, PyFunction>,
+        selection_function: &Bound<'py, PyAny>,
     ) -> PyResult<()> {
         let py = trace_batch.py();
+        check_array(py, "trace_batch", trace_batch, 2, &self.dtype)?;
+        check_trace_length("trace_batch", trace_batch, self.num_samples)?;
+
+        let plaintext_batch = plaintext_batch.readonly();
+        let plaintext_batch = plaintext_batch.as_array();
+        check_num_traces(trace_batch, plaintext_batch.len(), 1)?;
+
+        let selections = tabulate::<bool>(
+            selection_function,
+            plaintext_range(plaintext_batch),
+            self.guess_range,
+            plaintext_batch.iter().copied(),
+        )?;
+
+        dispatch!(self, py, |dpa_processor: &mut UsizeDpaProcessor<T>| {
+            dpa_processor.batch_update(
+                trace_batch.cast::<PyArray2<T>>()?.readonly().as_array(),
+                plaintext_batch,
+                &|plaintext, guess| selections[[plaintext, guess]],
+            );
+            Ok(())
+        })
+    }
 
-        if trace_batch.ndim() != 2 {
-            return Err(PyTypeError::new_err(format!(
-                "Invalid trace batch ndim: {}. trace_batch ndim should be equal to 2.",
-                trace_batch.ndim()
-            )));
-        }
-
-        if !trace_batch.dtype().is_equiv_to(self.dtype.bind(py)) {
-            return Err(PyTypeError::new_err(format!(
-                "Invalid trace batch dtype: {}. trace_batch dtype should be {}.",
-                trace_batch.dtype(),
-                self.dtype
-            )));
-        }
-
-        macro_rules! type_dispatch {
-            ($($ty:ty),*) => {
-                $(
-                    if self.dtype.bind(py).is_equiv_to(&::numpy::dtype::<$ty>(py)) {
-                        let dpa_processor =
-                            self
-                                .inner
-                                .downcast_mut::<::muscat::distinguishers::dpa::DpaProcessor<$ty, usize>>()
-                                .unwrap();
-
-                        let trace_batch = trace_batch.cast::<PyArray2<$ty>>()?;
+    fn update<'py>(
+        &mut self,
+        trace: &Bound<'py, PyUntypedArray>,
+        plaintext: usize,
+        selection_function: &Bound<'py, PyAny>,
+    ) -> PyResult<()> {
+        let py = trace.py();
+        check_array(py, "trace", trace, 1, &self.dtype)?;
+        check_trace_length("trace", trace, self.num_samples)?;
+
+        let selections = tabulate::<bool>(
+            selection_function,
+            plaintext + 1,
+            self.guess_range,
+            [plaintext],
+        )?;
+
+        dispatch!(self, py, |dpa_processor: &mut UsizeDpaProcessor<T>| {
+            dpa_processor.update(
+                trace.cast::<PyArray1<T>>()?.readonly().as_array(),
+                plaintext,
+                |plaintext, guess| selections[[plaintext, guess]],
+            );
+            Ok(())
+        })
+    }
 
-                        dpa_processor.batch_update(
-                            trace_batch.readonly().as_array(),
-                            plaintext_batch.readonly().as_array(),
-                            &|plaintext, guess| selection_function.call((plaintext, guess), None).unwrap().extract::<bool>().unwrap()
-                        );
+    fn combine(&self, py: Python, other: &Self) -> PyResult<Self> {
+        check_same_dtype(py, &self.dtype, &other.dtype)?;
+        check_same_param("num_samples", self.num_samples, other.num_samples)?;
+        check_same_param("guess_range", self.guess_range, other.guess_range)?;
+
+        dispatch!(self, py, |dpa_processor: &UsizeDpaProcessor<T>| {
+            let other = other.inner.downcast_ref::<UsizeDpaProcessor<T>>().unwrap();
+            Ok(Self {
+                inner: Box::new(dpa_processor.clone().combine(other.clone())),
+                num_samples: self.num_samples,
+                guess_range: self.guess_range,
+                dtype: self.dtype.clone_ref(py),
+            })
+        })
+    }
 
-                        return Ok(());
-                    }
-                )*
-            };
-        }
+    fn __getnewargs__(&self, py: Python) -> (usize, usize, Py<PyArrayDescr>) {
+        (self.num_samples, self.guess_range, self.dtype.clone_ref(py))
+    }
 
-        type_dispatch! { u8, u16, u32, u64, i8, i16, i32, i64, f32 }
+    fn __getstate__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        dispatch!(self, py, |dpa_processor: &UsizeDpaProcessor<T>| {
+            serialize_state(py, dpa_processor)
+        })
+    }
 
-        unreachable!()
+    fn __setstate__(&mut self, py: Python, state: &[u8]) -> PyResult<()> {
+        dispatch!(self, py, |dpa_processor: &mut UsizeDpaProcessor<T>| {
+            let state: UsizeDpaProcessor<T> = deserialize_state(state)?;
+            check_state_param("num_samples", state.num_samples(), self.num_samples)?;
+            check_state_param("guess_range", state.guess_range(), self.guess_range)?;
+            *dpa_processor = state;
+            Ok(())
+        })
     }
 
     fn finalize<'py>(&self, py: Python<'py>) -> Dpa {
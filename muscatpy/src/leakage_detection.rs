@@ -1,28 +1,65 @@
+use std::any::Any;
+
+use muscat::leakage_detection::{
+    NicvProcessor, SnrProcessor, TTestProcessor, nicv_with_progress, snr_with_progress,
+    ttest_with_progress,
+};
 use numpy::{
-    IntoPyArray, PyArrayDescrMethods, PyArrayMethods, PyUntypedArray, PyUntypedArrayMethods,
+    IntoPyArray, PyArrayDescr, PyArrayDescrMethods, PyArrayMethods, PyUntypedArray,
+    PyUntypedArrayMethods,
     array::{PyArray1, PyArray2},
     dtype,
     ndarray::Array1,
 };
-use pyo3::exceptions::PyTypeError;
+use pyo3::exceptions::{PyTypeError, PyValueError};
 use pyo3::prelude::*;
-use pyo3::types::PyFunction;
+use pyo3::types::{PyBytes, PyFunction};
+
+use crate::{
+    check_array, check_same_dtype, check_same_param, check_state_param, check_trace_length,
+    deserialize_state, run_detached, serialize_state,
+};
+
+/// Check that `batch_size` is not zero.
+fn check_batch_size(batch_size: usize) -> PyResult<()> {
+    if batch_size == 0 {
+        return Err(PyValueError::new_err("batch_size should not be zero."));
+    }
+
+    Ok(())
+}
+
+/// Call `get_class` for each of the `num_traces` traces, checking that classes are lower than
+/// `classes`.
+fn trace_classes(
+    get_class: &Bound<PyFunction>,
+    num_traces: usize,
+    classes: usize,
+) -> PyResult<Array1<usize>> {
+    (0..num_traces)
+        .map(|i| {
+            let class = get_class.call1((i,))?.extract::<usize>()?;
+            if class >= classes {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid class of trace {i}: {class}. Classes should be lower than {classes}."
+                )));
+            }
+
+            Ok(class)
+        })
+        .collect()
+}
 
 #[pyfunction]
+#[pyo3(signature = (traces, classes, get_class, batch_size, progress=None))]
 pub fn compute_snr<'py>(
     traces: &Bound<'py, PyUntypedArray>,
     classes: usize,
     get_class: &Bound<'py, PyFunction>,
     batch_size: usize,
+    progress: Option<&Bound<'py, PyAny>>,
 ) -> PyResult<Bound<'py, PyArray1<f32>>> {
-    let mut traces_class = Array1::zeros(traces.shape()[0]);
-    for i in 0..traces_class.shape()[0] {
-        traces_class[i] = get_class
-            .call((i,), None)
-            .unwrap()
-            .extract::<usize>()
-            .unwrap();
-    }
+    let py = traces.py();
 
     if traces.ndim() != 2 {
         return Err(PyTypeError::new_err(format!(
@@ -31,20 +68,23 @@ pub fn compute_snr<'py>(
         )));
     }
 
+    check_batch_size(batch_size)?;
+    let traces_class = trace_classes(get_class, traces.shape()[0], classes)?;
+
     let sample_dtype = traces.dtype();
 
     macro_rules! type_dispatch {
         ($($ty:ty),*) => {
             $(
                 if sample_dtype.is_equiv_to(&dtype::<$ty>(traces.py())) {
-                    let traces = traces.cast::<PyArray2<$ty>>()?;
-
-                    return Ok(muscat::leakage_detection::snr(
-                        traces.readonly().as_array(),
-                        classes,
-                        |i| traces_class[i],
-                        batch_size,
-                    ).into_pyarray(traces.py()));
+                    let traces = traces.cast::<PyArray2<$ty>>()?.readonly();
+                    let traces = traces.as_array();
+
+                    let snr = run_detached(py, progress, |progress| {
+                        snr_with_progress(traces, classes, |i| traces_class[i], batch_size, progress)
+                    })?;
+
+                    return Ok(snr.into_pyarray(py));
                 }
             )*
         };
@@ -58,20 +98,15 @@ pub fn compute_snr<'py>(
 }
 
 #[pyfunction]
+#[pyo3(signature = (traces, classes, get_class, batch_size, progress=None))]
 pub fn compute_nicv<'py>(
     traces: &Bound<'py, PyUntypedArray>,
     classes: usize,
     get_class: &Bound<'py, PyFunction>,
     batch_size: usize,
+    progress: Option<&Bound<'py, PyAny>>,
 ) -> PyResult<Bound<'py, PyArray1<f32>>> {
-    let mut traces_class = Array1::zeros(traces.shape()[0]);
-    for i in 0..traces_class.shape()[0] {
-        traces_class[i] = get_class
-            .call((i,), None)
-            .unwrap()
-            .extract::<usize>()
-            .unwrap();
-    }
+    let py = traces.py();
 
     if traces.ndim() != 2 {
         return Err(PyTypeError::new_err(format!(
@@ -80,20 +115,23 @@ pub fn compute_nicv<'py>(
         )));
     }
 
+    check_batch_size(batch_size)?;
+    let traces_class = trace_classes(get_class, traces.shape()[0], classes)?;
+
     let sample_dtype = traces.dtype();
 
     macro_rules! type_dispatch {
         ($($ty:ty),*) => {
             $(
                 if sample_dtype.is_equiv_to(&dtype::<$ty>(traces.py())) {
-                    let traces = traces.cast::<PyArray2<$ty>>()?;
-
-                    return Ok(muscat::leakage_detection::nicv(
-                        traces.readonly().as_array(),
-                        classes,
-                        |i| traces_class[i],
-                        batch_size,
-                    ).into_pyarray(traces.py()));
+                    let traces = traces.cast::<PyArray2<$ty>>()?.readonly();
+                    let traces = traces.as_array();
+
+                    let nicv = run_detached(py, progress, |progress| {
+                        nicv_with_progress(traces, classes, |i| traces_class[i], batch_size, progress)
+                    })?;
+
+                    return Ok(nicv.into_pyarray(py));
                 }
             )*
         };
@@ -107,11 +145,15 @@ pub fn compute_nicv<'py>(
 }
 
 #[pyfunction]
+#[pyo3(signature = (traces, trace_classes, batch_size, progress=None))]
 pub fn compute_ttest<'py>(
     traces: &Bound<'py, PyUntypedArray>,
     trace_classes: &Bound<'py, PyArray1<bool>>,
     batch_size: usize,
+    progress: Option<&Bound<'py, PyAny>>,
 ) -> PyResult<Bound<'py, PyArray1<f32>>> {
+    let py = traces.py();
+
     if traces.ndim() != 2 {
         return Err(PyTypeError::new_err(format!(
             "Invalid traces ndim: {}. traces ndim should be equal to 2.",
@@ -119,19 +161,31 @@ pub fn compute_ttest<'py>(
         )));
     }
 
+    let trace_classes = trace_classes.readonly();
+    let trace_classes = trace_classes.as_array();
+    if trace_classes.len() != traces.shape()[0] {
+        return Err(PyValueError::new_err(format!(
+            "Invalid number of trace classes: {}. It should be equal to the number of traces {}.",
+            trace_classes.len(),
+            traces.shape()[0]
+        )));
+    }
+    check_batch_size(batch_size)?;
+
     let sample_dtype = traces.dtype();
 
     macro_rules! type_dispatch {
         ($($ty:ty),*) => {
             $(
                 if sample_dtype.is_equiv_to(&dtype::<$ty>(traces.py())) {
-                    let traces = traces.cast::<PyArray2<$ty>>()?;
+                    let traces = traces.cast::<PyArray2<$ty>>()?.readonly();
+                    let traces = traces.as_array();
 
-                    return Ok(muscat::leakage_detection::ttest(
-                        traces.readonly().as_array(),
-                        trace_classes.readonly().as_array(),
-                        batch_size,
-                    ).into_pyarray(traces.py()));
+                    let ttest = run_detached(py, progress, |progress| {
+                        ttest_with_progress(traces, trace_classes, batch_size, progress)
+                    })?;
+
+                    return Ok(ttest.into_pyarray(py));
                 }
             )*
         };
@@ -144,11 +198,483 @@ pub fn compute_ttest<'py>(
     )))
 }
 
+#[pyclass(name = "SnrProcessor", module = "muscatpy.leakage_detection")]
+struct PySnrProcessor {
+    inner: Box<dyn Any + Send + Sync>,
+    trace_length: usize,
+    num_classes: usize,
+    dtype: Py<PyArrayDescr>,
+}
+
+#[pymethods]
+impl PySnrProcessor {
+    #[new]
+    #[pyo3(signature = (trace_length, num_classes, dtype, classes_var=false))]
+    fn new<'py>(
+        trace_length: usize,
+        num_classes: usize,
+        dtype: &Bound<'py, PyArrayDescr>,
+        classes_var: bool,
+    ) -> PyResult<Self> {
+        macro_rules! type_dispatch {
+            ($($ty:ty),*) => {
+                $(
+                    if dtype.is_equiv_to(&::numpy::dtype::<$ty>(dtype.py())) {
+                        let processor = if classes_var {
+                            SnrProcessor::<$ty>::with_classes_var(trace_length, num_classes)
+                        } else {
+                            SnrProcessor::<$ty>::new(trace_length, num_classes)
+                        };
+                        return Ok(Self {
+                            inner: Box::new(processor),
+                            trace_length,
+                            num_classes,
+                            dtype: dtype.clone().into(),
+                        });
+                    }
+                )*
+            };
+        }
+
+        type_dispatch! { u8, u16, u32, u64, i8, i16, i32, i64, f32 }
+
+        Err(PyTypeError::new_err(format!("Unsupported dtype {dtype}")))
+    }
+
+    fn update<'py>(&mut self, trace: &Bound<'py, PyUntypedArray>, class: usize) -> PyResult<()> {
+        let py = trace.py();
+        check_array(py, "trace", trace, 1, &self.dtype)?;
+        check_trace_length("trace", trace, self.trace_length)?;
+        self.check_class(class)?;
+
+        dispatch!(self, py, |processor: &mut SnrProcessor<T>| {
+            processor.process(trace.cast::<PyArray1<T>>()?.readonly().as_array(), class);
+            Ok(())
+        })
+    }
+
+    fn batch_update<'py>(
+        &mut self,
+        trace_batch: &Bound<'py, PyUntypedArray>,
+        class_batch: &Bound<'py, PyArray1<usize>>,
+    ) -> PyResult<()> {
+        let py = trace_batch.py();
+        check_array(py, "trace_batch", trace_batch, 2, &self.dtype)?;
+        check_trace_length("trace_batch", trace_batch, self.trace_length)?;
+        let class_batch = class_batch.readonly();
+        let class_batch = class_batch.as_array();
+        if class_batch.len() != trace_batch.shape()[0] {
+            return Err(PyValueError::new_err(format!(
+                "Invalid class_batch length: {}. class_batch length should be equal to the number of traces.",
+                class_batch.len()
+            )));
+        }
+        for &class in class_batch.iter() {
+            self.check_class(class)?;
+        }
+
+        dispatch!(self, py, |processor: &mut SnrProcessor<T>| {
+            let trace_batch = trace_batch.cast::<PyArray2<T>>()?.readonly();
+            for (trace, &class) in trace_batch.as_array().rows().into_iter().zip(class_batch) {
+                processor.process(trace, class);
+            }
+            Ok(())
+        })
+    }
+
+    fn combine(&self, py: Python, other: &Self) -> PyResult<Self> {
+        check_same_dtype(py, &self.dtype, &other.dtype)?;
+        check_same_param("trace_length", self.trace_length, other.trace_length)?;
+        check_same_param("num_classes", self.num_classes, other.num_classes)?;
+
+        dispatch!(self, py, |processor: &SnrProcessor<T>| {
+            let other = other.inner.downcast_ref::<SnrProcessor<T>>().unwrap();
+            check_same_param(
+                "classes_var",
+                processor.classes_var().is_some(),
+                other.classes_var().is_some(),
+            )?;
+            Ok(Self {
+                inner: Box::new(processor.clone().combine(other.clone())),
+                trace_length: self.trace_length,
+                num_classes: self.num_classes,
+                dtype: self.dtype.clone_ref(py),
+            })
+        })
+    }
+
+    fn snr<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f32>> {
+        dispatch!(self, py, |processor: &SnrProcessor<T>| processor
+            .snr()
+            .into_pyarray(py))
+    }
+
+    fn classes_mean<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f32>> {
+        dispatch!(self, py, |processor: &SnrProcessor<T>| processor
+            .classes_mean()
+            .into_pyarray(py))
+    }
+
+    fn classes_var<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyArray2<f32>>> {
+        dispatch!(self, py, |processor: &SnrProcessor<T>| processor
+            .classes_var()
+            .map(|classes_var| classes_var.into_pyarray(py)))
+    }
+
+    fn classes_count<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<usize>> {
+        dispatch!(self, py, |processor: &SnrProcessor<T>| processor
+            .classes_count()
+            .to_owned()
+            .into_pyarray(py))
+    }
+
+    #[getter]
+    fn trace_length(&self) -> usize {
+        self.trace_length
+    }
+
+    #[getter]
+    fn num_classes(&self) -> usize {
+        self.num_classes
+    }
+
+    fn __getnewargs__(&self, py: Python) -> (usize, usize, Py<PyArrayDescr>) {
+        (
+            self.trace_length,
+            self.num_classes,
+            self.dtype.clone_ref(py),
+        )
+    }
+
+    fn __getstate__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        dispatch!(self, py, |processor: &SnrProcessor<T>| serialize_state(
+            py, processor
+        ))
+    }
+
+    fn __setstate__(&mut self, py: Python, state: &[u8]) -> PyResult<()> {
+        dispatch!(self, py, |processor: &mut SnrProcessor<T>| {
+            let state: SnrProcessor<T> = deserialize_state(state)?;
+            check_state_param("trace_length", state.trace_length(), self.trace_length)?;
+            check_state_param("num_classes", state.num_classes(), self.num_classes)?;
+            *processor = state;
+            Ok(())
+        })
+    }
+}
+
+impl PySnrProcessor {
+    fn check_class(&self, class: usize) -> PyResult<()> {
+        if class >= self.num_classes {
+            return Err(PyValueError::new_err(format!(
+                "Invalid class: {class}. class should be lower than {}.",
+                self.num_classes
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[pyclass(name = "NicvProcessor", module = "muscatpy.leakage_detection")]
+struct PyNicvProcessor {
+    inner: Box<dyn Any + Send + Sync>,
+    trace_length: usize,
+    num_classes: usize,
+    dtype: Py<PyArrayDescr>,
+}
+
+#[pymethods]
+impl PyNicvProcessor {
+    #[new]
+    #[pyo3(signature = (trace_length, num_classes, dtype, classes_var=false))]
+    fn new<'py>(
+        trace_length: usize,
+        num_classes: usize,
+        dtype: &Bound<'py, PyArrayDescr>,
+        classes_var: bool,
+    ) -> PyResult<Self> {
+        macro_rules! type_dispatch {
+            ($($ty:ty),*) => {
+                $(
+                    if dtype.is_equiv_to(&::numpy::dtype::<$ty>(dtype.py())) {
+                        let processor = if classes_var {
+                            NicvProcessor::<$ty>::with_classes_var(trace_length, num_classes)
+                        } else {
+                            NicvProcessor::<$ty>::new(trace_length, num_classes)
+                        };
+                        return Ok(Self {
+                            inner: Box::new(processor),
+                            trace_length,
+                            num_classes,
+                            dtype: dtype.clone().into(),
+                        });
+                    }
+                )*
+            };
+        }
+
+        type_dispatch! { u8, u16, u32, u64, i8, i16, i32, i64, f32 }
+
+        Err(PyTypeError::new_err(format!("Unsupported dtype {dtype}")))
+    }
+
+    fn update<'py>(&mut self, trace: &Bound<'py, PyUntypedArray>, class: usize) -> PyResult<()> {
+        let py = trace.py();
+        check_array(py, "trace", trace, 1, &self.dtype)?;
+        check_trace_length("trace", trace, self.trace_length)?;
+        self.check_class(class)?;
+
+        dispatch!(self, py, |processor: &mut NicvProcessor<T>| {
+            processor.process(trace.cast::<PyArray1<T>>()?.readonly().as_array(), class);
+            Ok(())
+        })
+    }
+
+    fn batch_update<'py>(
+        &mut self,
+        trace_batch: &Bound<'py, PyUntypedArray>,
+        class_batch: &Bound<'py, PyArray1<usize>>,
+    ) -> PyResult<()> {
+        let py = trace_batch.py();
+        check_array(py, "trace_batch", trace_batch, 2, &self.dtype)?;
+        check_trace_length("trace_batch", trace_batch, self.trace_length)?;
+        let class_batch = class_batch.readonly();
+        let class_batch = class_batch.as_array();
+        if class_batch.len() != trace_batch.shape()[0] {
+            return Err(PyValueError::new_err(format!(
+                "Invalid class_batch length: {}. class_batch length should be equal to the number of traces.",
+                class_batch.len()
+            )));
+        }
+        for &class in class_batch.iter() {
+            self.check_class(class)?;
+        }
+
+        dispatch!(self, py, |processor: &mut NicvProcessor<T>| {
+            let trace_batch = trace_batch.cast::<PyArray2<T>>()?.readonly();
+            for (trace, &class) in trace_batch.as_array().rows().into_iter().zip(class_batch) {
+                processor.process(trace, class);
+            }
+            Ok(())
+        })
+    }
+
+    fn combine(&self, py: Python, other: &Self) -> PyResult<Self> {
+        check_same_dtype(py, &self.dtype, &other.dtype)?;
+        check_same_param("trace_length", self.trace_length, other.trace_length)?;
+        check_same_param("num_classes", self.num_classes, other.num_classes)?;
+
+        dispatch!(self, py, |processor: &NicvProcessor<T>| {
+            let other = other.inner.downcast_ref::<NicvProcessor<T>>().unwrap();
+            check_same_param(
+                "classes_var",
+                processor.classes_var().is_some(),
+                other.classes_var().is_some(),
+            )?;
+            Ok(Self {
+                inner: Box::new(processor.clone().combine(other.clone())),
+                trace_length: self.trace_length,
+                num_classes: self.num_classes,
+                dtype: self.dtype.clone_ref(py),
+            })
+        })
+    }
+
+    fn nicv<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f32>> {
+        dispatch!(self, py, |processor: &NicvProcessor<T>| processor
+            .nicv()
+            .into_pyarray(py))
+    }
+
+    fn classes_mean<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray2<f32>> {
+        dispatch!(self, py, |processor: &NicvProcessor<T>| processor
+            .classes_mean()
+            .into_pyarray(py))
+    }
+
+    fn classes_var<'py>(&self, py: Python<'py>) -> Option<Bound<'py, PyArray2<f32>>> {
+        dispatch!(self, py, |processor: &NicvProcessor<T>| processor
+            .classes_var()
+            .map(|classes_var| classes_var.into_pyarray(py)))
+    }
+
+    fn classes_count<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<usize>> {
+        dispatch!(self, py, |processor: &NicvProcessor<T>| processor
+            .classes_count()
+            .to_owned()
+            .into_pyarray(py))
+    }
+
+    #[getter]
+    fn trace_length(&self) -> usize {
+        self.trace_length
+    }
+
+    #[getter]
+    fn num_classes(&self) -> usize {
+        self.num_classes
+    }
+
+    fn __getnewargs__(&self, py: Python) -> (usize, usize, Py<PyArrayDescr>) {
+        (
+            self.trace_length,
+            self.num_classes,
+            self.dtype.clone_ref(py),
+        )
+    }
+
+    fn __getstate__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        dispatch!(self, py, |processor: &NicvProcessor<T>| serialize_state(
+            py, processor
+        ))
+    }
+
+    fn __setstate__(&mut self, py: Python, state: &[u8]) -> PyResult<()> {
+        dispatch!(self, py, |processor: &mut NicvProcessor<T>| {
+            let state: NicvProcessor<T> = deserialize_state(state)?;
+            check_state_param("trace_length", state.trace_length(), self.trace_length)?;
+            check_state_param("num_classes", state.num_classes(), self.num_classes)?;
+            *processor = state;
+            Ok(())
+        })
+    }
+}
+
+impl PyNicvProcessor {
+    fn check_class(&self, class: usize) -> PyResult<()> {
+        if class >= self.num_classes {
+            return Err(PyValueError::new_err(format!(
+                "Invalid class: {class}. class should be lower than {}.",
+                self.num_classes
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[pyclass(name = "TTestProcessor", module = "muscatpy.leakage_detection")]
+struct PyTTestProcessor {
+    inner: Box<dyn Any + Send + Sync>,
+    trace_length: usize,
+    dtype: Py<PyArrayDescr>,
+}
+
+#[pymethods]
+impl PyTTestProcessor {
+    #[new]
+    fn new<'py>(trace_length: usize, dtype: &Bound<'py, PyArrayDescr>) -> PyResult<Self> {
+        macro_rules! type_dispatch {
+            ($($ty:ty),*) => {
+                $(
+                    if dtype.is_equiv_to(&::numpy::dtype::<$ty>(dtype.py())) {
+                        return Ok(Self {
+                            inner: Box::new(TTestProcessor::<$ty>::new(trace_length)),
+                            trace_length,
+                            dtype: dtype.clone().into(),
+                        });
+                    }
+                )*
+            };
+        }
+
+        type_dispatch! { u8, u16, u32, u64, i8, i16, i32, i64, f32 }
+
+        Err(PyTypeError::new_err(format!("Unsupported dtype {dtype}")))
+    }
+
+    fn update<'py>(&mut self, trace: &Bound<'py, PyUntypedArray>, class: bool) -> PyResult<()> {
+        let py = trace.py();
+        check_array(py, "trace", trace, 1, &self.dtype)?;
+        check_trace_length("trace", trace, self.trace_length)?;
+
+        dispatch!(self, py, |processor: &mut TTestProcessor<T>| {
+            processor.process(trace.cast::<PyArray1<T>>()?.readonly().as_array(), class);
+            Ok(())
+        })
+    }
+
+    fn batch_update<'py>(
+        &mut self,
+        trace_batch: &Bound<'py, PyUntypedArray>,
+        class_batch: &Bound<'py, PyArray1<bool>>,
+    ) -> PyResult<()> {
+        let py = trace_batch.py();
+        check_array(py, "trace_batch", trace_batch, 2, &self.dtype)?;
+        check_trace_length("trace_batch", trace_batch, self.trace_length)?;
+        let class_batch = class_batch.readonly();
+        let class_batch = class_batch.as_array();
+        if class_batch.len() != trace_batch.shape()[0] {
+            return Err(PyValueError::new_err(format!(
+                "Invalid class_batch length: {}. class_batch length should be equal to the number of traces.",
+                class_batch.len()
+            )));
+        }
+
+        dispatch!(self, py, |processor: &mut TTestProcessor<T>| {
+            let trace_batch = trace_batch.cast::<PyArray2<T>>()?.readonly();
+            for (trace, &class) in trace_batch.as_array().rows().into_iter().zip(class_batch) {
+                processor.process(trace, class);
+            }
+            Ok(())
+        })
+    }
+
+    fn combine(&self, py: Python, other: &Self) -> PyResult<Self> {
+        check_same_dtype(py, &self.dtype, &other.dtype)?;
+        check_same_param("trace_length", self.trace_length, other.trace_length)?;
+
+        dispatch!(self, py, |processor: &TTestProcessor<T>| {
+            let other = other.inner.downcast_ref::<TTestProcessor<T>>().unwrap();
+            Ok(Self {
+                inner: Box::new(processor.clone().combine(other.clone())),
+                trace_length: self.trace_length,
+                dtype: self.dtype.clone_ref(py),
+            })
+        })
+    }
+
+    fn ttest<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f32>> {
+        dispatch!(self, py, |processor: &TTestProcessor<T>| processor
+            .ttest()
+            .into_pyarray(py))
+    }
+
+    #[getter]
+    fn trace_length(&self) -> usize {
+        self.trace_length
+    }
+
+    fn __getnewargs__(&self, py: Python) -> (usize, Py<PyArrayDescr>) {
+        (self.trace_length, self.dtype.clone_ref(py))
+    }
+
+    fn __getstate__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        dispatch!(self, py, |processor: &TTestProcessor<T>| serialize_state(
+            py, processor
+        ))
+    }
+
+    fn __setstate__(&mut self, py: Python, state: &[u8]) -> PyResult<()> {
+        dispatch!(self, py, |processor: &mut TTestProcessor<T>| {
+            let state: TTestProcessor<T> = deserialize_state(state)?;
+            check_state_param("trace_length", state.trace_length(), self.trace_length)?;
+            *processor = state;
+            Ok(())
+        })
+    }
+}
+
 #[pymodule]
 pub fn leakage_detection(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(compute_snr, m)?)?;
     m.add_function(wrap_pyfunction!(compute_nicv, m)?)?;
     m.add_function(wrap_pyfunction!(compute_ttest, m)?)?;
 
+    m.add_class::<PySnrProcessor>()?;
+    m.add_class::<PyNicvProcessor>()?;
+    m.add_class::<PyTTestProcessor>()?;
+
     Ok(())
 }
@@ -1,12 +1,324 @@
-use pyo3::prelude::*;
+use muscat::leakage_model::{
+    aes::{inv_sbox, sbox},
+    hw,
+};
+use numpy::{
+    Element, IntoPyArray, PyArray2, PyArrayDescrMethods, PyArrayMethods, PyUntypedArray,
+    PyUntypedArrayMethods,
+    ndarray::{Array2, s},
+};
+use pyo3::{
+    exceptions::{PyTypeError, PyValueError},
+    prelude::*,
+    types::PyString,
+};
 
 mod aes;
 
+/// A leakage model taking a plaintext (or ciphertext) byte and a key guess byte.
+type NamedLeakageModel = (&'static str, fn(u8, u8) -> usize);
+
+/// An AES operation on a byte, such as the S-Box.
+type NamedOperation = (&'static str, fn(u8) -> u8);
+
+/// Leakage models selectable by name.
+const LEAKAGE_MODELS: [NamedLeakageModel; 5] = [
+    ("aes_sbox", |plaintext, guess| {
+        sbox(plaintext ^ guess) as usize
+    }),
+    ("aes_sbox_hw", |plaintext, guess| {
+        hw(sbox(plaintext ^ guess) as usize)
+    }),
+    // Hamming distance between the S-Box input and output
+    ("aes_sbox_hd", |plaintext, guess| {
+        hw((sbox(plaintext ^ guess) ^ plaintext ^ guess) as usize)
+    }),
+    ("aes_inv_sbox_hw", |ciphertext, guess| {
+        hw(inv_sbox(ciphertext ^ guess) as usize)
+    }),
+    // Hamming distance between the last round S-Box input and the ciphertext byte it produces.
+    // Because of ShiftRows, this input is at another position of the state than the ciphertext
+    // byte, so this is not the distance between the last round state and the ciphertext.
+    ("aes_inv_sbox_hd", |ciphertext, guess| {
+        hw((inv_sbox(ciphertext ^ guess) ^ ciphertext) as usize)
+    }),
+];
+
+/// Selection functions selectable by name, of the form `aes_sbox_bit<i>` or `aes_inv_sbox_bit<i>`,
+/// selecting the `i`-th bit of the (inverse) S-Box output.
+const SELECTION_FUNCTIONS: [NamedOperation; 2] =
+    [("aes_sbox_bit", sbox), ("aes_inv_sbox_bit", inv_sbox)];
+
+/// Output of a leakage model, or of a selection function.
+pub(crate) trait ModelOutput: Element + Copy + Default {
+    /// Description of the model in error messages.
+    const DESCRIPTION: &'static str;
+
+    /// Returns the named model.
+    fn named(name: &str) -> Option<Box<dyn Fn(u8, u8) -> Self>>;
+
+    /// Returns the names of the named models.
+    fn names() -> Vec<String>;
+
+    /// Extract the output of a Python model.
+    fn extract(value: &Bound<PyAny>) -> PyResult<Self>;
+
+    /// Convert a lookup table given from Python.
+    fn lookup_table(table: &Bound<PyUntypedArray>) -> PyResult<Array2<Self>>;
+}
+
+impl ModelOutput for usize {
+    const DESCRIPTION: &'static str = "leakage model";
+
+    fn named(name: &str) -> Option<Box<dyn Fn(u8, u8) -> Self>> {
+        LEAKAGE_MODELS
+            .iter()
+            .find(|(model_name, _)| *model_name == name)
+            .map(|&(_, model)| Box::new(model) as Box<dyn Fn(u8, u8) -> Self>)
+    }
+
+    fn names() -> Vec<String> {
+        LEAKAGE_MODELS
+            .iter()
+            .map(|(name, _)| name.to_string())
+            .collect()
+    }
+
+    fn extract(value: &Bound<PyAny>) -> PyResult<Self> {
+        value.extract()
+    }
+
+    fn lookup_table(table: &Bound<PyUntypedArray>) -> PyResult<Array2<Self>> {
+        let py = table.py();
+
+        match table.dtype().kind() {
+            b'u' => {
+                let table = table.call_method1("astype", (numpy::dtype::<u64>(py),))?;
+                let table = table.cast::<PyArray2<u64>>()?.readonly();
+
+                Ok(table.as_array().mapv(|x| x as usize))
+            }
+            b'i' => {
+                let table = table.call_method1("astype", (numpy::dtype::<i64>(py),))?;
+                let table = table.cast::<PyArray2<i64>>()?.readonly();
+                if table.as_array().iter().any(|&x| x < 0) {
+                    return Err(PyValueError::new_err(
+                        "Invalid leakage model lookup table. Leakages should be non-negative.",
+                    ));
+                }
+
+                Ok(table.as_array().mapv(|x| x as usize))
+            }
+            _ => Err(PyTypeError::new_err(format!(
+                "Invalid leakage model lookup table dtype: {}. dtype should be an integer type.",
+                table.dtype()
+            ))),
+        }
+    }
+}
+
+impl ModelOutput for bool {
+    const DESCRIPTION: &'static str = "selection function";
+
+    fn named(name: &str) -> Option<Box<dyn Fn(u8, u8) -> Self>> {
+        SELECTION_FUNCTIONS.iter().find_map(|&(prefix, op)| {
+            let bit = name.strip_prefix(prefix)?.parse::<u32>().ok()?;
+            (bit < 8).then(|| {
+                Box::new(move |plaintext: u8, guess: u8| (op(plaintext ^ guess) >> bit) & 1 == 1)
+                    as Box<dyn Fn(u8, u8) -> Self>
+            })
+        })
+    }
+
+    fn names() -> Vec<String> {
+        SELECTION_FUNCTIONS
+            .iter()
+            .flat_map(|(prefix, _)| (0..8).map(move |bit| format!("{prefix}{bit}")))
+            .collect()
+    }
+
+    fn extract(value: &Bound<PyAny>) -> PyResult<Self> {
+        value.extract()
+    }
+
+    fn lookup_table(table: &Bound<PyUntypedArray>) -> PyResult<Array2<Self>> {
+        if table.dtype().kind() != b'b' {
+            return Err(PyTypeError::new_err(format!(
+                "Invalid selection function lookup table dtype: {}. dtype should be bool.",
+                table.dtype()
+            )));
+        }
+
+        Ok(table
+            .cast::<PyArray2<bool>>()?
+            .readonly()
+            .as_array()
+            .to_owned())
+    }
+}
+
+/// Tabulate a leakage model or a selection function given from Python, indexed by
+/// `[plaintext, guess]`.
+///
+/// `model` is either the name of a built-in model, a lookup table of shape at least
+/// `(plaintext_range, guess_range)`, or a callable taking a plaintext and a guess. Callables are
+/// only called for the given `plaintexts`, which must be lower than `plaintext_range`, and their
+/// exceptions are propagated.
+pub(crate) fn tabulate<V: ModelOutput>(
+    model: &Bound<PyAny>,
+    plaintext_range: usize,
+    guess_range: usize,
+    plaintexts: impl IntoIterator<Item = usize>,
+) -> PyResult<Array2<V>> {
+    if let Ok(name) = model.cast::<PyString>() {
+        let name = name.to_str()?;
+        let Some(model) = V::named(name) else {
+            return Err(PyValueError::new_err(format!(
+                "Unknown {} {name:?}. Available {}s are: {}.",
+                V::DESCRIPTION,
+                V::DESCRIPTION,
+                V::names().join(", ")
+            )));
+        };
+
+        if plaintext_range > 256 || guess_range > 256 {
+            return Err(PyValueError::new_err(format!(
+                "{name:?} {} only supports plaintexts and guesses lower than 256.",
+                V::DESCRIPTION
+            )));
+        }
+
+        return Ok(Array2::from_shape_fn(
+            (plaintext_range, guess_range),
+            |(plaintext, guess)| model(plaintext as u8, guess as u8),
+        ));
+    }
+
+    if let Ok(table) = model.cast::<PyUntypedArray>() {
+        if table.ndim() != 2 {
+            return Err(PyTypeError::new_err(format!(
+                "Invalid {} lookup table ndim: {}. ndim should be equal to 2.",
+                V::DESCRIPTION,
+                table.ndim()
+            )));
+        }
+
+        if table.shape()[0] < plaintext_range || table.shape()[1] < guess_range {
+            return Err(PyValueError::new_err(format!(
+                "Invalid {} lookup table shape: {:?}. shape should be at least ({plaintext_range}, {guess_range}).",
+                V::DESCRIPTION,
+                table.shape()
+            )));
+        }
+
+        let table = V::lookup_table(table)?;
+        return Ok(table.slice(s![..plaintext_range, ..guess_range]).to_owned());
+    }
+
+    if model.is_callable() {
+        let mut table = Array2::from_elem((plaintext_range, guess_range), V::default());
+        let mut tabulated = vec![false; plaintext_range];
+        for plaintext in plaintexts {
+            if tabulated[plaintext] {
+                continue;
+            }
+
+            for guess in 0..guess_range {
+                table[[plaintext, guess]] = V::extract(&model.call1((plaintext, guess))?)?;
+            }
+            tabulated[plaintext] = true;
+        }
+
+        return Ok(table);
+    }
+
+    Err(PyTypeError::new_err(format!(
+        "Invalid {}. It should be a name, a lookup table or a callable.",
+        V::DESCRIPTION
+    )))
+}
+
+/// Returns the names of the built-in leakage models.
+#[pyfunction]
+pub fn leakage_models() -> Vec<String> {
+    usize::names()
+}
+
+/// Returns the names of the built-in selection functions.
+#[pyfunction]
+pub fn selection_functions() -> Vec<String> {
+    bool::names()
+}
+
+/// Returns the lookup table of a built-in leakage model.
+#[pyfunction]
+#[pyo3(signature = (name, plaintext_range=256, guess_range=256))]
+pub fn leakage_table<'py>(
+    name: &Bound<'py, PyString>,
+    plaintext_range: usize,
+    guess_range: usize,
+) -> PyResult<Bound<'py, PyArray2<usize>>> {
+    let table = tabulate::<usize>(name, plaintext_range, guess_range, [])?;
+    Ok(table.into_pyarray(name.py()))
+}
+
+/// Returns the lookup table of a built-in selection function.
+#[pyfunction]
+#[pyo3(signature = (name, plaintext_range=256, guess_range=256))]
+pub fn selection_table<'py>(
+    name: &Bound<'py, PyString>,
+    plaintext_range: usize,
+    guess_range: usize,
+) -> PyResult<Bound<'py, PyArray2<bool>>> {
+    let table = tabulate::<bool>(name, plaintext_range, guess_range, [])?;
+    Ok(table.into_pyarray(name.py()))
+}
+
 #[pymodule]
 pub fn leakage_model(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(leakage_models, m)?)?;
+    m.add_function(wrap_pyfunction!(selection_functions, m)?)?;
+    m.add_function(wrap_pyfunction!(leakage_table, m)?)?;
+    m.add_function(wrap_pyfunction!(selection_table, m)?)?;
+
     let aes_module = PyModule::new(py, "aes")?;
     aes::aes(py, &aes_module)?;
     m.add_submodule(&aes_module)?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{LEAKAGE_MODELS, hw};
+
+    #[test]
+    fn test_aes_inv_sbox_hd() {
+        // AES-128 last round of FIPS-197 appendix B
+        let state: [u8; 16] = [
+            0xeb, 0x40, 0xf2, 0x1e, 0x59, 0x2e, 0x38, 0x84, 0x8b, 0xa1, 0x13, 0xe7, 0x1b, 0xc3,
+            0x42, 0xd2,
+        ];
+        let round_key: [u8; 16] = [
+            0xd0, 0x14, 0xf9, 0xa8, 0xc9, 0xee, 0x25, 0x89, 0xe1, 0x3f, 0x0c, 0xc8, 0xb6, 0x63,
+            0x0c, 0xa6,
+        ];
+        let ciphertext: [u8; 16] = [
+            0x39, 0x25, 0x84, 0x1d, 0x02, 0xdc, 0x09, 0xfb, 0xdc, 0x11, 0x85, 0x97, 0x19, 0x6a,
+            0x0b, 0x32,
+        ];
+        // Position in the state of the S-Box input of each ciphertext byte
+        let shift_rows = [0, 5, 10, 15, 4, 9, 14, 3, 8, 13, 2, 7, 12, 1, 6, 11];
+
+        let (_, model) = LEAKAGE_MODELS
+            .iter()
+            .find(|(name, _)| *name == "aes_inv_sbox_hd")
+            .unwrap();
+        for i in 0..16 {
+            assert_eq!(
+                model(ciphertext[i], round_key[i]),
+                hw((state[shift_rows[i]] ^ ciphertext[i]) as usize)
+            );
+        }
+    }
+}
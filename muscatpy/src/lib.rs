@@ -1,9 +1,221 @@
-use pyo3::prelude::*;
+use std::{
+    fmt::Display,
+    panic,
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+
+use muscat::{
+    progress::ProgressCounter,
+    serde::{Serialize, de::DeserializeOwned},
+};
+use numpy::{PyArrayDescr, PyArrayDescrMethods, PyUntypedArray, PyUntypedArrayMethods};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyTypeError, PyValueError},
+    prelude::*,
+    types::PyBytes,
+};
+
+/// Call `$body` with `$processor` bound to the processor wrapped by `$self`, downcast according to
+/// the sample dtype of `$self`. `$sample` is defined as an alias of the sample type in `$body`.
+///
+/// `$self` must have an `inner: Box<dyn Any + Send + Sync>` field holding a `$type<$sample>`
+/// processor and a `dtype: Py<PyArrayDescr>` field.
+macro_rules! dispatch {
+    ($self:expr, $py:expr, |$processor:ident: &mut $type:ident<$sample:ident>| $body:expr) => {
+        dispatch!(@types $self, $py, $processor, downcast_mut, $type, $sample, $body,
+            u8, u16, u32, u64, i8, i16, i32, i64, f32)
+    };
+    ($self:expr, $py:expr, |$processor:ident: &$type:ident<$sample:ident>| $body:expr) => {
+        dispatch!(@types $self, $py, $processor, downcast_ref, $type, $sample, $body,
+            u8, u16, u32, u64, i8, i16, i32, i64, f32)
+    };
+    (@types $self:expr, $py:expr, $processor:ident, $downcast:ident, $type:ident, $sample:ident,
+        $body:expr, $($ty:ty),*) => {{
+        $(
+            if $self.dtype.bind($py).is_equiv_to(&::numpy::dtype::<$ty>($py)) {
+                #[allow(dead_code)]
+                type $sample = $ty;
+                let $processor = $self.inner.$downcast::<$type<$ty>>().unwrap();
+                return $body;
+            }
+        )*
+
+        unreachable!()
+    }};
+}
 
 mod distinguishers;
 mod leakage_detection;
 mod leakage_model;
+mod preprocessors;
 mod processors;
+mod quicklog;
+
+/// Check that `array` has `ndim` dimensions and the sample `dtype` of a processor.
+fn check_array(
+    py: Python,
+    name: &str,
+    array: &Bound<PyUntypedArray>,
+    ndim: usize,
+    dtype: &Py<PyArrayDescr>,
+) -> PyResult<()> {
+    if array.ndim() != ndim {
+        return Err(PyTypeError::new_err(format!(
+            "Invalid {name} ndim: {}. {name} ndim should be equal to {ndim}.",
+            array.ndim()
+        )));
+    }
+
+    if !array.dtype().is_equiv_to(dtype.bind(py)) {
+        return Err(PyTypeError::new_err(format!(
+            "Invalid {name} dtype: {}. {name} dtype should be {dtype}.",
+            array.dtype(),
+        )));
+    }
+
+    Ok(())
+}
+
+/// Check that the last dimension of `array` is the trace length of a processor.
+fn check_trace_length(
+    name: &str,
+    array: &Bound<PyUntypedArray>,
+    trace_length: usize,
+) -> PyResult<()> {
+    let length = array.shape().last().copied().unwrap_or(0);
+    if length != trace_length {
+        return Err(PyValueError::new_err(format!(
+            "Invalid {name} length: {length}. {name} length should be equal to {trace_length}."
+        )));
+    }
+
+    Ok(())
+}
+
+/// Check that two processors have the same sample dtype, before combining them.
+fn check_same_dtype(
+    py: Python,
+    dtype: &Py<PyArrayDescr>,
+    other_dtype: &Py<PyArrayDescr>,
+) -> PyResult<()> {
+    if !dtype.bind(py).is_equiv_to(other_dtype.bind(py)) {
+        return Err(PyTypeError::new_err(format!(
+            "Cannot combine processors of dtypes {dtype} and {other_dtype}."
+        )));
+    }
+
+    Ok(())
+}
+
+/// Check that a parameter of two processors is the same, before combining them.
+fn check_same_param<V: PartialEq + Display>(name: &str, value: V, other_value: V) -> PyResult<()> {
+    if value != other_value {
+        return Err(PyValueError::new_err(format!(
+            "Cannot combine processors of different {name}: {value} and {other_value}."
+        )));
+    }
+
+    Ok(())
+}
+
+/// Check that a parameter of an unpickled processor state matches the one of the processor.
+fn check_state_param(name: &str, value: usize, expected_value: usize) -> PyResult<()> {
+    if value != expected_value {
+        return Err(PyValueError::new_err(format!(
+            "Invalid state {name}: {value}. {name} should be equal to {expected_value}."
+        )));
+    }
+
+    Ok(())
+}
+
+/// Serialize the state of a processor for pickling.
+fn serialize_state<'py, T: Serialize>(py: Python<'py>, state: &T) -> PyResult<Bound<'py, PyBytes>> {
+    let state = serde_json::to_vec(state).map_err(|err| PyValueError::new_err(err.to_string()))?;
+    Ok(PyBytes::new(py, &state))
+}
+
+/// Deserialize the state of a processor serialized by [`serialize_state`].
+fn deserialize_state<T: DeserializeOwned>(state: &[u8]) -> PyResult<T> {
+    serde_json::from_slice(state).map_err(|err| PyValueError::new_err(err.to_string()))
+}
+
+/// Interval at which Python signals are checked and progress is reported during computations.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Call `progress_callback` with the number of traces processed and the total number of traces.
+fn report_progress(
+    py: Python,
+    progress_callback: Option<&Py<PyAny>>,
+    progress: &ProgressCounter,
+) -> PyResult<()> {
+    if let Some(progress_callback) = progress_callback {
+        progress_callback.call1(py, (progress.processed(), progress.total()))?;
+    }
+
+    Ok(())
+}
+
+/// Run `compute` in another thread with the GIL released, passing it a [`ProgressCounter`] to
+/// report its progress to and to check for cancellation.
+///
+/// Meanwhile, Python signals are checked periodically so that a `KeyboardInterrupt` cancels the
+/// computation, and `progress_callback` is called with the number of traces processed and the
+/// total number of traces. Exceptions raised by signal handlers or by `progress_callback` cancel
+/// the computation and are propagated.
+fn run_detached<R: Send>(
+    py: Python,
+    progress_callback: Option<&Bound<PyAny>>,
+    compute: impl FnOnce(&ProgressCounter) -> Result<R, muscat::Error> + Send,
+) -> PyResult<R> {
+    let progress_callback = progress_callback.map(|callback| callback.clone().unbind());
+    let progress_callback = progress_callback.as_ref();
+    let progress = &ProgressCounter::new();
+
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        let worker = scope.spawn(move || {
+            // The receiver is only dropped once the worker has been joined
+            let _ = sender.send(compute(progress));
+        });
+
+        let (result, error) = py.detach(move || {
+            let mut error: Option<PyErr> = None;
+            loop {
+                match receiver.recv_timeout(POLL_INTERVAL) {
+                    Ok(result) => return (Some(result), error),
+                    Err(RecvTimeoutError::Disconnected) => return (None, error),
+                    Err(RecvTimeoutError::Timeout) if error.is_none() => {
+                        error = Python::attach(|py| {
+                            py.check_signals()?;
+                            report_progress(py, progress_callback, progress)
+                        })
+                        .err();
+                        if error.is_some() {
+                            progress.cancel();
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                }
+            }
+        });
+
+        let Some(result) = result else {
+            // The worker panicked before sending its result
+            panic::resume_unwind(worker.join().unwrap_err());
+        };
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        let result = result.map_err(|err| PyRuntimeError::new_err(err.to_string()))?;
+        report_progress(py, progress_callback, progress)?;
+
+        Ok(result)
+    })
+}
 
 #[pymodule]
 fn muscatpy(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
@@ -19,9 +231,30 @@ fn muscatpy(py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     leakage_model::leakage_model(py, &leakage_model_module)?;
     m.add_submodule(&leakage_model_module)?;
 
+    let preprocessors_module = PyModule::new(py, "preprocessors")?;
+    preprocessors::preprocessors(py, &preprocessors_module)?;
+    m.add_submodule(&preprocessors_module)?;
+
     let processors_module = PyModule::new(py, "processors")?;
     processors::processors(py, &processors_module)?;
     m.add_submodule(&processors_module)?;
 
+    let quicklog_module = PyModule::new(py, "quicklog")?;
+    quicklog::quicklog(py, &quicklog_module)?;
+    m.add_submodule(&quicklog_module)?;
+
+    // Register submodules so that they can be imported, which is needed to unpickle their classes
+    let modules = py.import("sys")?.getattr("modules")?;
+    for submodule in [
+        &distinguishers_module,
+        &leakage_detection_module,
+        &leakage_model_module,
+        &preprocessors_module,
+        &processors_module,
+        &quicklog_module,
+    ] {
+        modules.set_item(format!("muscatpy.{}", submodule.name()?), submodule)?;
+    }
+
     Ok(())
 }
@@ -0,0 +1,593 @@
+use std::{any::Any, cmp::Ordering, ops::Range};
+
+use muscat::preprocessors::{
+    CenteredProduct as CoreCenteredProduct, ElasticAlignment as CoreElasticAlignment,
+    Power as CorePower, StandardScaler as CoreStandardScaler, dist::euclidean_distance,
+};
+use numpy::{
+    IntoPyArray, PyArray1, PyArray2, PyArrayDescr, PyArrayDescrMethods, PyArrayMethods,
+    PyUntypedArray, PyUntypedArrayMethods, dtype, ndarray::Array2,
+};
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::{check_array, check_trace_length};
+
+/// Check that `array` has `ndim` dimensions, for preprocessors accepting several dtypes.
+fn check_ndim(name: &str, array: &Bound<PyUntypedArray>, ndim: usize) -> PyResult<()> {
+    if array.ndim() != ndim {
+        return Err(PyTypeError::new_err(format!(
+            "Invalid {name} ndim: {}. {name} ndim should be equal to {ndim}.",
+            array.ndim()
+        )));
+    }
+
+    Ok(())
+}
+
+/// Convert intervals given as `(start, end)` tuples to ranges.
+fn intervals_to_ranges(intervals: Vec<(usize, usize)>) -> PyResult<Vec<Range<usize>>> {
+    intervals
+        .into_iter()
+        .map(|(start, end)| {
+            if start > end {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid interval: ({start}, {end}). start should be lower than end."
+                )));
+            }
+
+            Ok(start..end)
+        })
+        .collect()
+}
+
+/// Total order on the sum containers of the elastic alignment, so that floating point traces can
+/// be aligned as well as integer ones.
+trait ContainerOrd {
+    fn container_cmp(&self, other: &Self) -> Ordering;
+}
+
+impl ContainerOrd for u64 {
+    fn container_cmp(&self, other: &Self) -> Ordering {
+        self.cmp(other)
+    }
+}
+
+impl ContainerOrd for i64 {
+    fn container_cmp(&self, other: &Self) -> Ordering {
+        self.cmp(other)
+    }
+}
+
+impl ContainerOrd for f64 {
+    fn container_cmp(&self, other: &Self) -> Ordering {
+        self.total_cmp(other)
+    }
+}
+
+#[pyclass(module = "muscatpy.preprocessors")]
+pub struct StandardScaler {
+    inner: Box<dyn Any + Send + Sync>,
+    trace_length: usize,
+    dtype: Py<PyArrayDescr>,
+}
+
+#[pymethods]
+impl StandardScaler {
+    #[new]
+    fn new<'py>(trace_length: usize, dtype: &Bound<'py, PyArrayDescr>) -> PyResult<Self> {
+        macro_rules! type_dispatch {
+            ($($ty:ty),*) => {
+                $(
+                    if dtype.is_equiv_to(&::numpy::dtype::<$ty>(dtype.py())) {
+                        return Ok(Self {
+                            inner: Box::new(CoreStandardScaler::<$ty>::new(trace_length)),
+                            trace_length,
+                            dtype: dtype.clone().into(),
+                        });
+                    }
+                )*
+            };
+        }
+
+        type_dispatch! { u8, u16, u32, u64, i8, i16, i32, i64, f32 }
+
+        Err(PyTypeError::new_err(format!("Unsupported dtype {dtype}")))
+    }
+
+    fn process<'py>(&mut self, trace: &Bound<'py, PyUntypedArray>) -> PyResult<()> {
+        let py = trace.py();
+        check_array(py, "trace", trace, 1, &self.dtype)?;
+        check_trace_length("trace", trace, self.trace_length)?;
+
+        dispatch!(self, py, |scaler: &mut CoreStandardScaler<T>| {
+            scaler.process(trace.cast::<PyArray1<T>>()?.readonly().as_array());
+            Ok(())
+        })
+    }
+
+    fn batch_process<'py>(&mut self, traces: &Bound<'py, PyUntypedArray>) -> PyResult<()> {
+        let py = traces.py();
+        check_array(py, "traces", traces, 2, &self.dtype)?;
+        check_trace_length("traces", traces, self.trace_length)?;
+
+        dispatch!(self, py, |scaler: &mut CoreStandardScaler<T>| {
+            let traces = traces.cast::<PyArray2<T>>()?.readonly();
+            for trace in traces.as_array().rows() {
+                scaler.process(trace);
+            }
+            Ok(())
+        })
+    }
+
+    fn finalize(&mut self, py: Python) {
+        dispatch!(self, py, |scaler: &mut CoreStandardScaler<T>| scaler
+            .finalize())
+    }
+
+    fn apply<'py>(
+        &self,
+        trace: &Bound<'py, PyUntypedArray>,
+    ) -> PyResult<Bound<'py, PyArray1<f32>>> {
+        let py = trace.py();
+        check_array(py, "trace", trace, 1, &self.dtype)?;
+        check_trace_length("trace", trace, self.trace_length)?;
+
+        dispatch!(self, py, |scaler: &CoreStandardScaler<T>| {
+            let trace = trace.cast::<PyArray1<T>>()?.readonly();
+            Ok(scaler.apply(trace.as_array()).into_pyarray(py))
+        })
+    }
+
+    fn batch_apply<'py>(
+        &self,
+        traces: &Bound<'py, PyUntypedArray>,
+    ) -> PyResult<Bound<'py, PyArray2<f32>>> {
+        let py = traces.py();
+        check_array(py, "traces", traces, 2, &self.dtype)?;
+        check_trace_length("traces", traces, self.trace_length)?;
+
+        dispatch!(self, py, |scaler: &CoreStandardScaler<T>| {
+            let traces = traces.cast::<PyArray2<T>>()?.readonly();
+            let traces = traces.as_array();
+
+            let mut output = Array2::zeros((traces.shape()[0], self.trace_length));
+            for (mut output_row, trace) in output.rows_mut().into_iter().zip(traces.rows()) {
+                output_row.assign(&scaler.apply(trace));
+            }
+
+            Ok(output.into_pyarray(py))
+        })
+    }
+
+    #[getter]
+    fn trace_length(&self) -> usize {
+        self.trace_length
+    }
+}
+
+#[pyclass(module = "muscatpy.preprocessors")]
+pub struct CenteredProduct {
+    inner: CoreCenteredProduct,
+    trace_length: usize,
+}
+
+#[pymethods]
+impl CenteredProduct {
+    #[new]
+    fn new(trace_length: usize, intervals: Vec<(usize, usize)>) -> PyResult<Self> {
+        if intervals.iter().any(|&(_, end)| end > trace_length) {
+            return Err(PyValueError::new_err(format!(
+                "Invalid intervals. intervals should be within the trace length {trace_length}."
+            )));
+        }
+
+        Ok(Self {
+            inner: CoreCenteredProduct::new(trace_length, intervals_to_ranges(intervals)?),
+            trace_length,
+        })
+    }
+
+    fn process<'py>(&mut self, trace: &Bound<'py, PyUntypedArray>) -> PyResult<()> {
+        check_ndim("trace", trace, 1)?;
+        check_trace_length("trace", trace, self.trace_length)?;
+
+        let sample_dtype = trace.dtype();
+
+        macro_rules! type_dispatch {
+            ($($ty:ty),*) => {
+                $(
+                    if sample_dtype.is_equiv_to(&dtype::<$ty>(trace.py())) {
+                        let trace = trace.cast::<PyArray1<$ty>>()?.readonly();
+                        self.inner.process(trace.as_array());
+
+                        return Ok(());
+                    }
+                )*
+            };
+        }
+
+        type_dispatch! { u8, u16, u32, i8, i16, i32, i64 }
+
+        Err(PyTypeError::new_err(format!(
+            "Unsupported trace dtype: {sample_dtype}."
+        )))
+    }
+
+    fn batch_process<'py>(&mut self, traces: &Bound<'py, PyUntypedArray>) -> PyResult<()> {
+        check_ndim("traces", traces, 2)?;
+        check_trace_length("traces", traces, self.trace_length)?;
+
+        let sample_dtype = traces.dtype();
+
+        macro_rules! type_dispatch {
+            ($($ty:ty),*) => {
+                $(
+                    if sample_dtype.is_equiv_to(&dtype::<$ty>(traces.py())) {
+                        let traces = traces.cast::<PyArray2<$ty>>()?.readonly();
+                        for trace in traces.as_array().rows() {
+                            self.inner.process(trace);
+                        }
+
+                        return Ok(());
+                    }
+                )*
+            };
+        }
+
+        type_dispatch! { u8, u16, u32, i8, i16, i32, i64 }
+
+        Err(PyTypeError::new_err(format!(
+            "Unsupported traces dtype: {sample_dtype}."
+        )))
+    }
+
+    fn finalize(&mut self) {
+        self.inner.finalize();
+    }
+
+    fn apply<'py>(
+        &self,
+        trace: &Bound<'py, PyUntypedArray>,
+    ) -> PyResult<Bound<'py, PyArray1<f32>>> {
+        check_ndim("trace", trace, 1)?;
+        check_trace_length("trace", trace, self.trace_length)?;
+
+        let sample_dtype = trace.dtype();
+
+        macro_rules! type_dispatch {
+            ($($ty:ty),*) => {
+                $(
+                    if sample_dtype.is_equiv_to(&dtype::<$ty>(trace.py())) {
+                        let trace = trace.cast::<PyArray1<$ty>>()?.readonly();
+
+                        return Ok(self.inner.apply(trace.as_array()).into_pyarray(trace.py()));
+                    }
+                )*
+            };
+        }
+
+        type_dispatch! { u8, u16, i8, i16, f32 }
+
+        Err(PyTypeError::new_err(format!(
+            "Unsupported trace dtype: {sample_dtype}."
+        )))
+    }
+
+    fn batch_apply<'py>(
+        &self,
+        traces: &Bound<'py, PyUntypedArray>,
+    ) -> PyResult<Bound<'py, PyArray2<f32>>> {
+        check_ndim("traces", traces, 2)?;
+        check_trace_length("traces", traces, self.trace_length)?;
+
+        let sample_dtype = traces.dtype();
+
+        macro_rules! type_dispatch {
+            ($($ty:ty),*) => {
+                $(
+                    if sample_dtype.is_equiv_to(&dtype::<$ty>(traces.py())) {
+                        let traces = traces.cast::<PyArray2<$ty>>()?.readonly();
+                        let rows: Vec<_> = traces
+                            .as_array()
+                            .rows()
+                            .into_iter()
+                            .map(|trace| self.inner.apply(trace))
+                            .collect();
+
+                        let output_length = rows.first().map_or(0, |row| row.len());
+                        let mut output = Array2::zeros((rows.len(), output_length));
+                        for (mut output_row, row) in output.rows_mut().into_iter().zip(rows.iter()) {
+                            output_row.assign(row);
+                        }
+
+                        return Ok(output.into_pyarray(traces.py()));
+                    }
+                )*
+            };
+        }
+
+        type_dispatch! { u8, u16, i8, i16, f32 }
+
+        Err(PyTypeError::new_err(format!(
+            "Unsupported traces dtype: {sample_dtype}."
+        )))
+    }
+
+    #[getter]
+    fn trace_length(&self) -> usize {
+        self.trace_length
+    }
+}
+
+#[pyclass(module = "muscatpy.preprocessors")]
+pub struct Power {
+    inner: CorePower,
+    /// Minimum trace length, i.e. the end of the last interval
+    min_trace_length: usize,
+}
+
+#[pymethods]
+impl Power {
+    #[new]
+    fn new(intervals: Vec<(usize, usize)>, power: i32) -> PyResult<Self> {
+        let min_trace_length = intervals.iter().map(|&(_, end)| end).max().unwrap_or(0);
+
+        Ok(Self {
+            inner: CorePower::new(intervals_to_ranges(intervals)?, power),
+            min_trace_length,
+        })
+    }
+
+    fn process<'py>(
+        &self,
+        trace: &Bound<'py, PyUntypedArray>,
+    ) -> PyResult<Bound<'py, PyArray1<f32>>> {
+        check_ndim("trace", trace, 1)?;
+        self.check_trace_length("trace", trace)?;
+
+        let sample_dtype = trace.dtype();
+
+        macro_rules! type_dispatch {
+            ($($ty:ty),*) => {
+                $(
+                    if sample_dtype.is_equiv_to(&dtype::<$ty>(trace.py())) {
+                        let trace = trace.cast::<PyArray1<$ty>>()?.readonly();
+
+                        return Ok(self.inner.process(trace.as_array()).into_pyarray(trace.py()));
+                    }
+                )*
+            };
+        }
+
+        type_dispatch! { u8, u16, u32, i8, i16, i32, i64 }
+
+        Err(PyTypeError::new_err(format!(
+            "Unsupported trace dtype: {sample_dtype}."
+        )))
+    }
+
+    fn batch_process<'py>(
+        &self,
+        traces: &Bound<'py, PyUntypedArray>,
+    ) -> PyResult<Bound<'py, PyArray2<f32>>> {
+        check_ndim("traces", traces, 2)?;
+        self.check_trace_length("traces", traces)?;
+
+        let sample_dtype = traces.dtype();
+
+        macro_rules! type_dispatch {
+            ($($ty:ty),*) => {
+                $(
+                    if sample_dtype.is_equiv_to(&dtype::<$ty>(traces.py())) {
+                        let traces = traces.cast::<PyArray2<$ty>>()?.readonly();
+                        let rows: Vec<_> = traces
+                            .as_array()
+                            .rows()
+                            .into_iter()
+                            .map(|trace| self.inner.process(trace))
+                            .collect();
+
+                        let output_length = rows.first().map_or(0, |row| row.len());
+                        let mut output = Array2::zeros((rows.len(), output_length));
+                        for (mut output_row, row) in output.rows_mut().into_iter().zip(rows.iter()) {
+                            output_row.assign(row);
+                        }
+
+                        return Ok(output.into_pyarray(traces.py()));
+                    }
+                )*
+            };
+        }
+
+        type_dispatch! { u8, u16, u32, i8, i16, i32, i64 }
+
+        Err(PyTypeError::new_err(format!(
+            "Unsupported traces dtype: {sample_dtype}."
+        )))
+    }
+}
+
+impl Power {
+    /// Check that the intervals of the processor are within the traces of `array`.
+    fn check_trace_length(&self, name: &str, array: &Bound<PyUntypedArray>) -> PyResult<()> {
+        let length = array.shape().last().copied().unwrap_or(0);
+        if length < self.min_trace_length {
+            return Err(PyValueError::new_err(format!(
+                "Invalid {name} length: {length}. {name} length should be at least {}.",
+                self.min_trace_length
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// [`CoreElasticAlignment`] using the euclidean distance.
+type EuclideanElasticAlignment<T> = CoreElasticAlignment<T, fn(T, T) -> T>;
+
+#[pyclass(module = "muscatpy.preprocessors")]
+pub struct ElasticAlignment {
+    inner: Box<dyn Any + Send + Sync>,
+    trace_length: usize,
+    dtype: Py<PyArrayDescr>,
+}
+
+#[pymethods]
+impl ElasticAlignment {
+    #[new]
+    fn new<'py>(reference_trace: &Bound<'py, PyUntypedArray>, radius: usize) -> PyResult<Self> {
+        check_ndim("reference_trace", reference_trace, 1)?;
+
+        let py = reference_trace.py();
+        let sample_dtype = reference_trace.dtype();
+
+        macro_rules! type_dispatch {
+            ($($ty:ty),*) => {
+                $(
+                    if sample_dtype.is_equiv_to(&dtype::<$ty>(py)) {
+                        let reference_trace = reference_trace
+                            .cast::<PyArray1<$ty>>()?
+                            .readonly()
+                            .as_array()
+                            .to_owned();
+                        let trace_length = reference_trace.len();
+
+                        return Ok(Self {
+                            inner: Box::new(EuclideanElasticAlignment::<$ty>::new(
+                                reference_trace,
+                                radius,
+                                euclidean_distance,
+                            )),
+                            trace_length,
+                            dtype: sample_dtype.into(),
+                        });
+                    }
+                )*
+            };
+        }
+
+        type_dispatch! { u8, u16, u32, u64, i8, i16, i32, i64, f32 }
+
+        Err(PyTypeError::new_err(format!(
+            "Unsupported reference_trace dtype: {sample_dtype}."
+        )))
+    }
+
+    #[getter]
+    fn reference_trace<'py>(&self, py: Python<'py>) -> Bound<'py, PyUntypedArray> {
+        dispatch!(self, py, |alignment: &EuclideanElasticAlignment<T>| {
+            alignment
+                .reference_trace()
+                .to_owned()
+                .into_pyarray(py)
+                .into_any()
+                .cast_into()
+                .unwrap()
+        })
+    }
+
+    fn align<'py>(
+        &self,
+        trace: &Bound<'py, PyUntypedArray>,
+    ) -> PyResult<Bound<'py, PyUntypedArray>> {
+        let py = trace.py();
+        check_array(py, "trace", trace, 1, &self.dtype)?;
+        check_trace_length("trace", trace, self.trace_length)?;
+
+        dispatch!(self, py, |alignment: &EuclideanElasticAlignment<T>| {
+            let trace = trace.cast::<PyArray1<T>>()?.readonly();
+            let aligned_trace =
+                alignment.align_with_cmp(trace.as_array(), &ContainerOrd::container_cmp);
+
+            Ok(aligned_trace.into_pyarray(py).into_any().cast_into()?)
+        })
+    }
+
+    fn batch_align<'py>(
+        &self,
+        traces: &Bound<'py, PyUntypedArray>,
+    ) -> PyResult<Bound<'py, PyUntypedArray>> {
+        let py = traces.py();
+        check_array(py, "traces", traces, 2, &self.dtype)?;
+        check_trace_length("traces", traces, self.trace_length)?;
+
+        dispatch!(self, py, |alignment: &EuclideanElasticAlignment<T>| {
+            let traces = traces.cast::<PyArray2<T>>()?.readonly();
+            let aligned_traces =
+                alignment.batch_align_with_cmp(traces.as_array(), &ContainerOrd::container_cmp);
+
+            Ok(aligned_traces.into_pyarray(py).into_any().cast_into()?)
+        })
+    }
+
+    /// Return the aligned trace, the warp path as an array of (reference trace index, trace
+    /// index) pairs and the DTW distance.
+    #[allow(clippy::type_complexity)]
+    fn warp<'py>(
+        &self,
+        trace: &Bound<'py, PyUntypedArray>,
+    ) -> PyResult<(Bound<'py, PyUntypedArray>, Bound<'py, PyArray2<usize>>, f64)> {
+        let py = trace.py();
+        check_array(py, "trace", trace, 1, &self.dtype)?;
+        check_trace_length("trace", trace, self.trace_length)?;
+
+        dispatch!(self, py, |alignment: &EuclideanElasticAlignment<T>| {
+            let trace = trace.cast::<PyArray1<T>>()?.readonly();
+            let warp = alignment.warp_with_cmp(trace.as_array(), &ContainerOrd::container_cmp);
+
+            let path = Array2::from_shape_vec(
+                (warp.path.len(), 2),
+                warp.path.iter().flat_map(|&(i, j)| [i, j]).collect(),
+            )
+            .unwrap();
+            // The distance is already a `f64` for floating point traces
+            #[allow(clippy::unnecessary_cast)]
+            let distance = warp.distance as f64;
+
+            Ok((
+                warp.aligned_trace.into_pyarray(py).into_any().cast_into()?,
+                path.into_pyarray(py),
+                distance,
+            ))
+        })
+    }
+
+    fn refine_reference<'py>(
+        &mut self,
+        traces: &Bound<'py, PyUntypedArray>,
+        num_iterations: usize,
+    ) -> PyResult<Vec<f64>> {
+        let py = traces.py();
+        check_array(py, "traces", traces, 2, &self.dtype)?;
+        check_trace_length("traces", traces, self.trace_length)?;
+        if traces.shape()[0] == 0 {
+            return Err(PyValueError::new_err("traces should not be empty."));
+        }
+
+        dispatch!(self, py, |alignment: &mut EuclideanElasticAlignment<T>| {
+            let traces = traces.cast::<PyArray2<T>>()?.readonly();
+
+            Ok(alignment.refine_reference_with_cmp(
+                traces.as_array(),
+                num_iterations,
+                &ContainerOrd::container_cmp,
+            ))
+        })
+    }
+
+    #[getter]
+    fn trace_length(&self) -> usize {
+        self.trace_length
+    }
+}
+
+#[pymodule]
+pub fn preprocessors(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<StandardScaler>()?;
+    m.add_class::<CenteredProduct>()?;
+    m.add_class::<Power>()?;
+    m.add_class::<ElasticAlignment>()?;
+
+    Ok(())
+}
@@ -1,10 +1,18 @@
+use std::any::Any;
+
 use muscat::processors::MeanVar;
 use numpy::{
-    IntoPyArray, PyArray1, PyArray2, PyArrayDescrMethods, PyArrayMethods, PyUntypedArray,
-    PyUntypedArrayMethods, dtype,
+    IntoPyArray, PyArray1, PyArray2, PyArrayDescr, PyArrayDescrMethods, PyArrayMethods,
+    PyUntypedArray, PyUntypedArrayMethods, dtype,
 };
 use pyo3::exceptions::PyTypeError;
 use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use crate::{
+    check_array, check_same_dtype, check_same_param, check_state_param, check_trace_length,
+    deserialize_state, serialize_state,
+};
 
 #[pyfunction]
 pub fn compute_mean<'py>(
@@ -90,10 +98,123 @@ pub fn compute_var<'py>(
     )))
 }
 
+#[pyclass(name = "MeanVar", module = "muscatpy.processors")]
+struct PyMeanVar {
+    inner: Box<dyn Any + Send + Sync>,
+    trace_length: usize,
+    dtype: Py<PyArrayDescr>,
+}
+
+#[pymethods]
+impl PyMeanVar {
+    #[new]
+    fn new<'py>(trace_length: usize, dtype: &Bound<'py, PyArrayDescr>) -> PyResult<Self> {
+        macro_rules! type_dispatch {
+            ($($ty:ty),*) => {
+                $(
+                    if dtype.is_equiv_to(&::numpy::dtype::<$ty>(dtype.py())) {
+                        return Ok(Self {
+                            inner: Box::new(MeanVar::<$ty>::new(trace_length)),
+                            trace_length,
+                            dtype: dtype.clone().into(),
+                        });
+                    }
+                )*
+            };
+        }
+
+        type_dispatch! { u8, u16, u32, u64, i8, i16, i32, i64, f32 }
+
+        Err(PyTypeError::new_err(format!("Unsupported dtype {dtype}")))
+    }
+
+    fn update<'py>(&mut self, trace: &Bound<'py, PyUntypedArray>) -> PyResult<()> {
+        let py = trace.py();
+        check_array(py, "trace", trace, 1, &self.dtype)?;
+        check_trace_length("trace", trace, self.trace_length)?;
+
+        dispatch!(self, py, |meanvar: &mut MeanVar<T>| {
+            meanvar.process(trace.cast::<PyArray1<T>>()?.readonly().as_array());
+            Ok(())
+        })
+    }
+
+    fn batch_update<'py>(&mut self, trace_batch: &Bound<'py, PyUntypedArray>) -> PyResult<()> {
+        let py = trace_batch.py();
+        check_array(py, "trace_batch", trace_batch, 2, &self.dtype)?;
+        check_trace_length("trace_batch", trace_batch, self.trace_length)?;
+
+        dispatch!(self, py, |meanvar: &mut MeanVar<T>| {
+            let trace_batch = trace_batch.cast::<PyArray2<T>>()?.readonly();
+            for trace in trace_batch.as_array().rows() {
+                meanvar.process(trace);
+            }
+            Ok(())
+        })
+    }
+
+    fn combine(&self, py: Python, other: &Self) -> PyResult<Self> {
+        check_same_dtype(py, &self.dtype, &other.dtype)?;
+        check_same_param("trace_length", self.trace_length, other.trace_length)?;
+
+        dispatch!(self, py, |meanvar: &MeanVar<T>| {
+            let other = other.inner.downcast_ref::<MeanVar<T>>().unwrap();
+            Ok(Self {
+                inner: Box::new(meanvar.clone().combine(other.clone())),
+                trace_length: self.trace_length,
+                dtype: self.dtype.clone_ref(py),
+            })
+        })
+    }
+
+    fn mean<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f32>> {
+        dispatch!(self, py, |meanvar: &MeanVar<T>| meanvar
+            .mean()
+            .into_pyarray(py))
+    }
+
+    fn var<'py>(&self, py: Python<'py>) -> Bound<'py, PyArray1<f32>> {
+        dispatch!(self, py, |meanvar: &MeanVar<T>| meanvar
+            .var()
+            .into_pyarray(py))
+    }
+
+    #[getter]
+    fn count(&self, py: Python) -> usize {
+        dispatch!(self, py, |meanvar: &MeanVar<T>| meanvar.count())
+    }
+
+    #[getter]
+    fn trace_length(&self) -> usize {
+        self.trace_length
+    }
+
+    fn __getnewargs__(&self, py: Python) -> (usize, Py<PyArrayDescr>) {
+        (self.trace_length, self.dtype.clone_ref(py))
+    }
+
+    fn __getstate__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        dispatch!(self, py, |meanvar: &MeanVar<T>| serialize_state(
+            py, meanvar
+        ))
+    }
+
+    fn __setstate__(&mut self, py: Python, state: &[u8]) -> PyResult<()> {
+        dispatch!(self, py, |meanvar: &mut MeanVar<T>| {
+            let state: MeanVar<T> = deserialize_state(state)?;
+            check_state_param("trace_length", state.trace_length(), self.trace_length)?;
+            *meanvar = state;
+            Ok(())
+        })
+    }
+}
+
 #[pymodule]
 pub fn processors(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(compute_mean, m)?)?;
     m.add_function(wrap_pyfunction!(compute_var, m)?)?;
 
+    m.add_class::<PyMeanVar>()?;
+
     Ok(())
 }
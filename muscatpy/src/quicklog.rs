@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use muscat::quicklog::{LogError, TraceSetLoader};
+use muscat::trace::Column;
+use numpy::{IntoPyArray, PyArrayDescr, PyArrayDescrMethods, PyUntypedArray};
+use pyo3::exceptions::{PyOSError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+/// Convert a quicklog [`LogError`] to a Python exception.
+fn log_error(err: LogError) -> PyErr {
+    match err {
+        LogError::IoError(err) => err.into(),
+        LogError::MissingTracesDir | LogError::MissingTraceFile(_) => {
+            PyOSError::new_err(err.to_string())
+        }
+        err => PyValueError::new_err(err.to_string()),
+    }
+}
+
+/// Load the traces of the log at `path`, along with the given record fields as columns.
+///
+/// Return the traces as a 2D array and a dictionary mapping column names to arrays.
+#[pyfunction]
+#[pyo3(signature = (
+    path,
+    dtype,
+    traces_dir=None,
+    bytes_columns=None,
+    bool_columns=None,
+    integer_columns=None,
+))]
+#[allow(clippy::type_complexity)]
+pub fn load<'py>(
+    path: &str,
+    dtype: &Bound<'py, PyArrayDescr>,
+    traces_dir: Option<&str>,
+    bytes_columns: Option<HashMap<String, String>>,
+    bool_columns: Option<HashMap<String, String>>,
+    integer_columns: Option<HashMap<String, String>>,
+) -> PyResult<(Bound<'py, PyUntypedArray>, Bound<'py, PyDict>)> {
+    let py = dtype.py();
+
+    macro_rules! type_dispatch {
+        ($($ty:ty),*) => {
+            $(
+                if dtype.is_equiv_to(&::numpy::dtype::<$ty>(py)) {
+                    let mut loader = TraceSetLoader::<$ty>::new();
+                    if let Some(traces_dir) = traces_dir {
+                        loader = loader.traces_dir(traces_dir);
+                    }
+                    for (name, key) in bytes_columns.iter().flatten() {
+                        loader = loader.bytes_column(name, key);
+                    }
+                    for (name, key) in bool_columns.iter().flatten() {
+                        loader = loader.bool_column(name, key);
+                    }
+                    for (name, key) in integer_columns.iter().flatten() {
+                        loader = loader.integer_column(name, key);
+                    }
+
+                    let (traces, trace_columns) =
+                        loader.load(path).map_err(log_error)?.into_parts();
+
+                    let columns = PyDict::new(py);
+                    for (name, column) in trace_columns {
+                        match column {
+                            Column::Bytes(values) => columns.set_item(name, values.into_pyarray(py))?,
+                            Column::Bool(values) => columns.set_item(name, values.into_pyarray(py))?,
+                            Column::Integer(values) => {
+                                columns.set_item(name, values.into_pyarray(py))?
+                            }
+                        }
+                    }
+
+                    let traces = traces.into_pyarray(py);
+
+                    return Ok((traces.into_any().cast_into()?, columns));
+                }
+            )*
+        };
+    }
+
+    type_dispatch! { u8, u16, u32, u64, i8, i16, i32, i64, f32 }
+
+    Err(PyTypeError::new_err(format!("Unsupported dtype {dtype}")))
+}
+
+#[pymodule]
+pub fn quicklog(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(load, m)?)?;
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+import pickle
+
+import numpy as np
+import pytest
+
+import muscatpy
+
+CpaProcessor = muscatpy.distinguishers.CpaProcessor
+DpaProcessor = muscatpy.distinguishers.DpaProcessor
+SnrProcessor = muscatpy.leakage_detection.SnrProcessor
+NicvProcessor = muscatpy.leakage_detection.NicvProcessor
+TTestProcessor = muscatpy.leakage_detection.TTestProcessor
+MeanVar = muscatpy.processors.MeanVar
+
+INT16 = np.dtype(np.int16)
+
+
+def test_combine_pickled():
+    traces = np.arange(24, dtype=np.int16).reshape(6, 4)
+    classes = np.array([0, 1, 2, 0, 1, 2], dtype=np.uint64)
+
+    snr = SnrProcessor(4, 3, INT16, classes_var=True)
+    snr.batch_update(traces[:3], classes[:3])
+    other = SnrProcessor(4, 3, INT16, classes_var=True)
+    other.batch_update(traces[3:], classes[3:])
+
+    combined = pickle.loads(pickle.dumps(snr)).combine(pickle.loads(pickle.dumps(other)))
+    expected = SnrProcessor(4, 3, INT16, classes_var=True)
+    expected.batch_update(traces, classes)
+    np.testing.assert_array_equal(combined.classes_count(), expected.classes_count())
+    np.testing.assert_allclose(combined.classes_var(), expected.classes_var())
+
+    meanvar = MeanVar(4, INT16)
+    meanvar.batch_update(traces)
+    combined = pickle.loads(pickle.dumps(meanvar)).combine(meanvar)
+    assert combined.count == 12
+
+
+@pytest.mark.parametrize(
+    ("processor", "other"),
+    [
+        (CpaProcessor(4, 256, INT16), CpaProcessor(5, 256, INT16)),
+        (CpaProcessor(4, 256, INT16), CpaProcessor(4, 16, INT16)),
+        (DpaProcessor(4, 256, INT16), DpaProcessor(5, 256, INT16)),
+        (DpaProcessor(4, 256, INT16), DpaProcessor(4, 16, INT16)),
+        (SnrProcessor(4, 3, INT16), SnrProcessor(5, 3, INT16)),
+        (SnrProcessor(4, 3, INT16), SnrProcessor(4, 2, INT16)),
+        (
+            SnrProcessor(4, 3, INT16),
+            SnrProcessor(4, 3, INT16, classes_var=True),
+        ),
+        (NicvProcessor(4, 3, INT16), NicvProcessor(5, 3, INT16)),
+        (NicvProcessor(4, 3, INT16), NicvProcessor(4, 2, INT16)),
+        (
+            NicvProcessor(4, 3, INT16, classes_var=True),
+            NicvProcessor(4, 3, INT16),
+        ),
+        (TTestProcessor(4, INT16), TTestProcessor(5, INT16)),
+        (MeanVar(4, INT16), MeanVar(5, INT16)),
+    ],
+)
+def test_combine_mismatch(processor, other):
+    with pytest.raises(ValueError):
+        processor.combine(other)
+
+    # Unpickled processors keep their parameters
+    with pytest.raises(ValueError):
+        pickle.loads(pickle.dumps(processor)).combine(other)
+
+
+def test_combine_dtype_mismatch():
+    with pytest.raises(TypeError):
+        MeanVar(4, INT16).combine(MeanVar(4, np.dtype(np.float32)))
+
+
+@pytest.mark.parametrize(
+    ("processor", "other"),
+    [
+        (CpaProcessor(4, 256, INT16), CpaProcessor(5, 256, INT16)),
+        (CpaProcessor(4, 256, INT16), CpaProcessor(4, 16, INT16)),
+        (DpaProcessor(4, 256, INT16), DpaProcessor(5, 256, INT16)),
+        (DpaProcessor(4, 256, INT16), DpaProcessor(4, 16, INT16)),
+        (SnrProcessor(4, 3, INT16), SnrProcessor(5, 3, INT16)),
+        (SnrProcessor(4, 3, INT16), SnrProcessor(4, 2, INT16)),
+        (NicvProcessor(4, 3, INT16), NicvProcessor(5, 3, INT16)),
+        (NicvProcessor(4, 3, INT16), NicvProcessor(4, 2, INT16)),
+        (TTestProcessor(4, INT16), TTestProcessor(5, INT16)),
+        (MeanVar(4, INT16), MeanVar(5, INT16)),
+    ],
+)
+def test_setstate_mismatch(processor, other):
+    with pytest.raises(ValueError):
+        processor.__setstate__(other.__getstate__())
@@ -0,0 +1,330 @@
+//! Checkpointing of long-running computations.
+//!
+//! A [`Checkpointer`] holds the state of a computation (typically a processor) along with a trace
+//! cursor, the number of traces processed so far. Both are periodically saved together in a single
+//! file, that is replaced atomically. After a crash or a reboot, the computation resumes from the
+//! cursor of the last checkpoint: traces processed after it were not saved in the state, so that
+//! they are processed again, and each trace is counted exactly once.
+//!
+//! # Examples
+//! ```
+//! use muscat::checkpoint::Checkpointer;
+//! use muscat::processors::MeanVar;
+//! use ndarray::{array, s};
+//!
+//! # let dir = tempfile::tempdir().unwrap();
+//! # let path = dir.path().join("meanvar.json");
+//! let traces = array![[77u8, 137, 51, 91], [72, 61, 91, 83], [39, 49, 52, 23], [26, 114, 63, 45]];
+//!
+//! // Resume from the checkpoint if it exists, and save it every 2 traces
+//! let mut checkpointer =
+//!     Checkpointer::open(&path, 2, || MeanVar::new(traces.shape()[1])).unwrap();
+//! checkpointer
+//!     .run(traces.shape()[0], 2, |meanvar, range| {
+//!         for trace in traces.slice(s![range, ..]).rows() {
+//!             meanvar.process(trace);
+//!         }
+//!
+//!         Ok::<(), muscat::Error>(())
+//!     })
+//!     .unwrap();
+//!
+//! let meanvar = checkpointer.into_state();
+//! assert_eq!(meanvar.count(), 4);
+//! ```
+
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{BufReader, BufWriter, ErrorKind, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::Error;
+
+/// Content of a checkpoint file.
+#[derive(Serialize, Deserialize)]
+struct Checkpoint<S> {
+    /// Number of traces processed
+    cursor: usize,
+    /// State of the computation after processing `cursor` traces
+    state: S,
+}
+
+/// Periodically saves the state of a computation along with the number of traces processed, and
+/// resumes it from the last checkpoint.
+///
+/// # Warning
+/// The file format is not stable as muscat is active development. Thus, the format might change
+/// between versions.
+pub struct Checkpointer<S> {
+    /// Path of the checkpoint file
+    path: PathBuf,
+    /// Number of traces between two checkpoints
+    save_interval: usize,
+    /// Number of traces processed
+    cursor: usize,
+    /// Number of traces processed at the last checkpoint
+    saved_cursor: usize,
+    /// State of the computation
+    state: S,
+    /// Whether the state may have been partially updated by a failed batch
+    poisoned: bool,
+}
+
+impl<S> Checkpointer<S>
+where
+    S: Serialize + DeserializeOwned,
+{
+    /// Resume the computation from the checkpoint at `path` if it exists, otherwise start a new
+    /// computation from the state returned by `init`.
+    ///
+    /// A checkpoint is saved each time at least `save_interval` traces have been processed since
+    /// the last one.
+    ///
+    /// # Panics
+    /// Panic if `save_interval` is 0.
+    pub fn open<P, F>(path: P, save_interval: usize, init: F) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+        F: FnOnce() -> S,
+    {
+        assert!(save_interval > 0);
+
+        let path = path.as_ref().to_path_buf();
+        let checkpoint = match File::open(&path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file))?,
+            Err(err) if err.kind() == ErrorKind::NotFound => Checkpoint {
+                cursor: 0,
+                state: init(),
+            },
+            Err(err) => return Err(err.into()),
+        };
+
+        Ok(Self {
+            path,
+            save_interval,
+            cursor: checkpoint.cursor,
+            saved_cursor: checkpoint.cursor,
+            state: checkpoint.state,
+            poisoned: false,
+        })
+    }
+
+    /// Returns the number of traces processed, which is also the index of the next trace to
+    /// process.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Returns the state of the computation.
+    pub fn state(&self) -> &S {
+        &self.state
+    }
+
+    /// Returns the state of the computation, dropping the checkpointer.
+    pub fn into_state(self) -> S {
+        self.state
+    }
+
+    /// Returns `true` if a batch failed in [`Checkpointer::run`], in which case the state may
+    /// have been partially updated and the checkpointer must be reopened.
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Update the state with `update`, which must process the `count` traces following the
+    /// cursor, then advance the cursor and save a checkpoint if needed.
+    ///
+    /// Return [`Error::Poisoned`] if the checkpointer is poisoned.
+    pub fn update<F>(&mut self, count: usize, update: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut S),
+    {
+        self.check_poisoned()?;
+        update(&mut self.state);
+        self.cursor += count;
+
+        if self.cursor - self.saved_cursor >= self.save_interval {
+            self.save()?;
+        }
+
+        Ok(())
+    }
+
+    /// Process the traces following the cursor up to `num_traces`, in batches of at most
+    /// `batch_size` traces given by their index range, then save a checkpoint.
+    ///
+    /// If `process` fails, the state may have been partially updated by the failing batch, thus
+    /// the checkpointer is poisoned: [`Checkpointer::run`], [`Checkpointer::update`] and
+    /// [`Checkpointer::save`] return [`Error::Poisoned`], and the computation must be resumed by
+    /// reopening the checkpoint.
+    ///
+    /// # Panics
+    /// Panic if `batch_size` is 0.
+    pub fn run<F, E>(
+        &mut self,
+        num_traces: usize,
+        batch_size: usize,
+        mut process: F,
+    ) -> Result<(), E>
+    where
+        F: FnMut(&mut S, Range<usize>) -> Result<(), E>,
+        E: From<Error>,
+    {
+        assert!(batch_size > 0);
+        self.check_poisoned()?;
+
+        while self.cursor < num_traces {
+            let range = self.cursor..num_traces.min(self.cursor + batch_size);
+            if let Err(err) = process(&mut self.state, range.clone()) {
+                self.poisoned = true;
+                return Err(err);
+            }
+            self.update(range.len(), |_| ())?;
+        }
+
+        Ok(self.save()?)
+    }
+
+    /// Save a checkpoint of the state and the cursor.
+    ///
+    /// The checkpoint is written to a temporary file which then replaces the previous checkpoint,
+    /// so that a crash while saving leaves the previous checkpoint intact.
+    ///
+    /// Return [`Error::Poisoned`] if the checkpointer is poisoned.
+    pub fn save(&mut self) -> Result<(), Error> {
+        self.check_poisoned()?;
+
+        let mut tmp_path = OsString::from(self.path.as_os_str());
+        tmp_path.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_path);
+
+        let file = File::create(&tmp_path)?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(
+            &mut writer,
+            &Checkpoint {
+                cursor: self.cursor,
+                state: &self.state,
+            },
+        )?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+
+        fs::rename(&tmp_path, &self.path)?;
+        // Persist the rename itself
+        #[cfg(unix)]
+        if let Some(dir) = self.path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            File::open(dir)?.sync_all()?;
+        }
+
+        self.saved_cursor = self.cursor;
+
+        Ok(())
+    }
+
+    fn check_poisoned(&self) -> Result<(), Error> {
+        if self.poisoned {
+            return Err(Error::Poisoned);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Checkpointer;
+    use crate::{Error, processors::MeanVar};
+    use ndarray::{Array2, s};
+
+    #[test]
+    fn test_checkpointer_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checkpoint.json");
+        let traces = Array2::from_shape_fn((25, 4), |(i, j)| ((i * 7 + j * 13) % 31) as u8);
+
+        let mut expected = MeanVar::new(4);
+        for trace in traces.rows() {
+            expected.process(trace);
+        }
+
+        // Process 13 traces, with checkpoints after 5 and 10 traces, then "crash"
+        let mut checkpointer = Checkpointer::open(&path, 5, || MeanVar::<u8>::new(4)).unwrap();
+        for i in 0..13 {
+            checkpointer
+                .update(1, |meanvar| meanvar.process(traces.row(i)))
+                .unwrap();
+        }
+        drop(checkpointer);
+
+        let mut checkpointer = Checkpointer::open(&path, 5, || MeanVar::<u8>::new(4)).unwrap();
+        assert_eq!(checkpointer.cursor(), 10);
+        assert_eq!(checkpointer.state().count(), 10);
+
+        checkpointer
+            .run(traces.shape()[0], 4, |meanvar, range| {
+                for trace in traces.slice(s![range, ..]).rows() {
+                    meanvar.process(trace);
+                }
+
+                Ok::<(), Error>(())
+            })
+            .unwrap();
+        assert_eq!(checkpointer.cursor(), 25);
+
+        // The completed computation is saved
+        let checkpointer = Checkpointer::open(&path, 5, || MeanVar::<u8>::new(4)).unwrap();
+        assert_eq!(checkpointer.cursor(), 25);
+        let meanvar = checkpointer.into_state();
+        assert_eq!(meanvar.count(), 25);
+        assert_eq!(meanvar.mean(), expected.mean());
+        assert_eq!(meanvar.var(), expected.var());
+    }
+
+    #[test]
+    fn test_checkpointer_run_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("checkpoint.json");
+
+        let mut checkpointer = Checkpointer::open(&path, 1, || 0usize).unwrap();
+        let result = checkpointer.run(10, 3, |sum, range| {
+            if range.start >= 6 {
+                return Err(Error::Cancelled);
+            }
+            *sum += range.len();
+
+            Ok(())
+        });
+        assert!(matches!(result, Err(Error::Cancelled)));
+
+        // The failed batch cannot be counted twice by retrying with the same checkpointer
+        assert!(checkpointer.is_poisoned());
+        let result = checkpointer.run(10, 3, |sum, range| {
+            *sum += range.len();
+            Ok::<(), Error>(())
+        });
+        assert!(matches!(result, Err(Error::Poisoned)));
+        assert!(matches!(checkpointer.save(), Err(Error::Poisoned)));
+        assert!(matches!(
+            checkpointer.update(1, |sum| *sum += 1),
+            Err(Error::Poisoned)
+        ));
+
+        let mut checkpointer = Checkpointer::open(&path, 1, || 0usize).unwrap();
+        assert_eq!(checkpointer.cursor(), 6);
+        assert_eq!(*checkpointer.state(), 6);
+
+        checkpointer
+            .run(10, 3, |sum, range| {
+                *sum += range.len();
+                Ok::<(), Error>(())
+            })
+            .unwrap();
+        assert_eq!(*checkpointer.state(), 10);
+    }
+}
@@ -0,0 +1,350 @@
+//! Load [ChipWhisperer](https://github.com/newaetech/chipwhisperer) projects.
+//!
+//! A ChipWhisperer project is made of a `.cwp` configuration file and a `<name>_data` directory.
+//! Captures are stored in segments, each segment being a set of npy files sharing a prefix in the
+//! `traces` subdirectory: `<prefix>traces.npy`, `<prefix>textin.npy`, `<prefix>textout.npy` and
+//! `<prefix>keylist.npy`.
+
+use ndarray::Array2;
+use npyz::{NpyFile, Order};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+use crate::{
+    trace::{Column, TraceSet, names},
+    util::chipwhisperer_float_to_u16,
+};
+
+/// Errors raised when loading ChipWhisperer projects.
+#[derive(Error, Debug)]
+pub enum ChipWhispererError {
+    #[error("IO error")]
+    IoError(#[from] io::Error),
+    #[error("Invalid project: {0}")]
+    InvalidProject(String),
+    #[error("Invalid array {path}: {reason}")]
+    InvalidArray { path: PathBuf, reason: String },
+    #[error("Project has no trace segment")]
+    NoSegments,
+}
+
+/// Parse an INI file into a map of sections, each one being a map of keys to values.
+fn parse_ini(content: &str) -> HashMap<String, HashMap<String, String>> {
+    let mut sections: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut section = String::new();
+
+    for line in content.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.trim().to_string();
+        } else if let Some((key, value)) = line.split_once('=') {
+            sections
+                .entry(section.clone())
+                .or_default()
+                .insert(key.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    sections
+}
+
+/// A ChipWhisperer project.
+///
+/// # Examples
+/// ```rust,no_run
+/// use muscat::chipwhisperer::Project;
+/// use muscat::trace::names;
+///
+/// let project = Project::open("capture.cwp").unwrap();
+/// let traces = project.load_u16().unwrap();
+/// let plaintexts = traces.column(names::PLAINTEXT);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Project {
+    /// Prefixes of the npy files of each segment, including the traces directory
+    segments: Vec<PathBuf>,
+}
+
+impl Project {
+    /// Opens the project with the given `.cwp` configuration file.
+    ///
+    /// Enabled segments are read from the trace management section of the configuration. If the
+    /// configuration does not list any segment, every `*traces.npy` file of the traces directory
+    /// is considered to be a segment.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ChipWhispererError> {
+        let path = path.as_ref();
+        let stem = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| ChipWhispererError::InvalidProject("invalid project path".into()))?;
+        let traces_dir = path.with_file_name(format!("{stem}_data")).join("traces");
+
+        let config = parse_ini(&fs::read_to_string(path)?);
+        let mut segments = Vec::new();
+        if let Some(trace_management) = config.get("Trace Management") {
+            let mut indices: Vec<usize> = trace_management
+                .keys()
+                .filter_map(|key| key.strip_prefix("tracefile")?.parse().ok())
+                .collect();
+            indices.sort_unstable();
+
+            for i in indices {
+                let enabled = trace_management
+                    .get(&format!("enabled{i}"))
+                    .is_none_or(|enabled| enabled.eq_ignore_ascii_case("true"));
+                if !enabled {
+                    continue;
+                }
+
+                let segment_config = path
+                    .with_file_name(format!("{stem}_data"))
+                    .join(&trace_management[&format!("tracefile{i}")]);
+                let segment_config = parse_ini(&fs::read_to_string(&segment_config)?);
+                let prefix = segment_config
+                    .get("Trace Config")
+                    .and_then(|section| section.get("prefix"))
+                    .ok_or_else(|| {
+                        ChipWhispererError::InvalidProject(format!("segment {i} has no prefix"))
+                    })?;
+                segments.push(traces_dir.join(prefix));
+            }
+        }
+
+        if segments.is_empty() && traces_dir.is_dir() {
+            let mut files: Vec<PathBuf> = fs::read_dir(&traces_dir)?
+                .filter_map(|entry| {
+                    let name = entry.ok()?.file_name().into_string().ok()?;
+                    let prefix = name.strip_suffix("traces.npy")?;
+                    Some(traces_dir.join(prefix))
+                })
+                .collect();
+            files.sort();
+            segments = files;
+        }
+
+        if segments.is_empty() {
+            return Err(ChipWhispererError::NoSegments);
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Returns the prefixes of the npy files of each segment.
+    pub fn segments(&self) -> &[PathBuf] {
+        &self.segments
+    }
+
+    /// Load the traces of every segment along with the plaintext, ciphertext and key columns
+    /// (see [`names`]), when available in every segment.
+    pub fn load(&self) -> Result<TraceSet<f64>, ChipWhispererError> {
+        let mut traces = Vec::new();
+        let mut columns: Vec<(&str, Option<Vec<Array2<u8>>>)> = vec![
+            (names::PLAINTEXT, Some(Vec::new())),
+            (names::CIPHERTEXT, Some(Vec::new())),
+            (names::KEY, Some(Vec::new())),
+        ];
+
+        for segment in self.segments.iter() {
+            let segment_traces = read_traces(&segment_file(segment, "traces.npy"))?;
+
+            for ((_, column), suffix) in
+                columns
+                    .iter_mut()
+                    .zip(["textin.npy", "textout.npy", "keylist.npy"])
+            {
+                let path = segment_file(segment, suffix);
+                if column.is_none() {
+                    continue;
+                }
+                if !path.exists() {
+                    *column = None;
+                    continue;
+                }
+
+                let values = read_bytes(&path)?;
+                if values.shape()[0] != segment_traces.shape()[0] {
+                    return Err(ChipWhispererError::InvalidArray {
+                        path,
+                        reason: "number of rows differs from the number of traces".into(),
+                    });
+                }
+                column.as_mut().unwrap().push(values);
+            }
+
+            traces.push(segment_traces);
+        }
+
+        let mut trace_set = TraceSet::new(concatenate(&traces, &self.segments[0])?);
+        for (name, column) in columns {
+            if let Some(column) = column {
+                trace_set.insert_column(
+                    name,
+                    Column::Bytes(concatenate(&column, &self.segments[0])?),
+                );
+            }
+        }
+
+        Ok(trace_set)
+    }
+
+    /// Load the project (see [`Project::load`]) and convert the samples to integers (see
+    /// [`chipwhisperer_float_to_u16`]).
+    pub fn load_u16(&self) -> Result<TraceSet<u16>, ChipWhispererError> {
+        Ok(self.load()?.mapv(chipwhisperer_float_to_u16))
+    }
+}
+
+fn segment_file(prefix: &Path, suffix: &str) -> PathBuf {
+    let mut name = prefix.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    prefix.with_file_name(name)
+}
+
+type BufNpyFile = NpyFile<BufReader<File>>;
+
+/// Open a npy file holding a 2D array in C order, returning it along with its shape.
+fn open_npy(path: &Path) -> Result<(BufNpyFile, (usize, usize)), ChipWhispererError> {
+    let npy = NpyFile::new(BufReader::new(File::open(path)?))?;
+    let invalid = |reason: &str| ChipWhispererError::InvalidArray {
+        path: path.to_path_buf(),
+        reason: reason.into(),
+    };
+
+    let shape = match npy.shape() {
+        &[rows, columns] => (rows as usize, columns as usize),
+        _ => return Err(invalid("expected a 2D array")),
+    };
+    if npy.order() != Order::C {
+        return Err(invalid("expected C order"));
+    }
+
+    Ok((npy, shape))
+}
+
+/// Read a 2D array of `f64` or `f32` traces as `f64`.
+fn read_traces(path: &Path) -> Result<Array2<f64>, ChipWhispererError> {
+    let (npy, shape) = open_npy(path)?;
+
+    let data = match npy.try_data::<f64>() {
+        Ok(data) => data.collect::<io::Result<Vec<_>>>()?,
+        Err(npy) => match npy.try_data::<f32>() {
+            Ok(data) => data
+                .map(|x| x.map(f64::from))
+                .collect::<io::Result<Vec<_>>>()?,
+            Err(_) => {
+                return Err(ChipWhispererError::InvalidArray {
+                    path: path.to_path_buf(),
+                    reason: "expected float64 or float32 samples".into(),
+                });
+            }
+        },
+    };
+
+    // The shape is the one of the npy file, thus this cannot fail
+    Ok(Array2::from_shape_vec(shape, data).unwrap())
+}
+
+/// Read a 2D array of bytes.
+fn read_bytes(path: &Path) -> Result<Array2<u8>, ChipWhispererError> {
+    let (npy, shape) = open_npy(path)?;
+
+    let data = npy
+        .data::<u8>()
+        .map_err(|_| ChipWhispererError::InvalidArray {
+            path: path.to_path_buf(),
+            reason: "expected uint8 values".into(),
+        })?
+        .collect::<io::Result<Vec<_>>>()?;
+
+    Ok(Array2::from_shape_vec(shape, data).unwrap())
+}
+
+/// Concatenate the rows of arrays read from the segments of `project`.
+fn concatenate<T: Clone>(
+    arrays: &[Array2<T>],
+    project: &Path,
+) -> Result<Array2<T>, ChipWhispererError> {
+    let views: Vec<_> = arrays.iter().map(|array| array.view()).collect();
+
+    ndarray::concatenate(ndarray::Axis(0), &views).map_err(|_| ChipWhispererError::InvalidArray {
+        path: project.to_path_buf(),
+        reason: "segments have different row lengths".into(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Project;
+    use crate::trace::names;
+    use ndarray::{Array2, array};
+    use ndarray_npy::write_npy;
+    use std::fs;
+
+    #[test]
+    fn test_load_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let traces_dir = dir.path().join("capture_data").join("traces");
+        fs::create_dir_all(&traces_dir).unwrap();
+
+        fs::write(
+            dir.path().join("capture.cwp"),
+            "[Trace Management]\n\
+             tracefile0 = traces/config_a_.cfg\n\
+             enabled0 = True\n\
+             tracefile1 = traces/config_b_.cfg\n\
+             enabled1 = True\n\
+             tracefile2 = traces/config_c_.cfg\n\
+             enabled2 = False\n",
+        )
+        .unwrap();
+        for (prefix, offset) in [("a_", 0.), ("b_", 0.25)] {
+            fs::write(
+                traces_dir.join(format!("config_{prefix}.cfg")),
+                format!("[Trace Config]\nnumtraces = 2\nprefix = {prefix}\n"),
+            )
+            .unwrap();
+            let traces = Array2::from_shape_fn((2, 3), |(i, j)| offset + (i * 3 + j) as f64 / 64.);
+            write_npy(traces_dir.join(format!("{prefix}traces.npy")), &traces).unwrap();
+            let textin =
+                Array2::from_shape_fn((2, 16), |(i, j)| (offset * 100.) as u8 + (i + j) as u8);
+            write_npy(traces_dir.join(format!("{prefix}textin.npy")), &textin).unwrap();
+            write_npy(
+                traces_dir.join(format!("{prefix}keylist.npy")),
+                &Array2::<u8>::zeros((2, 16)),
+            )
+            .unwrap();
+        }
+        // Only present in the first segment
+        write_npy(
+            traces_dir.join("a_textout.npy"),
+            &Array2::<u8>::zeros((2, 16)),
+        )
+        .unwrap();
+
+        let project = Project::open(dir.path().join("capture.cwp")).unwrap();
+        assert_eq!(project.segments().len(), 2);
+
+        let traces = project.load().unwrap();
+        assert_eq!(traces.len(), 4);
+        assert_eq!(
+            traces.traces().row(2),
+            array![0.25, 0.25 + 1. / 64., 0.25 + 2. / 64.]
+        );
+        assert_eq!(
+            traces.column_names().collect::<Vec<_>>(),
+            vec![names::KEY, names::PLAINTEXT]
+        );
+        assert_eq!(traces.get(3).bytes(names::PLAINTEXT).unwrap()[0], 26);
+
+        let traces = project.load_u16().unwrap();
+        assert_eq!(traces.traces()[[0, 0]], 2048);
+        assert_eq!(traces.traces()[[2, 0]], 2560);
+    }
+}
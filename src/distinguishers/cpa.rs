@@ -1,13 +1,12 @@
 use crate::{
     Error, Sample,
+    progress::{Progress, par_fold_batches, without_progress},
+    statistics::{ConfidenceInterval, fisher_confidence_interval},
     util::{argmax_by, argsort_by, max_per_row},
 };
-use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis};
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis, Ix2};
 use num_traits::AsPrimitive;
-use rayon::{
-    iter::ParallelBridge,
-    prelude::{IntoParallelIterator, ParallelIterator},
-};
+use rayon::prelude::{IntoParallelIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 use std::{fmt::Debug, fs::File, iter::zip, path::Path};
 
@@ -18,6 +17,8 @@ use std::{fmt::Debug, fs::File, iter::zip, path::Path};
 pub struct Cpa {
     /// Pearson correlation coefficients
     pub(crate) corr: Array2<f32>,
+    /// Number of traces used to compute the coefficients
+    pub(crate) num_traces: usize,
 }
 
 impl Cpa {
@@ -33,6 +34,23 @@ impl Cpa {
         self.corr.view()
     }
 
+    /// Return the Pearson correlation coefficients along with their confidence interval at the
+    /// given `confidence` level (e.g. 0.95), computed using the Fisher z-transformation.
+    ///
+    /// As the coefficients are stored in absolute value, a lower bound below zero means that the
+    /// coefficient is not significantly different from zero at the given confidence level.
+    ///
+    /// # Panics
+    /// Panic if `confidence` is not in the open interval (0, 1).
+    pub fn corr_confidence_interval(&self, confidence: f32) -> ConfidenceInterval<Ix2> {
+        fisher_confidence_interval(self.corr.clone(), self.num_traces, confidence)
+    }
+
+    /// Return the number of traces used to compute the Pearson correlation coefficients.
+    pub fn num_traces(&self) -> usize {
+        self.num_traces
+    }
+
     /// Return the guess with the highest Pearson correlation coefficient.
     pub fn best_guess(&self) -> usize {
         argmax_by(self.max_corr().view(), f32::total_cmp)
@@ -95,31 +113,68 @@ where
     <T as Sample>::Container: Send + Sync,
     P: Into<usize> + Copy + Sync,
     F: Fn(usize, usize) -> usize + Send + Sync + Copy,
+{
+    without_progress(|progress| {
+        cpa_with_progress(
+            traces,
+            plaintexts,
+            guess_range,
+            target_byte,
+            leakage_model,
+            batch_size,
+            progress,
+        )
+    })
+}
+
+/// Compute the [`Cpa`] of the given traces like [`cpa`], reporting progress to `progress`.
+///
+/// # Errors
+/// Return [`Error::Cancelled`] if `progress` is cancelled before all traces are processed.
+///
+/// # Panics
+/// - Panic if `traces.shape()[0] != plaintexts.shape()[0]`
+/// - Panic if `batch_size` is 0.
+pub fn cpa_with_progress<T, P, F, Pr>(
+    traces: ArrayView2<T>,
+    plaintexts: ArrayView2<P>,
+    guess_range: usize,
+    target_byte: usize,
+    leakage_model: F,
+    batch_size: usize,
+    progress: &Pr,
+) -> Result<Cpa, Error>
+where
+    T: Sample + Copy + Sync,
+    <T as Sample>::Container: Send + Sync,
+    P: Into<usize> + Copy + Sync,
+    F: Fn(usize, usize) -> usize + Send + Sync + Copy,
+    Pr: Progress + ?Sized,
 {
     assert_eq!(traces.shape()[0], plaintexts.shape()[0]);
     assert!(batch_size > 0);
 
-    // From benchmarks fold + reduce_with is faster than map + reduce/reduce_with and fold + reduce
-    zip(
-        traces.axis_chunks_iter(Axis(0), batch_size),
-        plaintexts.axis_chunks_iter(Axis(0), batch_size),
-    )
-    .par_bridge()
-    .fold(
+    let cpa = par_fold_batches(
+        zip(
+            traces.axis_chunks_iter(Axis(0), batch_size),
+            plaintexts.axis_chunks_iter(Axis(0), batch_size),
+        ),
+        traces.shape()[0],
+        progress,
         || CpaProcessor::new(traces.shape()[1], guess_range),
-        |mut cpa, (trace_batch, plaintext_batch)| {
+        |cpa, (trace_batch, plaintext_batch)| {
             cpa.batch_update(
                 trace_batch,
                 plaintext_batch.column(target_byte),
                 &leakage_model,
             );
 
-            cpa
+            trace_batch.shape()[0]
         },
-    )
-    .reduce_with(|a, b| a.combine(b))
-    .unwrap()
-    .finalize(leakage_model)
+        |a, b| a.combine(b),
+    )?;
+
+    Ok(cpa.finalize(leakage_model))
 }
 
 /// A processor that computes the [`Cpa`] of the given traces.
@@ -127,7 +182,7 @@ where
 /// It implements algorithm 4 from [^1].
 ///
 /// [^1]: <https://eprint.iacr.org/2013/794.pdf>
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct CpaProcessor<T>
 where
     T: Sample,
@@ -174,6 +229,16 @@ where
         }
     }
 
+    /// Return the number of samples per trace handled.
+    pub fn num_samples(&self) -> usize {
+        self.num_samples
+    }
+
+    /// Return the guess range upper exclusive bound.
+    pub fn guess_range(&self) -> usize {
+        self.guess_range
+    }
+
     /// Determine if two [`CpaProcessor`] are compatible to be merged.
     ///
     /// If they were created with the same parameters, they are compatible.
@@ -277,7 +342,10 @@ where
             }
         }
 
-        Cpa { corr }
+        Cpa {
+            corr,
+            num_traces: self.num_traces,
+        }
     }
 
     /// Merge computations of two [`CpaProcessor`]. Processors need to be compatible to be merged
@@ -371,6 +439,33 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_cpa_corr_confidence_interval() {
+        let traces = array![
+            [77u8, 137, 51, 91],
+            [72, 61, 91, 83],
+            [39, 49, 52, 23],
+            [26, 114, 63, 45],
+            [30, 8, 97, 91],
+            [13, 68, 7, 45],
+            [17, 181, 60, 34],
+            [43, 88, 76, 78],
+            [0, 36, 35, 0],
+            [93, 191, 49, 26],
+        ];
+        let plaintexts = array![[1usize], [3], [1], [2], [3], [2], [2], [1], [3], [1]];
+
+        let leakage_model = |plaintext, guess| plaintext ^ guess;
+        let cpa = cpa(traces.view(), plaintexts.view(), 4, 0, leakage_model, 2);
+        assert_eq!(cpa.num_traces(), traces.shape()[0]);
+
+        let ci = cpa.corr_confidence_interval(0.95);
+        assert_eq!(ci.estimate, cpa.corr());
+        for ((lower, upper), corr) in ci.lower.iter().zip(ci.upper.iter()).zip(cpa.corr().iter()) {
+            assert!(lower <= corr && corr <= upper);
+        }
+    }
+
     #[test]
     fn test_serialize_deserialize_processor() {
         let traces = array![
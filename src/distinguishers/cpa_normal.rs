@@ -1,10 +1,13 @@
 use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis};
 use num_traits::AsPrimitive;
-use rayon::iter::{ParallelBridge, ParallelIterator};
 use serde::{Deserialize, Serialize};
 use std::{fs::File, iter::zip, path::Path};
 
-use crate::{Error, Sample, distinguishers::cpa::Cpa};
+use crate::{
+    Error, Sample,
+    distinguishers::cpa::Cpa,
+    progress::{Progress, par_fold_batches, without_progress},
+};
 
 /// Compute the [`Cpa`] of the given traces using [`CpaProcessor`].
 ///
@@ -56,26 +59,62 @@ where
     <T as Sample>::Container: Send,
     P: Into<usize> + Copy + Sync,
     F: Fn(ArrayView1<usize>, usize) -> usize + Send + Sync + Copy,
+{
+    without_progress(|progress| {
+        cpa_with_progress(
+            traces,
+            plaintexts,
+            guess_range,
+            leakage_model,
+            batch_size,
+            progress,
+        )
+    })
+}
+
+/// Compute the [`Cpa`] of the given traces like [`cpa`], reporting progress to `progress`.
+///
+/// # Errors
+/// Return [`Error::Cancelled`] if `progress` is cancelled before all traces are processed.
+///
+/// # Panics
+/// - Panic if `traces.shape()[0] != plaintexts.shape()[0]`
+/// - Panic if `batch_size` is 0.
+pub fn cpa_with_progress<T, P, F, Pr>(
+    traces: ArrayView2<T>,
+    plaintexts: ArrayView2<P>,
+    guess_range: usize,
+    leakage_model: F,
+    batch_size: usize,
+    progress: &Pr,
+) -> Result<Cpa, Error>
+where
+    T: Sample + Copy + Sync,
+    <T as Sample>::Container: Send,
+    P: Into<usize> + Copy + Sync,
+    F: Fn(ArrayView1<usize>, usize) -> usize + Send + Sync + Copy,
+    Pr: Progress + ?Sized,
 {
     assert_eq!(traces.shape()[0], plaintexts.shape()[0]);
     assert!(batch_size > 0);
 
-    zip(
-        traces.axis_chunks_iter(Axis(0), batch_size),
-        plaintexts.axis_chunks_iter(Axis(0), batch_size),
-    )
-    .par_bridge()
-    .fold(
+    let cpa = par_fold_batches(
+        zip(
+            traces.axis_chunks_iter(Axis(0), batch_size),
+            plaintexts.axis_chunks_iter(Axis(0), batch_size),
+        ),
+        traces.shape()[0],
+        progress,
         || CpaProcessor::new(traces.shape()[1], batch_size, guess_range),
-        |mut cpa, (trace_batch, plaintext_batch)| {
+        |cpa, (trace_batch, plaintext_batch)| {
             cpa.batch_update(trace_batch, plaintext_batch, leakage_model);
 
-            cpa
+            trace_batch.shape()[0]
         },
-    )
-    .reduce_with(|x, y| x.combine(y))
-    .unwrap()
-    .finalize()
+        |x, y| x.combine(y),
+    )?;
+
+    Ok(cpa.finalize())
 }
 
 /// A processor that computes the [`Cpa`] of the given traces.
@@ -209,7 +248,10 @@ where
             }
         }
 
-        Cpa { corr }
+        Cpa {
+            corr,
+            num_traces: self.num_traces,
+        }
     }
 
     /// Merge computations of two [`CpaProcessor`]. Processors need to be compatible to be merged
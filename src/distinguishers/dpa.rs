@@ -1,11 +1,11 @@
 use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis};
 use num_traits::AsPrimitive;
-use rayon::iter::{ParallelBridge, ParallelIterator};
 use serde::{Deserialize, Serialize};
 use std::{fs::File, iter::zip, marker::PhantomData, path::Path};
 
 use crate::{
     Error, Sample,
+    progress::{Progress, par_fold_batches, without_progress},
     util::{argmax_by, argsort_by, max_per_row},
 };
 
@@ -69,25 +69,60 @@ where
     <T as Sample>::Container: Send,
     M: Clone + Send + Sync,
     F: Fn(M, usize) -> bool + Send + Sync + Copy,
+{
+    without_progress(|progress| {
+        dpa_with_progress(
+            traces,
+            metadata,
+            guess_range,
+            selection_function,
+            batch_size,
+            progress,
+        )
+    })
+}
+
+/// Compute the [`Dpa`] of the given traces like [`dpa`], reporting progress to `progress`.
+///
+/// # Errors
+/// Return [`Error::Cancelled`] if `progress` is cancelled before all traces are processed.
+///
+/// # Panics
+/// Panic if `batch_size` is not strictly positive.
+pub fn dpa_with_progress<T, M, F, P>(
+    traces: ArrayView2<T>,
+    metadata: ArrayView1<M>,
+    guess_range: usize,
+    selection_function: F,
+    batch_size: usize,
+    progress: &P,
+) -> Result<Dpa, Error>
+where
+    T: Sample + Copy + Sync,
+    <T as Sample>::Container: Send,
+    M: Clone + Send + Sync,
+    F: Fn(M, usize) -> bool + Send + Sync + Copy,
+    P: Progress + ?Sized,
 {
     assert!(batch_size > 0);
 
-    zip(
-        traces.axis_chunks_iter(Axis(0), batch_size),
-        metadata.axis_chunks_iter(Axis(0), batch_size),
-    )
-    .par_bridge()
-    .fold(
+    let dpa = par_fold_batches(
+        zip(
+            traces.axis_chunks_iter(Axis(0), batch_size),
+            metadata.axis_chunks_iter(Axis(0), batch_size),
+        ),
+        traces.shape()[0],
+        progress,
         || DpaProcessor::new(traces.shape()[1], guess_range),
-        |mut dpa, (trace_batch, metadata_batch)| {
+        |dpa, (trace_batch, metadata_batch)| {
             dpa.batch_update(trace_batch, metadata_batch, &selection_function);
 
-            dpa
+            trace_batch.shape()[0]
         },
-    )
-    .reduce_with(|a, b| a.combine(b))
-    .unwrap()
-    .finalize()
+        |a, b| a.combine(b),
+    )?;
+
+    Ok(dpa.finalize())
 }
 
 /// Result of the DPA[^1] on some traces.
@@ -126,7 +161,7 @@ impl Dpa {
 ///
 /// [^1]: <https://paulkocher.com/doc/DifferentialPowerAnalysis.pdf>
 /// [^2]: <https://web.mit.edu/6.857/OldStuff/Fall03/ref/kocher-DPATechInfo.pdf>
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct DpaProcessor<T, M>
 where
     T: Sample,
@@ -170,6 +205,16 @@ where
         }
     }
 
+    /// Return the number of samples per trace handled.
+    pub fn num_samples(&self) -> usize {
+        self.num_samples
+    }
+
+    /// Return the guess range upper exclusive bound.
+    pub fn guess_range(&self) -> usize {
+        self.guess_range
+    }
+
     /// # Panics
     /// Panic in debug if `trace.shape()[0] != self.num_samples`.
     pub fn update<F>(&mut self, trace: ArrayView1<T>, metadata: M, selection_function: F)
@@ -7,4 +7,8 @@ pub enum Error {
     SaveLoadError(#[from] serde_json::Error),
     #[error(transparent)]
     IoError(#[from] io::Error),
+    #[error("Computation cancelled")]
+    Cancelled,
+    #[error("Checkpointer poisoned by a failed batch, it must be reopened")]
+    Poisoned,
 }
@@ -1,9 +1,13 @@
 //! Leakage detection methods
 
-use crate::{Error, Sample, processors::MeanVar};
-use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis};
+use crate::{
+    Error, Sample,
+    processors::MeanVar,
+    progress::{Progress, par_fold_batches, without_progress},
+    statistics::{ConfidenceInterval, bootstrap},
+};
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis, Ix1};
 use num_traits::AsPrimitive;
-use rayon::iter::{ParallelBridge, ParallelIterator};
 use serde::{Deserialize, Serialize};
 use std::{fs::File, iter::zip, path::Path};
 
@@ -55,32 +59,114 @@ where
     T: Sample + Copy + Sync,
     <T as Sample>::Container: Send,
     F: Fn(usize) -> usize + Sync,
+{
+    without_progress(|progress| snr_with_progress(traces, classes, get_class, batch_size, progress))
+}
+
+/// Compute the SNR of the given traces like [`snr`], reporting progress to `progress`.
+///
+/// # Errors
+/// Return [`Error::Cancelled`] if `progress` is cancelled before all traces are processed.
+///
+/// # Panics
+/// - Panic if `batch_size` is 0.
+pub fn snr_with_progress<T, F, P>(
+    traces: ArrayView2<T>,
+    classes: usize,
+    get_class: F,
+    batch_size: usize,
+    progress: &P,
+) -> Result<Array1<f32>, Error>
+where
+    T: Sample + Copy + Sync,
+    <T as Sample>::Container: Send,
+    F: Fn(usize) -> usize + Sync,
+    P: Progress + ?Sized,
 {
     assert!(batch_size > 0);
 
-    // From benchmarks fold + reduce_with is faster than map + reduce/reduce_with and fold + reduce
-    traces
-        .axis_chunks_iter(Axis(0), batch_size)
-        .enumerate()
-        .par_bridge()
-        .fold(
-            || SnrProcessor::new(traces.shape()[1], classes),
-            |mut snr, (batch_idx, trace_batch)| {
-                for i in 0..trace_batch.shape()[0] {
-                    snr.process(trace_batch.row(i), get_class(batch_idx * batch_size + i));
-                }
-                snr
-            },
-        )
-        .reduce_with(|a, b| a.combine(b))
-        .unwrap()
-        .snr()
+    let snr = par_fold_batches(
+        traces.axis_chunks_iter(Axis(0), batch_size).enumerate(),
+        traces.shape()[0],
+        progress,
+        || SnrProcessor::new(traces.shape()[1], classes),
+        |snr, (batch_idx, trace_batch)| {
+            for i in 0..trace_batch.shape()[0] {
+                snr.process(trace_batch.row(i), get_class(batch_idx * batch_size + i));
+            }
+
+            trace_batch.shape()[0]
+        },
+        |a, b| a.combine(b),
+    )?;
+
+    Ok(snr.snr())
+}
+
+/// Compute the SNR of the given sub-processors along with its percentile bootstrap confidence
+/// interval at the given `confidence` level (e.g. 0.95).
+///
+/// Sub-processors are resampled with replacement `num_resamples` times (see
+/// [`crate::statistics::bootstrap`]), they should thus be computed on disjoint sets of traces of
+/// similar sizes, such as batches of traces.
+///
+/// # Examples
+/// ```
+/// use muscat::leakage_detection::{SnrProcessor, snr_bootstrap};
+/// use ndarray::array;
+///
+/// let traces = array![
+///     [77, 137, 51, 91],
+///     [72, 61, 91, 83],
+///     [39, 49, 52, 23],
+///     [26, 114, 63, 45],
+///     [30, 8, 97, 91],
+///     [13, 68, 7, 45],
+///     [17, 181, 60, 34],
+///     [43, 88, 76, 78],
+/// ];
+/// let classes = [0, 1, 0, 1, 1, 0, 0, 1];
+///
+/// let mut processors = Vec::new();
+/// for (batch_idx, trace_batch) in traces.outer_iter().collect::<Vec<_>>().chunks(2).enumerate() {
+///     let mut processor = SnrProcessor::new(traces.shape()[1], 2);
+///     for (i, trace) in trace_batch.iter().enumerate() {
+///         processor.process(trace.view(), classes[batch_idx * 2 + i]);
+///     }
+///     processors.push(processor);
+/// }
+///
+/// let snr = snr_bootstrap(&processors, 100, 0.95, 0);
+/// ```
+///
+/// # Panics
+/// - Panic if `processors` is empty.
+/// - Panic if `num_resamples` is 0.
+/// - Panic if `confidence` is not in the open interval (0, 1).
+pub fn snr_bootstrap<T>(
+    processors: &[SnrProcessor<T>],
+    num_resamples: usize,
+    confidence: f32,
+    seed: u64,
+) -> ConfidenceInterval<Ix1>
+where
+    T: Sample + Copy + Sync,
+    <T as Sample>::Container: Sync,
+{
+    bootstrap(
+        processors,
+        num_resamples,
+        confidence,
+        seed,
+        SnrProcessor::combine,
+        SnrProcessor::snr,
+    )
 }
 
 /// A processor that computes the Signal-to-Noise Ratio[^1] (SNR) of the given traces.
 ///
 /// [^1]: <https://en.wikipedia.org/wiki/Signal-to-noise_ratio>
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SnrProcessor<T>
 where
     T: Sample,
@@ -94,6 +180,12 @@ where
     classes_sum: Array2<<T as Sample>::Container>,
     /// Counts the number of traces per class
     classes_count: Array1<usize>,
+    /// Sum of square of traces per class, only accumulated when created with
+    /// [`SnrProcessor::with_classes_var`]
+    #[serde(default = "Option::default")]
+    #[serde(bound(serialize = "<T as Sample>::Container: Serialize"))]
+    #[serde(bound(deserialize = "<T as Sample>::Container: Deserialize<'de>"))]
+    classes_sum_squares: Option<Array2<<T as Sample>::Container>>,
 }
 
 impl<T> SnrProcessor<T>
@@ -111,6 +203,21 @@ where
             mean_var: MeanVar::new(trace_length),
             classes_sum: Array2::zeros((num_classes, trace_length)),
             classes_count: Array1::zeros(num_classes),
+            classes_sum_squares: None,
+        }
+    }
+
+    /// Create a new [`SnrProcessor`] that also accumulates the second moment of each class, allowing
+    /// to retrieve the per-class variances with [`SnrProcessor::classes_var`].
+    ///
+    /// # Arguments
+    ///
+    /// - `trace_length`: number of samples per trace.
+    /// - `num_classes`: number of classes.
+    pub fn with_classes_var(trace_length: usize, num_classes: usize) -> Self {
+        Self {
+            classes_sum_squares: Some(Array2::zeros((num_classes, trace_length))),
+            ..Self::new(trace_length, num_classes)
         }
     }
 
@@ -128,6 +235,13 @@ where
             self.classes_sum[[class, i]] += trace[i].into();
         }
 
+        if let Some(classes_sum_squares) = self.classes_sum_squares.as_mut() {
+            for i in 0..trace.shape()[0] {
+                let x: <T as Sample>::Container = trace[i].into();
+                classes_sum_squares[[class, i]] += x * x;
+            }
+        }
+
         self.classes_count[class] += 1;
     }
 
@@ -174,6 +288,43 @@ where
         self.classes_count.shape()[0]
     }
 
+    /// Return the mean of each class as a `(num_classes, trace_length)` matrix.
+    ///
+    /// Rows of classes without any trace are filled with NaN.
+    pub fn classes_mean(&self) -> Array2<f32> {
+        let mut classes_mean = self.classes_sum.mapv(|x| x.as_());
+        for (mut row, &count) in zip(classes_mean.rows_mut(), self.classes_count.iter()) {
+            row /= count as f32;
+        }
+
+        classes_mean
+    }
+
+    /// Return the variance of each class as a `(num_classes, trace_length)` matrix, or `None` if
+    /// the processor was not created with [`SnrProcessor::with_classes_var`].
+    ///
+    /// Rows of classes without any trace are filled with NaN.
+    pub fn classes_var(&self) -> Option<Array2<f32>> {
+        let classes_sum_squares = self.classes_sum_squares.as_ref()?;
+
+        let mut classes_var = Array2::zeros(classes_sum_squares.raw_dim());
+        for class in 0..self.num_classes() {
+            let count = self.classes_count[class] as f32;
+            for i in 0..self.trace_length() {
+                let mean = self.classes_sum[[class, i]].as_() / count;
+                classes_var[[class, i]] =
+                    classes_sum_squares[[class, i]].as_() / count - mean * mean;
+            }
+        }
+
+        Some(classes_var)
+    }
+
+    /// Return the number of traces processed per class.
+    pub fn classes_count(&self) -> ArrayView1<'_, usize> {
+        self.classes_count.view()
+    }
+
     /// Merge computations of two [`SnrProcessor`]. Processors need to be compatible to be merged
     /// together, otherwise it can panic or yield incoherent result (see
     /// [`SnrProcessor::is_compatible_with`]).
@@ -187,6 +338,10 @@ where
             mean_var: self.mean_var.combine(rhs.mean_var),
             classes_sum: self.classes_sum + rhs.classes_sum,
             classes_count: self.classes_count + rhs.classes_count,
+            classes_sum_squares: match (self.classes_sum_squares, rhs.classes_sum_squares) {
+                (Some(lhs), Some(rhs)) => Some(lhs + rhs),
+                _ => None,
+            },
         }
     }
 
@@ -194,7 +349,9 @@ where
     ///
     /// If they were created with the same parameters, they are compatible.
     fn is_compatible_with(&self, other: &Self) -> bool {
-        self.trace_length() == other.trace_length() && self.num_classes() == other.num_classes()
+        self.trace_length() == other.trace_length()
+            && self.num_classes() == other.num_classes()
+            && self.classes_sum_squares.is_some() == other.classes_sum_squares.is_some()
     }
 }
 
@@ -283,32 +440,56 @@ where
     T: Sample + Copy + Sync,
     <T as Sample>::Container: Send,
     F: Fn(usize) -> usize + Sync,
+{
+    without_progress(|progress| {
+        nicv_with_progress(traces, classes, get_class, batch_size, progress)
+    })
+}
+
+/// Compute the NICV of the given traces like [`nicv`], reporting progress to `progress`.
+///
+/// # Errors
+/// Return [`Error::Cancelled`] if `progress` is cancelled before all traces are processed.
+///
+/// # Panics
+/// - Panic if `batch_size` is 0.
+pub fn nicv_with_progress<T, F, P>(
+    traces: ArrayView2<T>,
+    classes: usize,
+    get_class: F,
+    batch_size: usize,
+    progress: &P,
+) -> Result<Array1<f32>, Error>
+where
+    T: Sample + Copy + Sync,
+    <T as Sample>::Container: Send,
+    F: Fn(usize) -> usize + Sync,
+    P: Progress + ?Sized,
 {
     assert!(batch_size > 0);
 
-    // From benchmarks fold + reduce_with is faster than map + reduce/reduce_with and fold + reduce
-    traces
-        .axis_chunks_iter(Axis(0), batch_size)
-        .enumerate()
-        .par_bridge()
-        .fold(
-            || NicvProcessor::new(traces.shape()[1], classes),
-            |mut nicv, (batch_idx, trace_batch)| {
-                for i in 0..trace_batch.shape()[0] {
-                    nicv.process(trace_batch.row(i), get_class(batch_idx * batch_size + i));
-                }
-                nicv
-            },
-        )
-        .reduce_with(|a, b| a.combine(b))
-        .unwrap()
-        .nicv()
+    let nicv = par_fold_batches(
+        traces.axis_chunks_iter(Axis(0), batch_size).enumerate(),
+        traces.shape()[0],
+        progress,
+        || NicvProcessor::new(traces.shape()[1], classes),
+        |nicv, (batch_idx, trace_batch)| {
+            for i in 0..trace_batch.shape()[0] {
+                nicv.process(trace_batch.row(i), get_class(batch_idx * batch_size + i));
+            }
+
+            trace_batch.shape()[0]
+        },
+        |a, b| a.combine(b),
+    )?;
+
+    Ok(nicv.nicv())
 }
 
 /// A processor that computes the Normalized Inter-Class Variance[^1] (NICV) of given traces.
 ///
 /// [^1]: <https://eprint.iacr.org/2014/1020.pdf>
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct NicvProcessor<T>
 where
     T: Sample,
@@ -322,6 +503,12 @@ where
     classes_sum: Array2<<T as Sample>::Container>,
     /// Counts the number of traces per class
     classes_count: Array1<usize>,
+    /// Sum of square of traces per class, only accumulated when created with
+    /// [`NicvProcessor::with_classes_var`]
+    #[serde(default = "Option::default")]
+    #[serde(bound(serialize = "<T as Sample>::Container: Serialize"))]
+    #[serde(bound(deserialize = "<T as Sample>::Container: Deserialize<'de>"))]
+    classes_sum_squares: Option<Array2<<T as Sample>::Container>>,
 }
 
 impl<T> NicvProcessor<T>
@@ -339,6 +526,21 @@ where
             mean_var: MeanVar::new(trace_length),
             classes_sum: Array2::zeros((num_classes, trace_length)),
             classes_count: Array1::zeros(num_classes),
+            classes_sum_squares: None,
+        }
+    }
+
+    /// Create a new [`NicvProcessor`] that also accumulates the second moment of each class, allowing
+    /// to retrieve the per-class variances with [`NicvProcessor::classes_var`].
+    ///
+    /// # Arguments
+    ///
+    /// - `trace_length`: number of samples per trace.
+    /// - `num_classes`: number of classes.
+    pub fn with_classes_var(trace_length: usize, num_classes: usize) -> Self {
+        Self {
+            classes_sum_squares: Some(Array2::zeros((num_classes, trace_length))),
+            ..Self::new(trace_length, num_classes)
         }
     }
 
@@ -356,6 +558,13 @@ where
             self.classes_sum[[class, i]] += trace[i].into();
         }
 
+        if let Some(classes_sum_squares) = self.classes_sum_squares.as_mut() {
+            for i in 0..trace.shape()[0] {
+                let x: <T as Sample>::Container = trace[i].into();
+                classes_sum_squares[[class, i]] += x * x;
+            }
+        }
+
         self.classes_count[class] += 1;
     }
 
@@ -396,6 +605,43 @@ where
         self.classes_count.shape()[0]
     }
 
+    /// Return the mean of each class as a `(num_classes, trace_length)` matrix.
+    ///
+    /// Rows of classes without any trace are filled with NaN.
+    pub fn classes_mean(&self) -> Array2<f32> {
+        let mut classes_mean = self.classes_sum.mapv(|x| x.as_());
+        for (mut row, &count) in zip(classes_mean.rows_mut(), self.classes_count.iter()) {
+            row /= count as f32;
+        }
+
+        classes_mean
+    }
+
+    /// Return the variance of each class as a `(num_classes, trace_length)` matrix, or `None` if
+    /// the processor was not created with [`NicvProcessor::with_classes_var`].
+    ///
+    /// Rows of classes without any trace are filled with NaN.
+    pub fn classes_var(&self) -> Option<Array2<f32>> {
+        let classes_sum_squares = self.classes_sum_squares.as_ref()?;
+
+        let mut classes_var = Array2::zeros(classes_sum_squares.raw_dim());
+        for class in 0..self.num_classes() {
+            let count = self.classes_count[class] as f32;
+            for i in 0..self.trace_length() {
+                let mean = self.classes_sum[[class, i]].as_() / count;
+                classes_var[[class, i]] =
+                    classes_sum_squares[[class, i]].as_() / count - mean * mean;
+            }
+        }
+
+        Some(classes_var)
+    }
+
+    /// Return the number of traces processed per class.
+    pub fn classes_count(&self) -> ArrayView1<'_, usize> {
+        self.classes_count.view()
+    }
+
     /// Merge computations of two [`NicvProcessor`]. Processors need to be compatible to be merged
     /// together, otherwise it can panic or yield incoherent result (see
     /// [`NicvProcessor::is_compatible_with`]).
@@ -409,6 +655,10 @@ where
             mean_var: self.mean_var.combine(rhs.mean_var),
             classes_sum: self.classes_sum + rhs.classes_sum,
             classes_count: self.classes_count + rhs.classes_count,
+            classes_sum_squares: match (self.classes_sum_squares, rhs.classes_sum_squares) {
+                (Some(lhs), Some(rhs)) => Some(lhs + rhs),
+                _ => None,
+            },
         }
     }
 
@@ -416,7 +666,9 @@ where
     ///
     /// If they were created with the same parameters, they are compatible.
     fn is_compatible_with(&self, other: &Self) -> bool {
-        self.trace_length() == other.trace_length() && self.num_classes() == other.num_classes()
+        self.trace_length() == other.trace_length()
+            && self.num_classes() == other.num_classes()
+            && self.classes_sum_squares.is_some() == other.classes_sum_squares.is_some()
     }
 }
 
@@ -455,33 +707,58 @@ pub fn ttest<T>(
 where
     T: Sample + Copy + Sync,
     <T as Sample>::Container: Send,
+{
+    without_progress(|progress| ttest_with_progress(traces, trace_classes, batch_size, progress))
+}
+
+/// Compute the Welch's T-test of the given traces like [`ttest`], reporting progress to
+/// `progress`.
+///
+/// # Errors
+/// Return [`Error::Cancelled`] if `progress` is cancelled before all traces are processed.
+///
+/// # Panics
+/// - Panic if `traces.shape()[0] != trace_classes.shape()[0]`
+/// - Panic if `batch_size` is 0.
+pub fn ttest_with_progress<T, P>(
+    traces: ArrayView2<T>,
+    trace_classes: ArrayView1<bool>,
+    batch_size: usize,
+    progress: &P,
+) -> Result<Array1<f32>, Error>
+where
+    T: Sample + Copy + Sync,
+    <T as Sample>::Container: Send,
+    P: Progress + ?Sized,
 {
     assert_eq!(traces.shape()[0], trace_classes.shape()[0]);
     assert!(batch_size > 0);
 
-    zip(
-        traces.axis_chunks_iter(Axis(0), batch_size),
-        trace_classes.axis_chunks_iter(Axis(0), batch_size),
-    )
-    .par_bridge()
-    .fold(
+    let ttest = par_fold_batches(
+        zip(
+            traces.axis_chunks_iter(Axis(0), batch_size),
+            trace_classes.axis_chunks_iter(Axis(0), batch_size),
+        ),
+        traces.shape()[0],
+        progress,
         || TTestProcessor::new(traces.shape()[1]),
-        |mut ttest, (trace_batch, trace_classes_batch)| {
+        |ttest, (trace_batch, trace_classes_batch)| {
             for i in 0..trace_batch.shape()[0] {
                 ttest.process(trace_batch.row(i), trace_classes_batch[i]);
             }
-            ttest
+
+            trace_batch.shape()[0]
         },
-    )
-    .reduce_with(|a, b| a.combine(b))
-    .unwrap()
-    .ttest()
+        |a, b| a.combine(b),
+    )?;
+
+    Ok(ttest.ttest())
 }
 
 /// A processor that computes the Welch's T-Test[^1] of the given traces.
 ///
 /// [^1]: <https://en.wikipedia.org/wiki/Welch%27s_t-test>
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct TTestProcessor<T>
 where
     T: Sample,
@@ -605,8 +882,9 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::{NicvProcessor, SnrProcessor, TTestProcessor, nicv, snr, ttest};
-    use ndarray::array;
+    use super::{NicvProcessor, SnrProcessor, TTestProcessor, nicv, snr, snr_bootstrap, ttest};
+    use crate::processors::MeanVar;
+    use ndarray::{Axis, array};
 
     #[test]
     fn test_snr_helper() {
@@ -631,6 +909,97 @@ mod tests {
         assert_eq!(processor.snr(), snr(traces.view(), 256, |i| classes[i], 2));
     }
 
+    #[test]
+    fn test_snr_classes_var() {
+        let traces = array![
+            [77, 137, 51, 91],
+            [72, 61, 91, 83],
+            [39, 49, 52, 23],
+            [26, 114, 63, 45],
+            [30, 8, 97, 91],
+            [13, 68, 7, 45],
+            [17, 181, 60, 34],
+            [43, 88, 76, 78],
+            [0, 36, 35, 0],
+            [93, 191, 49, 26],
+        ];
+        let classes = [1, 3, 1, 2, 3, 2, 2, 1, 3, 1];
+
+        let mut processor1 = SnrProcessor::with_classes_var(traces.shape()[1], 4);
+        let mut processor2 = SnrProcessor::with_classes_var(traces.shape()[1], 4);
+        let mut classes_mean_var = [
+            MeanVar::new(traces.shape()[1]),
+            MeanVar::new(traces.shape()[1]),
+            MeanVar::new(traces.shape()[1]),
+            MeanVar::new(traces.shape()[1]),
+        ];
+        for (i, (trace, class)) in std::iter::zip(traces.rows(), classes.iter()).enumerate() {
+            if i < 5 {
+                processor1.process(trace, *class);
+            } else {
+                processor2.process(trace, *class);
+            }
+            classes_mean_var[*class].process(trace);
+        }
+        let processor = processor1.combine(processor2);
+
+        let classes_mean = processor.classes_mean();
+        let classes_var = processor.classes_var().unwrap();
+        assert_eq!(processor.classes_count(), array![0, 4, 3, 3]);
+        assert!(classes_mean.row(0).iter().all(|x| x.is_nan()));
+        for (class, mean_var) in classes_mean_var.iter().enumerate().skip(1) {
+            assert_eq!(classes_mean.row(class), mean_var.mean());
+            assert_eq!(classes_var.row(class), mean_var.var());
+        }
+
+        // Tracking per-class variance does not affect the SNR
+        let mut processor_without_var = SnrProcessor::new(traces.shape()[1], 4);
+        for (trace, class) in std::iter::zip(traces.rows(), classes.iter()) {
+            processor_without_var.process(trace, *class);
+        }
+        assert!(processor_without_var.classes_var().is_none());
+        assert_eq!(processor.snr(), processor_without_var.snr());
+    }
+
+    #[test]
+    fn test_snr_bootstrap() {
+        let traces = array![
+            [77, 137, 51, 91],
+            [72, 61, 91, 83],
+            [39, 49, 52, 23],
+            [26, 114, 63, 45],
+            [30, 8, 97, 91],
+            [13, 68, 7, 45],
+            [17, 181, 60, 34],
+            [43, 88, 76, 78],
+            [0, 36, 35, 0],
+            [93, 191, 49, 26],
+        ];
+        let classes = [0, 1, 0, 1, 1, 0, 0, 1, 1, 0];
+
+        let mut processors = Vec::new();
+        let mut full_processor = SnrProcessor::new(traces.shape()[1], 2);
+        for (batch_idx, trace_batch) in traces.axis_chunks_iter(Axis(0), 2).enumerate() {
+            let mut processor = SnrProcessor::new(traces.shape()[1], 2);
+            for (i, trace) in trace_batch.rows().into_iter().enumerate() {
+                processor.process(trace, classes[batch_idx * 2 + i]);
+                full_processor.process(trace, classes[batch_idx * 2 + i]);
+            }
+            processors.push(processor);
+        }
+
+        let ci = snr_bootstrap(&processors, 200, 0.9, 0);
+        assert_eq!(ci.estimate, full_processor.snr());
+        for i in 0..traces.shape()[1] {
+            assert!(ci.lower[i] <= ci.upper[i]);
+        }
+
+        // Same seed yields the same interval
+        let ci2 = snr_bootstrap(&processors, 200, 0.9, 0);
+        assert_eq!(ci.lower, ci2.lower);
+        assert_eq!(ci.upper, ci2.upper);
+    }
+
     #[test]
     fn test_ttest() {
         let traces = [
@@ -713,6 +1082,40 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_nicv_classes_var() {
+        let traces = array![
+            [77, 137, 51, 91],
+            [72, 61, 91, 83],
+            [39, 49, 52, 23],
+            [26, 114, 63, 45],
+            [30, 8, 97, 91],
+            [13, 68, 7, 45],
+            [17, 181, 60, 34],
+            [43, 88, 76, 78],
+            [0, 36, 35, 0],
+            [93, 191, 49, 26],
+        ];
+        let classes = [0, 1, 0, 1, 1, 0, 0, 1, 1, 0];
+
+        let mut processor = NicvProcessor::with_classes_var(traces.shape()[1], 2);
+        let mut classes_mean_var = [
+            MeanVar::new(traces.shape()[1]),
+            MeanVar::new(traces.shape()[1]),
+        ];
+        for (trace, class) in std::iter::zip(traces.rows(), classes.iter()) {
+            processor.process(trace, *class);
+            classes_mean_var[*class].process(trace);
+        }
+
+        let classes_mean = processor.classes_mean();
+        let classes_var = processor.classes_var().unwrap();
+        for (class, mean_var) in classes_mean_var.iter().enumerate() {
+            assert_eq!(classes_mean.row(class), mean_var.mean());
+            assert_eq!(classes_var.row(class), mean_var.var());
+        }
+    }
+
     #[test]
     fn test_nicv_bounds() {
         let traces = array![
@@ -71,15 +71,21 @@ pub use ndarray;
 pub use serde;
 
 pub mod asymmetric;
+pub mod checkpoint;
+pub mod chipwhisperer;
 pub mod distinguishers;
 pub mod error;
 pub mod leakage_detection;
 pub mod leakage_model;
+pub mod npy;
 pub mod preprocessors;
 pub mod processors;
+pub mod progress;
 #[cfg(feature = "quicklog")]
 pub mod quicklog;
+pub mod statistics;
 pub mod trace;
+pub mod trs;
 pub mod util;
 
 use std::ops::{Add, AddAssign, Mul};
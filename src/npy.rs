@@ -0,0 +1,1001 @@
+//! Read and write traces and results in `.npy` and `.npz` files.
+//!
+//! Traces are expected to be stored as a 2D array, one trace per row. [`MmapNpy`] maps the file
+//! in memory and hands out [`ArrayView2`] chunks without copying, which requires little-endian
+//! data in C order. [`ChunkedNpyReader`] reads chunks of traces in buffers and supports any
+//! layout. [`NpyTraces`] picks the best of both for a given file.
+//!
+//! [`NpyWriter`] appends traces to a `.npy` file as they are produced. Results of the
+//! distinguishers and leakage detection processors can be exported to `.npz` archives with
+//! [`NpzExport`].
+
+use memmap2::Mmap;
+use ndarray::{
+    Array0, Array2, ArrayView, ArrayView1, ArrayView2, Axis, Dimension, ShapeBuilder, s,
+};
+use npyz::{DType, NpyHeader, Order};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    mem,
+    path::Path,
+};
+use thiserror::Error;
+use zip::{CompressionMethod, ZipWriter, result::ZipError, write::SimpleFileOptions};
+
+use crate::{
+    Sample,
+    distinguishers::{cpa::Cpa, dpa::Dpa},
+    leakage_detection::{NicvProcessor, SnrProcessor, TTestProcessor},
+};
+
+/// Errors raised when reading or writing `.npy` and `.npz` files.
+#[derive(Error, Debug)]
+pub enum NpyError {
+    #[error("IO error")]
+    IoError(#[from] io::Error),
+    #[error("Invalid npy header: {0}")]
+    InvalidHeader(String),
+    #[error("Unsupported dtype {found}, expected {expected}")]
+    DtypeMismatch {
+        expected: &'static str,
+        found: String,
+    },
+    #[error("Expected a 2D array of addressable size, found shape {0:?}")]
+    InvalidShape(Vec<u64>),
+    #[error("File cannot be memory-mapped: {0}")]
+    NotMappable(&'static str),
+    #[error("Zip error")]
+    ZipError(#[from] ZipError),
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+/// Sample types that can be read from and written to `.npy` files.
+///
+/// This trait is sealed: memory-mapped data is reinterpreted as samples, which is only sound for
+/// plain numeric types.
+pub trait NpySample: private::Sealed + Copy + Default {
+    /// Type character and size of the npy dtype, without endianness.
+    const DTYPE: &'static str;
+
+    /// Decode a sample from its representation with the given endianness.
+    fn from_bytes(bytes: &[u8], big_endian: bool) -> Self;
+
+    /// Append the little-endian representation of the sample to `buffer`.
+    fn extend_le_bytes(self, buffer: &mut Vec<u8>);
+}
+
+macro_rules! impl_npy_sample {
+    ($($t:ty => $dtype:literal),*) => {
+        $(
+            impl private::Sealed for $t {}
+
+            impl NpySample for $t {
+                const DTYPE: &'static str = $dtype;
+
+                fn from_bytes(bytes: &[u8], big_endian: bool) -> Self {
+                    let bytes = bytes.try_into().unwrap();
+                    if big_endian {
+                        <$t>::from_be_bytes(bytes)
+                    } else {
+                        <$t>::from_le_bytes(bytes)
+                    }
+                }
+
+                fn extend_le_bytes(self, buffer: &mut Vec<u8>) {
+                    buffer.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_npy_sample! {
+    u8 => "u1", u16 => "u2", u32 => "u4", u64 => "u8",
+    i8 => "i1", i16 => "i2", i32 => "i4", i64 => "i8",
+    f32 => "f4", f64 => "f8"
+}
+
+/// Layout of the traces in a `.npy` file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Layout {
+    /// Offset of the data in the file
+    offset: u64,
+    /// Number of traces and number of samples per trace
+    shape: (usize, usize),
+    /// Size of the data in bytes
+    data_size: usize,
+    order: Order,
+    big_endian: bool,
+}
+
+impl Layout {
+    /// Parse the header of a `.npy` file holding samples of type `T`.
+    fn read<T: NpySample, R: Read + Seek>(reader: &mut R) -> Result<Self, NpyError> {
+        let start = reader.stream_position()?;
+        let header = NpyHeader::from_reader(&mut *reader)
+            .map_err(|err| NpyError::InvalidHeader(err.to_string()))?;
+        let offset = reader.stream_position()? - start;
+
+        let dtype_mismatch = |found: String| NpyError::DtypeMismatch {
+            expected: T::DTYPE,
+            found,
+        };
+        let big_endian = match header.dtype() {
+            DType::Plain(type_str) => {
+                let type_str = type_str.to_string();
+                let (endianness, dtype) = type_str.split_at(1);
+                if dtype != T::DTYPE {
+                    return Err(dtype_mismatch(type_str));
+                }
+                endianness == ">" || (endianness == "=" && cfg!(target_endian = "big"))
+            }
+            dtype => return Err(dtype_mismatch(dtype.descr())),
+        };
+
+        let invalid_shape = || NpyError::InvalidShape(header.shape().to_vec());
+        let [rows, columns] = *header.shape() else {
+            return Err(invalid_shape());
+        };
+        let rows = usize::try_from(rows).map_err(|_| invalid_shape())?;
+        let columns = usize::try_from(columns).map_err(|_| invalid_shape())?;
+        // Slices and arrays cannot be larger than `isize::MAX` bytes
+        let data_size = rows
+            .checked_mul(columns)
+            .and_then(|len| len.checked_mul(mem::size_of::<T>()))
+            .filter(|&size| size <= isize::MAX as usize)
+            .ok_or_else(invalid_shape)?;
+
+        Ok(Self {
+            offset,
+            shape: (rows, columns),
+            data_size,
+            order: header.order(),
+            big_endian,
+        })
+    }
+}
+
+/// Memory-mapped `.npy` file of traces, handing out views without copying.
+///
+/// # Examples
+/// ```rust,no_run
+/// use muscat::leakage_detection::SnrProcessor;
+/// use muscat::npy::MmapNpy;
+///
+/// // SAFETY: the file is not modified while it is mapped
+/// let traces = unsafe { MmapNpy::<i16>::open("traces.npy") }.unwrap();
+/// let mut snr = SnrProcessor::new(traces.shape().1, 256);
+/// for (i, chunk) in traces.chunks(10_000).enumerate() {
+///     for (j, trace) in chunk.rows().into_iter().enumerate() {
+///         snr.process(trace, (i * 10_000 + j) % 256);
+///     }
+/// }
+/// ```
+pub struct MmapNpy<T> {
+    mmap: Mmap,
+    layout: Layout,
+    phantom: PhantomData<T>,
+}
+
+impl<T: NpySample> MmapNpy<T> {
+    /// Maps the `.npy` file at the given path in memory.
+    ///
+    /// Returns [`NpyError::NotMappable`] if the data is not stored in C order, is not in the
+    /// native endianness, or is not aligned for `T`. Use [`ChunkedNpyReader`] for such files.
+    ///
+    /// # Safety
+    /// The file must not be modified or truncated, by this process or another one, while it is
+    /// mapped, which is undefined behavior (truncating it typically raises `SIGBUS` when accessing
+    /// the traces). [`ChunkedNpyReader`] is a safe alternative. See [`Mmap::map`] for details.
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<Self, NpyError> {
+        let mut file = File::open(path)?;
+        let layout = Layout::read::<T, _>(&mut file)?;
+
+        if layout.order != Order::C {
+            return Err(NpyError::NotMappable("data is not in C order"));
+        }
+        if layout.big_endian != cfg!(target_endian = "big") {
+            return Err(NpyError::NotMappable("data is not in native endianness"));
+        }
+        if !(layout.offset as usize).is_multiple_of(mem::align_of::<T>()) {
+            return Err(NpyError::NotMappable("data is not aligned"));
+        }
+
+        // SAFETY: the file is not modified while it is mapped, as required from the caller.
+        let mmap = unsafe { Mmap::map(&file)? };
+        if mmap.len().saturating_sub(layout.offset as usize) < layout.data_size {
+            return Err(NpyError::InvalidHeader("file is truncated".into()));
+        }
+
+        Ok(Self {
+            mmap,
+            layout,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Returns the number of traces and the number of samples per trace.
+    pub fn shape(&self) -> (usize, usize) {
+        self.layout.shape
+    }
+
+    /// Returns a view of all the traces.
+    pub fn view(&self) -> ArrayView2<'_, T> {
+        let (rows, columns) = self.layout.shape;
+        let data = &self.mmap[self.layout.offset as usize..];
+        // SAFETY: the mapping is page-aligned and the offset is aligned for `T` (checked in
+        // `open`), the mapping is large enough for the whole array (checked in `open`), and
+        // `NpySample` types are valid for any bit pattern.
+        let data = unsafe { std::slice::from_raw_parts(data.as_ptr() as *const T, rows * columns) };
+
+        // The length of the slice matches the shape, thus this cannot fail
+        ArrayView2::from_shape((rows, columns), data).unwrap()
+    }
+
+    /// Iterate over views of `chunk_size` traces. The last chunk may be smaller.
+    ///
+    /// # Panics
+    /// Panic if `chunk_size` is 0.
+    pub fn chunks(&self, chunk_size: usize) -> impl Iterator<Item = ArrayView2<'_, T>> {
+        assert!(chunk_size > 0);
+
+        let view = self.view();
+        let rows = view.nrows();
+        (0..rows)
+            .step_by(chunk_size)
+            .map(move |start| view.slice_move(s![start..(start + chunk_size).min(rows), ..]))
+    }
+}
+
+/// Reader of `.npy` files of traces, reading chunks of traces in memory.
+///
+/// Supports C and Fortran orders and both endiannesses. Unlike [`MmapNpy`], it is safe to use on
+/// files that may be modified while they are read.
+pub struct ChunkedNpyReader<T, R> {
+    reader: R,
+    layout: Layout,
+    chunk_size: usize,
+    /// Number of traces read so far
+    position: usize,
+    buffer: Vec<u8>,
+    phantom: PhantomData<T>,
+}
+
+impl<T: NpySample> ChunkedNpyReader<T, BufReader<File>> {
+    /// Opens the `.npy` file at the given path, to be read by chunks of `chunk_size` traces.
+    pub fn open<P: AsRef<Path>>(path: P, chunk_size: usize) -> Result<Self, NpyError> {
+        Self::new(BufReader::new(File::open(path)?), chunk_size)
+    }
+}
+
+impl<T: NpySample, R: Read + Seek> ChunkedNpyReader<T, R> {
+    /// Creates a new [`ChunkedNpyReader`] from a reader positioned at the start of a `.npy` file,
+    /// reading chunks of `chunk_size` traces.
+    ///
+    /// # Panics
+    /// Panic if `chunk_size` is 0.
+    pub fn new(mut reader: R, chunk_size: usize) -> Result<Self, NpyError> {
+        assert!(chunk_size > 0);
+
+        let start = reader.stream_position()?;
+        let mut layout = Layout::read::<T, _>(&mut reader)?;
+        layout.offset += start;
+
+        Ok(Self {
+            reader,
+            layout,
+            chunk_size,
+            position: 0,
+            buffer: Vec::new(),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Returns the number of traces and the number of samples per trace.
+    pub fn shape(&self) -> (usize, usize) {
+        self.layout.shape
+    }
+
+    /// Read the next chunk of traces, or return `None` if all the traces have been read.
+    ///
+    /// After an error, no more chunks are read.
+    pub fn read_chunk(&mut self) -> Result<Option<Array2<T>>, NpyError> {
+        let (rows, columns) = self.layout.shape;
+        let num_rows = self.chunk_size.min(rows - self.position);
+        if num_rows == 0 {
+            return Ok(None);
+        }
+
+        if let Err(err) = self.read_buffer(num_rows) {
+            self.position = rows;
+            return Err(err.into());
+        }
+        self.position += num_rows;
+
+        let sample_size = mem::size_of::<T>();
+        let samples: Vec<T> = self
+            .buffer
+            .chunks_exact(sample_size)
+            .map(|bytes| T::from_bytes(bytes, self.layout.big_endian))
+            .collect();
+        let shape = (num_rows, columns);
+        let chunk = match self.layout.order {
+            Order::C => Array2::from_shape_vec(shape, samples),
+            Order::Fortran => Array2::from_shape_vec(shape.f(), samples),
+        };
+
+        // The number of samples matches the shape, thus this cannot fail
+        Ok(Some(chunk.unwrap()))
+    }
+
+    /// Read the bytes of the `num_rows` traces following the current position in the buffer.
+    fn read_buffer(&mut self, num_rows: usize) -> io::Result<()> {
+        let (rows, columns) = self.layout.shape;
+        let sample_size = mem::size_of::<T>();
+        // Chunks are not larger than the whole data, whose size is checked in `Layout::read`
+        let chunk_size = num_rows
+            .checked_mul(columns * sample_size)
+            .filter(|&size| size <= self.layout.data_size)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "chunk size overflows"))?;
+        self.buffer.resize(chunk_size, 0);
+        match self.layout.order {
+            Order::C => {
+                let offset = (self.position * columns * sample_size) as u64;
+                self.reader
+                    .seek(SeekFrom::Start(self.layout.offset + offset))?;
+                self.reader.read_exact(&mut self.buffer)?;
+            }
+            Order::Fortran => {
+                // Each column of the chunk is stored separately
+                for (j, column) in self
+                    .buffer
+                    .chunks_exact_mut(num_rows * sample_size)
+                    .enumerate()
+                {
+                    let offset = ((j * rows + self.position) * sample_size) as u64;
+                    self.reader
+                        .seek(SeekFrom::Start(self.layout.offset + offset))?;
+                    self.reader.read_exact(column)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: NpySample, R: Read + Seek> Iterator for ChunkedNpyReader<T, R> {
+    type Item = Result<Array2<T>, NpyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_chunk().transpose()
+    }
+}
+
+/// Traces stored in a `.npy` file, memory-mapped when possible and read by chunks otherwise.
+///
+/// # Examples
+/// ```rust,no_run
+/// use muscat::leakage_detection::SnrProcessor;
+/// use muscat::npy::NpyTraces;
+///
+/// // SAFETY: the file is not modified while it is read
+/// let mut traces = unsafe { NpyTraces::<u8>::open("traces.npy") }.unwrap();
+/// let mut snr = SnrProcessor::new(traces.shape().1, 2);
+/// let mut i = 0;
+/// traces
+///     .for_each_chunk(10_000, |chunk| {
+///         for trace in chunk.rows() {
+///             snr.process(trace, i % 2);
+///             i += 1;
+///         }
+///     })
+///     .unwrap();
+/// ```
+pub enum NpyTraces<T> {
+    Mmap(MmapNpy<T>),
+    Chunked(ChunkedNpyReader<T, BufReader<File>>),
+}
+
+impl<T: NpySample> NpyTraces<T> {
+    /// Opens the `.npy` file at the given path.
+    ///
+    /// # Safety
+    /// The file may be memory-mapped, see [`MmapNpy::open`].
+    pub unsafe fn open<P: AsRef<Path>>(path: P) -> Result<Self, NpyError> {
+        // SAFETY: the file is not modified while it is mapped, as required from the caller.
+        match unsafe { MmapNpy::open(&path) } {
+            Ok(mmap) => Ok(NpyTraces::Mmap(mmap)),
+            // The chunk size is set when iterating
+            Err(NpyError::NotMappable(_)) => {
+                Ok(NpyTraces::Chunked(ChunkedNpyReader::open(path, 1)?))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the number of traces and the number of samples per trace.
+    pub fn shape(&self) -> (usize, usize) {
+        match self {
+            NpyTraces::Mmap(mmap) => mmap.shape(),
+            NpyTraces::Chunked(reader) => reader.shape(),
+        }
+    }
+
+    /// Call `f` on successive chunks of `chunk_size` traces, from the first trace of the file.
+    ///
+    /// # Panics
+    /// Panic if `chunk_size` is 0.
+    pub fn for_each_chunk<F>(&mut self, chunk_size: usize, mut f: F) -> Result<(), NpyError>
+    where
+        F: FnMut(ArrayView2<T>),
+    {
+        assert!(chunk_size > 0);
+
+        match self {
+            NpyTraces::Mmap(mmap) => {
+                for chunk in mmap.view().axis_chunks_iter(Axis(0), chunk_size) {
+                    f(chunk);
+                }
+            }
+            NpyTraces::Chunked(reader) => {
+                reader.position = 0;
+                reader.chunk_size = chunk_size;
+                while let Some(chunk) = reader.read_chunk()? {
+                    f(chunk.view());
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Length of the header written by [`NpyWriter`], large enough for any 2D shape.
+const WRITER_HEADER_LENGTH: usize = 128;
+
+/// Build the header of a `.npy` file holding a C-order array of `T` of the given shape.
+///
+/// The header is padded with spaces to at least `min_length` bytes, and to a multiple of 64 bytes
+/// as recommended by the format.
+fn npy_header<T: NpySample>(shape: &[usize], min_length: usize) -> Vec<u8> {
+    let shape = match shape {
+        [length] => format!("({length},)"),
+        shape => format!(
+            "({})",
+            shape
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut dict = format!(
+        "{{'descr': '<{}', 'fortran_order': False, 'shape': {shape}, }}",
+        T::DTYPE
+    );
+    // Magic string, version and header length take 10 bytes, the dictionary ends with a newline
+    while 10 + dict.len() + 1 < min_length || !(10 + dict.len() + 1).is_multiple_of(64) {
+        dict.push(' ');
+    }
+    dict.push('\n');
+
+    let mut header = b"\x93NUMPY\x01\x00".to_vec();
+    header.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+    header.extend_from_slice(dict.as_bytes());
+    header
+}
+
+/// Write an array in `.npy` format.
+fn write_array<T, D, W>(mut writer: W, array: ArrayView<T, D>) -> Result<(), NpyError>
+where
+    T: NpySample,
+    D: Dimension,
+    W: Write,
+{
+    writer.write_all(&npy_header::<T>(array.shape(), 0))?;
+
+    // Samples are written in logical order, i.e. in C order, whatever the memory layout
+    let mut buffer = Vec::with_capacity(array.len() * mem::size_of::<T>());
+    for &x in array.iter() {
+        x.extend_le_bytes(&mut buffer);
+    }
+    writer.write_all(&buffer)?;
+
+    Ok(())
+}
+
+/// Write an array to the `.npy` file at the given path.
+///
+/// # Examples
+/// ```rust,no_run
+/// use muscat::npy::write_npy;
+/// use ndarray::array;
+///
+/// write_npy("snr.npy", array![0.1f32, 2.5, 0.3].view()).unwrap();
+/// ```
+pub fn write_npy<P, T, D>(path: P, array: ArrayView<T, D>) -> Result<(), NpyError>
+where
+    P: AsRef<Path>,
+    T: NpySample,
+    D: Dimension,
+{
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_array(&mut writer, array)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Writer of `.npy` files of traces, appending traces as they are produced.
+///
+/// The number of traces is written in the header by [`NpyWriter::finish`]. A file whose writer is
+/// dropped without being finished holds no trace.
+///
+/// # Examples
+/// ```rust,no_run
+/// use muscat::npy::NpyWriter;
+/// use ndarray::Array1;
+///
+/// let mut writer = NpyWriter::<f32, _>::create("preprocessed.npy", 1000).unwrap();
+/// for _ in 0..100 {
+///     writer.write_trace(Array1::zeros(1000).view()).unwrap();
+/// }
+/// writer.finish().unwrap();
+/// ```
+pub struct NpyWriter<T, W: Write + Seek> {
+    writer: W,
+    /// Position of the header in the writer
+    start: u64,
+    trace_length: usize,
+    num_traces: usize,
+    buffer: Vec<u8>,
+    phantom: PhantomData<T>,
+}
+
+impl<T: NpySample> NpyWriter<T, BufWriter<File>> {
+    /// Creates a new `.npy` file at the given path, holding traces of `trace_length` samples.
+    pub fn create<P: AsRef<Path>>(path: P, trace_length: usize) -> Result<Self, NpyError> {
+        Self::new(BufWriter::new(File::create(path)?), trace_length)
+    }
+}
+
+impl<T: NpySample, W: Write + Seek> NpyWriter<T, W> {
+    /// Creates a new [`NpyWriter`] writing a `.npy` file to `writer` from its current position,
+    /// holding traces of `trace_length` samples.
+    pub fn new(mut writer: W, trace_length: usize) -> Result<Self, NpyError> {
+        let start = writer.stream_position()?;
+        writer.write_all(&npy_header::<T>(&[0, trace_length], WRITER_HEADER_LENGTH))?;
+
+        Ok(Self {
+            writer,
+            start,
+            trace_length,
+            num_traces: 0,
+            buffer: Vec::new(),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Returns the number of traces written so far.
+    pub fn num_traces(&self) -> usize {
+        self.num_traces
+    }
+
+    /// Append a trace to the file.
+    ///
+    /// # Panics
+    /// Panic if the length of `trace` is not the trace length given to the constructor.
+    pub fn write_trace(&mut self, trace: ArrayView1<T>) -> Result<(), NpyError> {
+        assert_eq!(trace.len(), self.trace_length);
+
+        self.buffer.clear();
+        for &x in trace.iter() {
+            x.extend_le_bytes(&mut self.buffer);
+        }
+        self.writer.write_all(&self.buffer)?;
+        self.num_traces += 1;
+
+        Ok(())
+    }
+
+    /// Append a batch of traces to the file.
+    ///
+    /// # Panics
+    /// Panic if the length of the traces is not the trace length given to the constructor.
+    pub fn write_traces(&mut self, traces: ArrayView2<T>) -> Result<(), NpyError> {
+        for trace in traces.rows() {
+            self.write_trace(trace)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write the number of traces in the header and return the underlying writer.
+    pub fn finish(mut self) -> Result<W, NpyError> {
+        let end = self.writer.stream_position()?;
+        let header = npy_header::<T>(&[self.num_traces, self.trace_length], WRITER_HEADER_LENGTH);
+        debug_assert_eq!(header.len(), WRITER_HEADER_LENGTH);
+
+        self.writer.seek(SeekFrom::Start(self.start))?;
+        self.writer.write_all(&header)?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Writer of `.npz` archives, as read by `numpy.load`.
+///
+/// Arrays are stored uncompressed, like `numpy.savez` does.
+pub struct NpzWriter<W: Write + Seek> {
+    zip: ZipWriter<W>,
+}
+
+impl NpzWriter<BufWriter<File>> {
+    /// Creates a new `.npz` archive at the given path.
+    pub fn create<P: AsRef<Path>>(path: P) -> Result<Self, NpyError> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+}
+
+impl<W: Write + Seek> NpzWriter<W> {
+    /// Creates a new [`NpzWriter`] writing to `writer`.
+    pub fn new(writer: W) -> Self {
+        Self {
+            zip: ZipWriter::new(writer),
+        }
+    }
+
+    /// Add an array to the archive under the given name.
+    pub fn add_array<T, D>(&mut self, name: &str, array: ArrayView<T, D>) -> Result<(), NpyError>
+    where
+        T: NpySample,
+        D: Dimension,
+    {
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Stored)
+            .large_file(array.len() * mem::size_of::<T>() >= u32::MAX as usize);
+        self.zip.start_file(format!("{name}.npy"), options)?;
+        write_array(&mut self.zip, array)
+    }
+
+    /// Write the central directory of the archive and return the underlying writer.
+    pub fn finish(self) -> Result<W, NpyError> {
+        let mut writer = self.zip.finish()?;
+        writer.flush()?;
+
+        Ok(writer)
+    }
+}
+
+/// Results that can be exported to `.npz` archives.
+///
+/// The arrays of a result are named after the methods returning them (e.g. `corr`, `max_corr` and
+/// `rank` for [`Cpa`]). Guess ranks and counts are stored as `uint64`.
+///
+/// # Examples
+/// ```rust,no_run
+/// use muscat::distinguishers::cpa::cpa;
+/// use muscat::leakage_model::aes::sbox;
+/// use muscat::npy::{NpzExport, NpzWriter};
+/// use ndarray::array;
+///
+/// let traces = array![[77u8, 137, 51, 91], [72, 61, 91, 83], [39, 49, 52, 23]];
+/// let plaintexts = array![[1usize, 2], [2, 1], [1, 2]];
+/// let cpa = cpa(
+///     traces.view(),
+///     plaintexts.view(),
+///     256,
+///     0,
+///     |plaintext, guess| sbox((plaintext ^ guess) as u8) as usize,
+///     2,
+/// );
+///
+/// // Results of several attacks can be stored in the same archive
+/// let mut npz = NpzWriter::create("results.npz").unwrap();
+/// cpa.export(&mut npz, "byte0_").unwrap();
+/// npz.finish().unwrap();
+/// ```
+pub trait NpzExport {
+    /// Add the arrays of the result to `npz`, prefixing their names with `prefix`.
+    fn export<W: Write + Seek>(&self, npz: &mut NpzWriter<W>, prefix: &str)
+    -> Result<(), NpyError>;
+
+    /// Save the arrays of the result to a new `.npz` archive at the given path.
+    fn save_npz<P: AsRef<Path>>(&self, path: P) -> Result<(), NpyError> {
+        let mut npz = NpzWriter::create(path)?;
+        self.export(&mut npz, "")?;
+        npz.finish()?;
+
+        Ok(())
+    }
+}
+
+impl NpzExport for Cpa {
+    fn export<W: Write + Seek>(
+        &self,
+        npz: &mut NpzWriter<W>,
+        prefix: &str,
+    ) -> Result<(), NpyError> {
+        npz.add_array(&format!("{prefix}corr"), self.corr())?;
+        npz.add_array(&format!("{prefix}max_corr"), self.max_corr().view())?;
+        npz.add_array(
+            &format!("{prefix}rank"),
+            self.rank().mapv(|x| x as u64).view(),
+        )?;
+        npz.add_array(
+            &format!("{prefix}num_traces"),
+            Array0::from_elem((), self.num_traces() as u64).view(),
+        )
+    }
+}
+
+impl NpzExport for Dpa {
+    fn export<W: Write + Seek>(
+        &self,
+        npz: &mut NpzWriter<W>,
+        prefix: &str,
+    ) -> Result<(), NpyError> {
+        npz.add_array(
+            &format!("{prefix}differential_curves"),
+            self.differential_curves(),
+        )?;
+        npz.add_array(
+            &format!("{prefix}max_differential_curves"),
+            self.max_differential_curves().view(),
+        )?;
+        npz.add_array(
+            &format!("{prefix}rank"),
+            self.rank().mapv(|x| x as u64).view(),
+        )
+    }
+}
+
+impl<T: Sample + Copy> NpzExport for SnrProcessor<T> {
+    fn export<W: Write + Seek>(
+        &self,
+        npz: &mut NpzWriter<W>,
+        prefix: &str,
+    ) -> Result<(), NpyError> {
+        npz.add_array(&format!("{prefix}snr"), self.snr().view())?;
+        export_classes(
+            npz,
+            prefix,
+            self.classes_mean(),
+            self.classes_var(),
+            self.classes_count(),
+        )
+    }
+}
+
+impl<T: Sample + Copy> NpzExport for NicvProcessor<T> {
+    fn export<W: Write + Seek>(
+        &self,
+        npz: &mut NpzWriter<W>,
+        prefix: &str,
+    ) -> Result<(), NpyError> {
+        npz.add_array(&format!("{prefix}nicv"), self.nicv().view())?;
+        export_classes(
+            npz,
+            prefix,
+            self.classes_mean(),
+            self.classes_var(),
+            self.classes_count(),
+        )
+    }
+}
+
+impl<T: Sample + Copy> NpzExport for TTestProcessor<T> {
+    fn export<W: Write + Seek>(
+        &self,
+        npz: &mut NpzWriter<W>,
+        prefix: &str,
+    ) -> Result<(), NpyError> {
+        npz.add_array(&format!("{prefix}ttest"), self.ttest().view())
+    }
+}
+
+/// Add the per-class statistics of [`SnrProcessor`] and [`NicvProcessor`] to `npz`.
+fn export_classes<W: Write + Seek>(
+    npz: &mut NpzWriter<W>,
+    prefix: &str,
+    classes_mean: Array2<f32>,
+    classes_var: Option<Array2<f32>>,
+    classes_count: ArrayView1<usize>,
+) -> Result<(), NpyError> {
+    npz.add_array(&format!("{prefix}classes_mean"), classes_mean.view())?;
+    if let Some(classes_var) = classes_var {
+        npz.add_array(&format!("{prefix}classes_var"), classes_var.view())?;
+    }
+    npz.add_array(
+        &format!("{prefix}classes_count"),
+        classes_count.mapv(|x| x as u64).view(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ChunkedNpyReader, MmapNpy, NpyError, NpyTraces, NpyWriter, NpzExport, NpzWriter};
+    use crate::leakage_detection::SnrProcessor;
+    use ndarray::{Array1, Array2, ShapeBuilder, array, s};
+    use ndarray_npy::{ReadNpyExt, read_npy, write_npy};
+    use std::{fs, io::Cursor};
+    use zip::ZipArchive;
+
+    /// Build a `.npy` file from its header dictionary and data.
+    fn npy_bytes(dict: &str, data: &[u8]) -> Vec<u8> {
+        let mut header = dict.to_string();
+        while !(10 + header.len() + 1).is_multiple_of(64) {
+            header.push(' ');
+        }
+        header.push('\n');
+
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn test_mmap_npy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("traces.npy");
+        let traces = Array2::from_shape_fn((10, 7), |(i, j)| (i * 7 + j) as i16 - 30);
+        write_npy(&path, &traces).unwrap();
+
+        let mmap = unsafe { MmapNpy::<i16>::open(&path) }.unwrap();
+        assert_eq!(mmap.shape(), (10, 7));
+        assert_eq!(mmap.view(), traces);
+        let chunks: Vec<_> = mmap.chunks(4).collect();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(chunks[2], traces.slice(s![8.., ..]));
+
+        assert!(matches!(
+            unsafe { MmapNpy::<f32>::open(&path) },
+            Err(NpyError::DtypeMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_chunked_npy_fortran_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("traces.npy");
+        let traces = Array2::from_shape_fn((5, 3).f(), |(i, j)| (i * 3 + j) as f32);
+        write_npy(&path, &traces).unwrap();
+
+        assert!(matches!(
+            unsafe { MmapNpy::<f32>::open(&path) },
+            Err(NpyError::NotMappable(_))
+        ));
+
+        let mut npy_traces = unsafe { NpyTraces::<f32>::open(&path) }.unwrap();
+        assert!(matches!(npy_traces, NpyTraces::Chunked(_)));
+        let mut rows = Vec::new();
+        npy_traces
+            .for_each_chunk(2, |chunk| {
+                rows.extend(chunk.rows().into_iter().map(|r| r.to_vec()))
+            })
+            .unwrap();
+        assert_eq!(rows.len(), 5);
+        for (row, expected) in rows.iter().zip(traces.rows()) {
+            assert_eq!(row, &expected.to_vec());
+        }
+    }
+
+    #[test]
+    fn test_chunked_npy_big_endian() {
+        let data: Vec<u8> = (0..6i16).flat_map(|x| (x - 2).to_be_bytes()).collect();
+        let bytes = npy_bytes(
+            "{'descr': '>i2', 'fortran_order': False, 'shape': (3, 2), }",
+            &data,
+        );
+
+        let chunks: Vec<_> = ChunkedNpyReader::<i16, _>::new(Cursor::new(bytes), 2)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0], ndarray::array![[-2, -1], [0, 1]]);
+        assert_eq!(chunks[1], ndarray::array![[2, 3]]);
+    }
+
+    #[test]
+    fn test_npy_shape_overflow() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("traces.npy");
+        let bytes = npy_bytes(
+            "{'descr': '<f4', 'fortran_order': False, 'shape': (4611686018427387905, 1), }",
+            &[0; 16],
+        );
+        fs::write(&path, &bytes).unwrap();
+
+        assert!(matches!(
+            unsafe { MmapNpy::<f32>::open(&path) },
+            Err(NpyError::InvalidShape(_))
+        ));
+        assert!(matches!(
+            ChunkedNpyReader::<f32, _>::new(Cursor::new(bytes), 2),
+            Err(NpyError::InvalidShape(_))
+        ));
+    }
+
+    #[test]
+    fn test_chunked_npy_truncated() {
+        let data: Vec<u8> = (0..5i16).flat_map(|x| x.to_le_bytes()).collect();
+        let bytes = npy_bytes(
+            "{'descr': '<i2', 'fortran_order': False, 'shape': (3, 2), }",
+            &data,
+        );
+
+        let mut reader = ChunkedNpyReader::<i16, _>::new(Cursor::new(bytes), 2).unwrap();
+        assert_eq!(
+            reader.next().unwrap().unwrap(),
+            ndarray::array![[0, 1], [2, 3]]
+        );
+        assert!(reader.next().unwrap().is_err());
+        // The reader is fused after an error
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_npy_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("traces.npy");
+        let traces = Array2::from_shape_fn((150, 9), |(i, j)| (i * 9 + j) as f32 / 7.);
+
+        let mut writer = NpyWriter::<f32, _>::create(&path, 9).unwrap();
+        writer.write_trace(traces.row(0)).unwrap();
+        writer.write_traces(traces.slice(s![1.., ..])).unwrap();
+        assert_eq!(writer.num_traces(), 150);
+        writer.finish().unwrap();
+
+        assert_eq!(read_npy::<_, Array2<f32>>(&path).unwrap(), traces);
+        assert_eq!(
+            unsafe { MmapNpy::<f32>::open(&path) }.unwrap().view(),
+            traces
+        );
+
+        // Strided arrays are written in logical order
+        let path = dir.path().join("transposed.npy");
+        super::write_npy(&path, traces.t()).unwrap();
+        assert_eq!(read_npy::<_, Array2<f32>>(&path).unwrap(), traces.t());
+    }
+
+    #[test]
+    fn test_npz_export() {
+        let mut snr = SnrProcessor::new(3, 2);
+        snr.process(array![1u8, 2, 3].view(), 0);
+        snr.process(array![3u8, 2, 1].view(), 1);
+        snr.process(array![2u8, 4, 3].view(), 0);
+        snr.process(array![6u8, 1, 2].view(), 1);
+
+        let mut npz = NpzWriter::new(Cursor::new(Vec::new()));
+        snr.export(&mut npz, "byte0_").unwrap();
+        let bytes = npz.finish().unwrap().into_inner();
+
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut names: Vec<_> = archive.file_names().collect();
+        names.sort();
+        assert_eq!(
+            names,
+            vec![
+                "byte0_classes_count.npy",
+                "byte0_classes_mean.npy",
+                "byte0_snr.npy"
+            ]
+        );
+        let snr_array = Array1::<f32>::read_npy(archive.by_name("byte0_snr.npy").unwrap()).unwrap();
+        assert_eq!(snr_array, snr.snr());
+        let classes_count =
+            Array1::<u64>::read_npy(archive.by_name("byte0_classes_count.npy").unwrap()).unwrap();
+        assert_eq!(classes_count, array![2, 2]);
+    }
+}
@@ -0,0 +1,276 @@
+//! Combination of leakage samples for higher-order attacks.
+//!
+//! Against masked implementations, the leakages of the shares have to be combined into a single
+//! sample that depends on the unmasked value. The best combining function depends on the noise of
+//! the device, thus several are provided.
+
+use itertools::Itertools;
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2};
+use num_traits::AsPrimitive;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+use super::batch_apply;
+use crate::Sample;
+
+/// Function combining the centered samples of a tuple.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CombinationFunction {
+    /// Absolute difference `|x_i - x_j|` of a pair of samples
+    AbsoluteDifference,
+    /// Sum of the samples
+    Sum,
+    /// Product of the samples
+    CenteredProduct,
+}
+
+/// Combines tuples of samples of traces, after centering them by the mean trace.
+///
+/// Like [`super::CenteredProduct`], the mean trace is first computed by processing traces and
+/// calling [`Combination::finalize`], then combined traces are computed with
+/// [`Combination::apply`].
+///
+/// # Examples
+/// ```rust
+/// use muscat::preprocessors::combination::{Combination, CombinationFunction};
+/// use ndarray::array;
+///
+/// let traces = array![[1u8, 5, 3], [3, 1, 5]];
+///
+/// let mut combination = Combination::new(
+///     3,
+///     vec![vec![0, 1], vec![1, 2]],
+///     CombinationFunction::AbsoluteDifference,
+/// );
+/// for trace in traces.rows() {
+///     combination.process(trace);
+/// }
+/// combination.finalize();
+///
+/// assert_eq!(combination.apply(traces.row(0)), array![3., 3.]);
+/// ```
+pub struct Combination<T>
+where
+    T: Sample,
+{
+    /// Sum of traces
+    acc: Array1<<T as Sample>::Container>,
+    /// Number of traces processed
+    count: usize,
+    /// Mean of traces, available after [`Combination::finalize`]
+    mean: Option<Array1<f32>>,
+    /// Indices of the samples of each tuple
+    tuples: Vec<Vec<usize>>,
+    function: CombinationFunction,
+}
+
+impl<T> Combination<T>
+where
+    T: Sample + Copy,
+{
+    /// Creates a new [`Combination`] preprocessor combining the given tuples of samples.
+    ///
+    /// # Panics
+    /// - Panic if a tuple is empty or contains an index greater or equal to `trace_length`.
+    /// - Panic if `function` is [`CombinationFunction::AbsoluteDifference`] and a tuple is not a
+    ///   pair.
+    pub fn new(
+        trace_length: usize,
+        tuples: Vec<Vec<usize>>,
+        function: CombinationFunction,
+    ) -> Self {
+        for tuple in tuples.iter() {
+            assert!(!tuple.is_empty());
+            assert!(tuple.iter().all(|&i| i < trace_length));
+            if function == CombinationFunction::AbsoluteDifference {
+                assert_eq!(tuple.len(), 2);
+            }
+        }
+
+        Self {
+            acc: Array1::zeros(trace_length),
+            count: 0,
+            mean: None,
+            tuples,
+            function,
+        }
+    }
+
+    /// Creates a new [`Combination`] preprocessor combining the tuples of the Cartesian product of
+    /// the given intervals.
+    ///
+    /// # Panics
+    /// See [`Combination::new`].
+    pub fn from_intervals(
+        trace_length: usize,
+        intervals: Vec<Range<usize>>,
+        function: CombinationFunction,
+    ) -> Self {
+        let tuples = intervals.into_iter().multi_cartesian_product().collect();
+        Self::new(trace_length, tuples, function)
+    }
+
+    /// Creates a new [`Combination`] preprocessor combining every pair of distinct samples within
+    /// `samples`.
+    ///
+    /// # Panics
+    /// See [`Combination::new`].
+    pub fn all_pairs(
+        trace_length: usize,
+        samples: Range<usize>,
+        function: CombinationFunction,
+    ) -> Self {
+        let tuples = samples.combinations(2).collect();
+        Self::new(trace_length, tuples, function)
+    }
+
+    /// Returns the tuples of samples combined.
+    pub fn tuples(&self) -> &[Vec<usize>] {
+        &self.tuples
+    }
+
+    /// Returns the length of combined traces.
+    pub fn num_combinations(&self) -> usize {
+        self.tuples.len()
+    }
+
+    /// Processes an input trace to update the mean trace.
+    ///
+    /// # Panics
+    /// Panic if the shape of `trace` does not match the trace length given to the constructor.
+    pub fn process(&mut self, trace: ArrayView1<T>) {
+        assert_eq!(trace.shape()[0], self.acc.len());
+
+        for (acc, &x) in self.acc.iter_mut().zip(trace.iter()) {
+            *acc += x.into();
+        }
+        self.count += 1;
+    }
+
+    /// Compute the mean trace.
+    pub fn finalize(&mut self) {
+        let count = self.count.max(1) as f32;
+        self.mean = Some(self.acc.mapv(|x| x.as_() / count));
+    }
+
+    /// Combine the samples of the given trace.
+    ///
+    /// # Panics
+    /// Panic if [`Combination::finalize`] has not been called.
+    pub fn apply(&self, trace: ArrayView1<T>) -> Array1<f32> {
+        let mean = self
+            .mean
+            .as_ref()
+            .expect("finalize must be called before apply");
+        let centered =
+            |i: usize| -> f32 { <T as Sample>::Container::from(trace[i]).as_() - mean[i] };
+
+        self.tuples
+            .iter()
+            .map(|tuple| match self.function {
+                CombinationFunction::AbsoluteDifference => {
+                    (centered(tuple[0]) - centered(tuple[1])).abs()
+                }
+                CombinationFunction::Sum => tuple.iter().map(|&i| centered(i)).sum(),
+                CombinationFunction::CenteredProduct => {
+                    tuple.iter().map(|&i| centered(i)).product()
+                }
+            })
+            .collect()
+    }
+
+    /// Combine the samples of each trace of the given batch in parallel.
+    ///
+    /// # Panics
+    /// Panic if [`Combination::finalize`] has not been called.
+    pub fn batch_apply(&self, traces: ArrayView2<T>) -> Array2<f32>
+    where
+        T: Sync,
+        <T as Sample>::Container: Sync,
+    {
+        batch_apply(traces, self.num_combinations(), |trace| self.apply(trace))
+    }
+}
+
+impl<T> Combination<T>
+where
+    T: Sample,
+{
+    /// Returns the mean trace, available after [`Combination::finalize`].
+    pub fn mean(&self) -> Option<ArrayView1<'_, f32>> {
+        self.mean.as_ref().map(|mean| mean.view())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Combination, CombinationFunction};
+    use crate::preprocessors::CenteredProduct;
+    use ndarray::array;
+
+    #[test]
+    fn test_combination_functions() {
+        let traces = array![[1i16, 4, 2, 8], [3, 2, 6, 4], [2, 0, 4, 0]];
+
+        let mut combination = Combination::new(
+            4,
+            vec![vec![0, 3], vec![2, 1]],
+            CombinationFunction::AbsoluteDifference,
+        );
+        for trace in traces.rows() {
+            combination.process(trace);
+        }
+        combination.finalize();
+        // Mean trace is [2, 2, 4, 4]
+        assert_eq!(combination.mean().unwrap(), array![2., 2., 4., 4.]);
+        assert_eq!(
+            combination.batch_apply(traces.view()),
+            array![[5., 4.], [1., 2.], [4., 2.]]
+        );
+
+        let mut combination = Combination::new(4, vec![vec![0, 1, 2]], CombinationFunction::Sum);
+        for trace in traces.rows() {
+            combination.process(trace);
+        }
+        combination.finalize();
+        assert_eq!(combination.apply(traces.row(0)), array![-1.]);
+    }
+
+    #[test]
+    fn test_combination_matches_centered_product() {
+        let traces = array![
+            [77u8, 137, 51, 91],
+            [72, 61, 91, 83],
+            [39, 49, 52, 23],
+            [26, 114, 63, 45],
+            [30, 8, 97, 91],
+        ];
+
+        let mut centered_product = CenteredProduct::new(4, vec![0..1, 1..2, 2..4]);
+        let mut combination = Combination::from_intervals(
+            4,
+            vec![0..1, 1..2, 2..4],
+            CombinationFunction::CenteredProduct,
+        );
+        for trace in traces.rows() {
+            centered_product.process(trace);
+            combination.process(trace);
+        }
+        centered_product.finalize();
+        combination.finalize();
+
+        assert_eq!(combination.tuples(), &[vec![0, 1, 2], vec![0, 1, 3]]);
+        for trace in traces.rows() {
+            let expected = centered_product.apply(trace.mapv(f32::from).view());
+            for (x, y) in combination.apply(trace).iter().zip(expected.iter()) {
+                assert!((x - y).abs() < 1e-2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_combination_all_pairs() {
+        let combination = Combination::<u8>::all_pairs(5, 1..4, CombinationFunction::Sum);
+        assert_eq!(combination.tuples(), &[vec![1, 2], vec![1, 3], vec![2, 3]]);
+    }
+}
@@ -0,0 +1,346 @@
+//! Compression of traces by reducing their number of samples.
+//!
+//! Traces sampled at a much higher rate than the clock frequency of the target can be compressed
+//! before being fed to processors, which dramatically reduces the processing time.
+
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, s};
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::{as_f32, batch_apply, filter::FirFilter, window::Window};
+use crate::Sample;
+
+/// Reduction applied to the samples of each window by [`WindowCompression`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowReduction {
+    /// Sum of the samples
+    Sum,
+    /// Mean of the samples
+    Mean,
+    /// Maximum of the samples
+    Max,
+    /// Sum of the absolute values of the samples
+    AbsSum,
+}
+
+/// Compress traces by reducing non-overlapping windows of samples to a single sample.
+///
+/// Trailing samples that do not fill a whole window are ignored.
+///
+/// # Examples
+/// ```rust
+/// use muscat::preprocessors::compression::{WindowCompression, WindowReduction};
+/// use ndarray::array;
+///
+/// let trace = array![1i8, 2, -3, 4, 5, -6, 7];
+/// let compression = WindowCompression::new(3, WindowReduction::AbsSum);
+///
+/// assert_eq!(compression.apply(trace.view()), array![6., 15.]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WindowCompression {
+    /// Number of samples per window
+    window_length: usize,
+    reduction: WindowReduction,
+}
+
+impl WindowCompression {
+    /// Creates a new [`WindowCompression`] preprocessor.
+    ///
+    /// # Panics
+    /// Panic if `window_length` is 0.
+    pub fn new(window_length: usize, reduction: WindowReduction) -> Self {
+        assert!(window_length > 0);
+
+        Self {
+            window_length,
+            reduction,
+        }
+    }
+
+    /// Returns the length of a compressed trace of the given length.
+    pub fn output_length(&self, trace_length: usize) -> usize {
+        trace_length / self.window_length
+    }
+
+    /// Compress the given trace.
+    pub fn apply<T>(&self, trace: ArrayView1<T>) -> Array1<f32>
+    where
+        T: Sample + Copy,
+    {
+        Array1::from_shape_fn(self.output_length(trace.len()), |i| {
+            let start = i * self.window_length;
+            let window = trace.slice(s![start..start + self.window_length]);
+            let samples = window.iter().map(|&x| as_f32(x));
+            match self.reduction {
+                WindowReduction::Sum => samples.sum(),
+                WindowReduction::Mean => samples.sum::<f32>() / self.window_length as f32,
+                WindowReduction::Max => samples.fold(f32::NEG_INFINITY, f32::max),
+                WindowReduction::AbsSum => samples.map(f32::abs).sum(),
+            }
+        })
+    }
+
+    /// Compress each trace of the given batch in parallel.
+    pub fn batch_apply<T>(&self, traces: ArrayView2<T>) -> Array2<f32>
+    where
+        T: Sample + Copy + Sync,
+    {
+        batch_apply(traces, self.output_length(traces.shape()[1]), |trace| {
+            self.apply(trace)
+        })
+    }
+}
+
+/// Decimate traces by an integer factor, keeping one sample out of `factor` after low-pass
+/// filtering them to avoid aliasing.
+///
+/// The anti-aliasing filter is a linear phase FIR filter (see [`FirFilter::low_pass`]) centered on
+/// the kept samples, thus decimated traces are not delayed. Only the kept samples are filtered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Decimation {
+    factor: usize,
+    /// Anti-aliasing filter
+    filter: FirFilter,
+}
+
+impl Decimation {
+    /// Creates a new [`Decimation`] preprocessor.
+    ///
+    /// # Arguments
+    ///
+    /// * `factor` - Decimation factor.
+    /// * `num_taps` - Length of the impulse response of the anti-aliasing filter.
+    /// * `window` - Window used to design the anti-aliasing filter.
+    ///
+    /// # Panics
+    /// - Panic if `factor` is lower than 2.
+    /// - Panic if `num_taps` is 0.
+    pub fn new(factor: usize, num_taps: usize, window: Window) -> Self {
+        assert!(factor >= 2);
+
+        Self {
+            factor,
+            filter: FirFilter::low_pass(0.5 / factor as f32, num_taps, window),
+        }
+    }
+
+    /// Returns the anti-aliasing filter.
+    pub fn filter(&self) -> &FirFilter {
+        &self.filter
+    }
+
+    /// Returns the length of a decimated trace of the given length.
+    pub fn output_length(&self, trace_length: usize) -> usize {
+        trace_length.div_ceil(self.factor)
+    }
+
+    /// Decimate the given trace. Samples outside of the trace are considered to be zero.
+    pub fn apply<T>(&self, trace: ArrayView1<T>) -> Array1<f32>
+    where
+        T: Sample + Copy,
+    {
+        let taps = self.filter.taps();
+        let delay = (taps.len() - 1) / 2;
+
+        Array1::from_shape_fn(self.output_length(trace.len()), |i| {
+            let center = i * self.factor;
+            taps.iter()
+                .enumerate()
+                .filter_map(|(k, &tap)| {
+                    (center + delay)
+                        .checked_sub(k)
+                        .and_then(|j| trace.get(j))
+                        .map(|&x| tap * as_f32(x))
+                })
+                .sum()
+        })
+    }
+
+    /// Decimate each trace of the given batch in parallel.
+    pub fn batch_apply<T>(&self, traces: ArrayView2<T>) -> Array2<f32>
+    where
+        T: Sample + Copy + Sync,
+    {
+        batch_apply(traces, self.output_length(traces.shape()[1]), |trace| {
+            self.apply(trace)
+        })
+    }
+}
+
+/// Resample traces to an arbitrary length using the Fourier method.
+///
+/// Frequencies above the Nyquist frequency of the output are discarded, and traces are considered
+/// periodic. This allows compressing traces by a fractional factor, for instance to bring traces
+/// from a 5 GS/s scope down to two samples per cycle of a 1.6 GHz clock.
+///
+/// # Examples
+/// ```rust
+/// use muscat::preprocessors::compression::Resampler;
+/// use ndarray::Array1;
+///
+/// let trace = Array1::from_shape_fn(50, |i| (i % 5) as u8);
+/// let resampler = Resampler::new(50, 20);
+///
+/// assert_eq!(resampler.apply(trace.view()).len(), 20);
+/// ```
+pub struct Resampler {
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+}
+
+impl Resampler {
+    /// Creates a new [`Resampler`] from traces of `input_length` samples to traces of
+    /// `output_length` samples.
+    ///
+    /// # Panics
+    /// Panic if `input_length` or `output_length` is 0.
+    pub fn new(input_length: usize, output_length: usize) -> Self {
+        assert!(input_length > 0);
+        assert!(output_length > 0);
+
+        let mut planner = RealFftPlanner::new();
+        Self {
+            fft: planner.plan_fft_forward(input_length),
+            ifft: planner.plan_fft_inverse(output_length),
+        }
+    }
+
+    /// Returns the trace length handled.
+    pub fn input_length(&self) -> usize {
+        self.fft.len()
+    }
+
+    /// Returns the length of resampled traces.
+    pub fn output_length(&self) -> usize {
+        self.ifft.len()
+    }
+
+    /// Resample the given trace.
+    ///
+    /// # Panics
+    /// Panic if `trace.len() != self.input_length()`.
+    pub fn apply<T>(&self, trace: ArrayView1<T>) -> Array1<f32>
+    where
+        T: Sample + Copy,
+    {
+        assert_eq!(trace.len(), self.input_length());
+
+        let mut input = self.fft.make_input_vec();
+        for (x, &sample) in input.iter_mut().zip(trace.iter()) {
+            *x = as_f32(sample);
+        }
+        let mut spectrum = self.fft.make_output_vec();
+        // Buffer lengths are the ones expected by the plan, thus this cannot fail
+        self.fft.process(&mut input, &mut spectrum).unwrap();
+
+        let n = self.input_length();
+        let m = self.output_length();
+        let mut resampled_spectrum = self.ifft.make_input_vec();
+        let num_bins = n.min(m) / 2 + 1;
+        resampled_spectrum[..num_bins].copy_from_slice(&spectrum[..num_bins]);
+        if n.min(m).is_multiple_of(2) {
+            // The energy of the Nyquist bin of the shortest length is split between the positive
+            // and negative frequencies of the longest length
+            let nyquist = &mut resampled_spectrum[num_bins - 1];
+            if m < n {
+                *nyquist *= 2.;
+            } else if n < m {
+                *nyquist *= 0.5;
+            }
+        }
+        // The imaginary parts of the DC and Nyquist bins must be zero for the inverse real FFT
+        resampled_spectrum[0].im = 0.;
+        if m.is_multiple_of(2) {
+            resampled_spectrum.last_mut().unwrap().im = 0.;
+        }
+
+        let mut output = self.ifft.make_output_vec();
+        self.ifft
+            .process(&mut resampled_spectrum, &mut output)
+            .unwrap();
+
+        Array1::from(output) / n as f32
+    }
+
+    /// Resample each trace of the given batch in parallel.
+    ///
+    /// # Panics
+    /// Panic if `traces.shape()[1] != self.input_length()`.
+    pub fn batch_apply<T>(&self, traces: ArrayView2<T>) -> Array2<f32>
+    where
+        T: Sample + Copy + Sync,
+    {
+        batch_apply(traces, self.output_length(), |trace| self.apply(trace))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Decimation, Resampler, WindowCompression, WindowReduction};
+    use crate::preprocessors::window::Window;
+    use ndarray::{Array1, Array2, array};
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_window_compression() {
+        let traces = array![[1u16, 2, 3, 4, 5, 6, 7], [7, 6, 5, 4, 3, 2, 1]];
+
+        let compression = WindowCompression::new(2, WindowReduction::Sum);
+        assert_eq!(compression.output_length(7), 3);
+        assert_eq!(
+            compression.batch_apply(traces.view()),
+            array![[3., 7., 11.], [13., 9., 5.]]
+        );
+
+        let compression = WindowCompression::new(3, WindowReduction::Mean);
+        assert_eq!(compression.apply(traces.row(0)), array![2., 5.]);
+
+        let compression = WindowCompression::new(3, WindowReduction::Max);
+        assert_eq!(compression.apply(traces.row(1)), array![7., 4.]);
+    }
+
+    #[test]
+    fn test_decimation() {
+        // Slow sinusoid with a high frequency component above the decimated Nyquist frequency
+        let trace = Array1::from_shape_fn(400, |i| {
+            (2. * PI * i as f32 / 100.).sin() + 0.5 * (2. * PI * 0.4 * i as f32).sin()
+        });
+
+        let decimation = Decimation::new(4, 63, Window::Blackman);
+        assert_eq!(decimation.output_length(400), 100);
+        let decimated = decimation.apply(trace.view());
+        assert_eq!(decimated.len(), 100);
+        // Away from the edges, only the slow sinusoid remains
+        for (i, &x) in decimated.iter().enumerate().skip(10).take(80) {
+            assert!((x - (2. * PI * 4. * i as f32 / 100.).sin()).abs() < 1e-2);
+        }
+
+        let traces = Array2::from_shape_fn((3, 400), |(_, j)| trace[j]);
+        assert_eq!(decimation.batch_apply(traces.view()).row(2), decimated);
+    }
+
+    #[test]
+    fn test_resampler() {
+        // Periodic signal whose frequency is below the Nyquist frequency of both lengths
+        let signal = |length: usize| {
+            Array1::from_shape_fn(length, |i| {
+                let t = i as f32 / length as f32;
+                2. + (2. * PI * 3. * t).cos() + 0.5 * (2. * PI * 5. * t).sin()
+            })
+        };
+
+        for (n, m) in [(100, 40), (40, 100), (64, 30), (30, 64), (50, 50)] {
+            let resampler = Resampler::new(n, m);
+            let resampled = resampler.apply(signal(n).view());
+            for (x, y) in resampled.iter().zip(signal(m).iter()) {
+                assert!((x - y).abs() < 1e-4);
+            }
+        }
+
+        let traces = Array2::from_shape_fn((2, 50), |(i, j)| (i * j % 7) as i8);
+        let resampler = Resampler::new(50, 20);
+        assert_eq!(resampler.batch_apply(traces.view()).shape(), &[2, 20]);
+    }
+}
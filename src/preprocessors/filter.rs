@@ -0,0 +1,523 @@
+//! Digital filters to remove unwanted frequency components of traces.
+//!
+//! Frequencies are normalized to the sampling frequency, thus cutoff frequencies lie in the open
+//! interval (0, 0.5), 0.5 being the Nyquist frequency. For example, with traces sampled at
+//! 1 GS/s, a cutoff frequency of 0.05 corresponds to 50 MHz.
+//!
+//! Filter designs implement [`Serialize`] and [`Deserialize`] so that the exact same filter can be
+//! applied across campaigns.
+
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis, s};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+use super::{as_f32, batch_apply, window::Window};
+use crate::Sample;
+
+/// A digital filter applicable to traces.
+pub trait Filter {
+    /// Filter the given signal. The filter is causal, with zero initial conditions, and the
+    /// output has the same length as the input.
+    fn filter(&self, signal: ArrayView1<f32>) -> Array1<f32>;
+
+    /// Return the magnitude of the frequency response of the filter at the given normalized
+    /// frequency.
+    fn gain(&self, frequency: f32) -> f32;
+
+    /// Number of samples used to extend traces in [`Filter::apply_zero_phase`].
+    fn pad_length(&self) -> usize;
+
+    /// Filter the given trace (see [`Filter::filter`]).
+    fn apply<T>(&self, trace: ArrayView1<T>) -> Array1<f32>
+    where
+        T: Sample + Copy,
+    {
+        self.filter(trace.mapv(as_f32).view())
+    }
+
+    /// Filter the given trace forward then backward, which cancels the phase shift introduced by
+    /// the filter and squares its magnitude response.
+    ///
+    /// To reduce transients, the trace is extended at both ends by an odd reflection of itself
+    /// before filtering.
+    fn apply_zero_phase<T>(&self, trace: ArrayView1<T>) -> Array1<f32>
+    where
+        T: Sample + Copy,
+    {
+        let trace = trace.mapv(as_f32);
+        let n = trace.len();
+        if n == 0 {
+            return trace;
+        }
+
+        let pad = self.pad_length().min(n - 1);
+        // Odd extension: 2 * x[0] - x[pad..0] and 2 * x[n - 1] - x[n - 2..n - pad - 2]
+        let mut extended = Array1::zeros(n + 2 * pad);
+        for i in 0..pad {
+            extended[i] = 2. * trace[0] - trace[pad - i];
+            extended[n + pad + i] = 2. * trace[n - 1] - trace[n - 2 - i];
+        }
+        extended.slice_mut(s![pad..pad + n]).assign(&trace);
+
+        let mut filtered = self.filter(extended.view());
+        filtered.invert_axis(Axis(0));
+        let mut filtered = self.filter(filtered.view());
+        filtered.invert_axis(Axis(0));
+
+        filtered.slice(s![pad..pad + n]).to_owned()
+    }
+
+    /// Filter each trace of the given batch in parallel (see [`Filter::apply`]).
+    fn batch_apply<T>(&self, traces: ArrayView2<T>) -> Array2<f32>
+    where
+        Self: Sync,
+        T: Sample + Copy + Sync,
+    {
+        batch_apply(traces, traces.shape()[1], |trace| self.apply(trace))
+    }
+
+    /// Filter each trace of the given batch in parallel (see [`Filter::apply_zero_phase`]).
+    fn batch_apply_zero_phase<T>(&self, traces: ArrayView2<T>) -> Array2<f32>
+    where
+        Self: Sync,
+        T: Sample + Copy + Sync,
+    {
+        batch_apply(traces, traces.shape()[1], |trace| {
+            self.apply_zero_phase(trace)
+        })
+    }
+}
+
+/// Finite impulse response (FIR) filter designed with the windowed-sinc method.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FirFilter {
+    /// Impulse response of the filter
+    taps: Array1<f32>,
+}
+
+impl FirFilter {
+    /// Creates a new [`FirFilter`] from its impulse response.
+    pub fn new(taps: Array1<f32>) -> Self {
+        Self { taps }
+    }
+
+    /// Design a low-pass filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `cutoff` - Normalized cutoff frequency.
+    /// * `num_taps` - Length of the impulse response. The longer, the sharper the transition.
+    /// * `window` - Window applied to the ideal impulse response.
+    ///
+    /// # Panics
+    /// - Panic if `cutoff` is not in the open interval (0, 0.5).
+    /// - Panic if `num_taps` is 0.
+    pub fn low_pass(cutoff: f32, num_taps: usize, window: Window) -> Self {
+        assert!(cutoff > 0. && cutoff < 0.5);
+        assert!(num_taps > 0);
+
+        let mut taps = windowed_sinc(cutoff, num_taps, window);
+        // Normalize to unit gain at DC
+        let sum = taps.sum();
+        taps /= sum;
+
+        Self { taps }
+    }
+
+    /// Design a high-pass filter by spectral inversion of a low-pass filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `cutoff` - Normalized cutoff frequency.
+    /// * `num_taps` - Length of the impulse response, must be odd.
+    /// * `window` - Window applied to the ideal impulse response.
+    ///
+    /// # Panics
+    /// - Panic if `cutoff` is not in the open interval (0, 0.5).
+    /// - Panic if `num_taps` is even.
+    pub fn high_pass(cutoff: f32, num_taps: usize, window: Window) -> Self {
+        assert!(num_taps % 2 == 1);
+
+        let mut taps = -Self::low_pass(cutoff, num_taps, window).taps;
+        taps[num_taps / 2] += 1.;
+
+        Self { taps }
+    }
+
+    /// Design a band-pass filter as the difference of two low-pass filters.
+    ///
+    /// # Arguments
+    ///
+    /// * `low` - Normalized lower cutoff frequency.
+    /// * `high` - Normalized upper cutoff frequency.
+    /// * `num_taps` - Length of the impulse response, must be odd.
+    /// * `window` - Window applied to the ideal impulse response.
+    ///
+    /// # Panics
+    /// - Panic if `low >= high`.
+    /// - Panic if the cutoff frequencies are not in the open interval (0, 0.5).
+    /// - Panic if `num_taps` is even.
+    pub fn band_pass(low: f32, high: f32, num_taps: usize, window: Window) -> Self {
+        assert!(low < high);
+        assert!(num_taps % 2 == 1);
+
+        let taps = Self::low_pass(high, num_taps, window).taps
+            - Self::low_pass(low, num_taps, window).taps;
+
+        Self { taps }
+    }
+
+    /// Return the impulse response of the filter.
+    pub fn taps(&self) -> ArrayView1<'_, f32> {
+        self.taps.view()
+    }
+}
+
+impl Filter for FirFilter {
+    fn filter(&self, signal: ArrayView1<f32>) -> Array1<f32> {
+        Array1::from_shape_fn(signal.len(), |n| {
+            self.taps
+                .iter()
+                .take(n + 1)
+                .enumerate()
+                .map(|(k, &h)| h * signal[n - k])
+                .sum()
+        })
+    }
+
+    fn gain(&self, frequency: f32) -> f32 {
+        let (re, im) = self
+            .taps
+            .iter()
+            .enumerate()
+            .fold((0., 0.), |(re, im), (k, &h)| {
+                let w = 2. * PI * frequency * k as f32;
+                (re + h * w.cos(), im - h * w.sin())
+            });
+
+        (re * re + im * im).sqrt()
+    }
+
+    fn pad_length(&self) -> usize {
+        3 * self.taps.len()
+    }
+}
+
+/// Compute the windowed impulse response of an ideal low-pass filter.
+fn windowed_sinc(cutoff: f32, num_taps: usize, window: Window) -> Array1<f32> {
+    let center = (num_taps - 1) as f32 / 2.;
+    let sinc = Array1::from_shape_fn(num_taps, |i| {
+        let x = 2. * cutoff * (i as f32 - center);
+        if x == 0. {
+            2. * cutoff
+        } else {
+            2. * cutoff * (PI * x).sin() / (PI * x)
+        }
+    });
+
+    sinc * window.coefficients(num_taps)
+}
+
+/// Second-order infinite impulse response section, with transfer function
+/// `H(z) = (b0 + b1 z^-1 + b2 z^-2) / (1 + a1 z^-1 + a2 z^-2)`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Biquad {
+    pub b0: f32,
+    pub b1: f32,
+    pub b2: f32,
+    pub a1: f32,
+    pub a2: f32,
+}
+
+impl Biquad {
+    /// Design a second-order low-pass section with the given quality factor.
+    fn low_pass(cutoff: f32, q: f32) -> Self {
+        let w0 = 2. * PI * cutoff;
+        let alpha = w0.sin() / (2. * q);
+        let cos = w0.cos();
+        let a0 = 1. + alpha;
+
+        Self {
+            b0: (1. - cos) / 2. / a0,
+            b1: (1. - cos) / a0,
+            b2: (1. - cos) / 2. / a0,
+            a1: -2. * cos / a0,
+            a2: (1. - alpha) / a0,
+        }
+    }
+
+    /// Design a second-order high-pass section with the given quality factor.
+    fn high_pass(cutoff: f32, q: f32) -> Self {
+        let w0 = 2. * PI * cutoff;
+        let alpha = w0.sin() / (2. * q);
+        let cos = w0.cos();
+        let a0 = 1. + alpha;
+
+        Self {
+            b0: (1. + cos) / 2. / a0,
+            b1: -(1. + cos) / a0,
+            b2: (1. + cos) / 2. / a0,
+            a1: -2. * cos / a0,
+            a2: (1. - alpha) / a0,
+        }
+    }
+
+    /// Design a first-order low-pass section using the bilinear transform.
+    fn first_order_low_pass(cutoff: f32) -> Self {
+        let k = (PI * cutoff).tan();
+
+        Self {
+            b0: k / (1. + k),
+            b1: k / (1. + k),
+            b2: 0.,
+            a1: (k - 1.) / (k + 1.),
+            a2: 0.,
+        }
+    }
+
+    /// Design a first-order high-pass section using the bilinear transform.
+    fn first_order_high_pass(cutoff: f32) -> Self {
+        let k = (PI * cutoff).tan();
+
+        Self {
+            b0: 1. / (1. + k),
+            b1: -1. / (1. + k),
+            b2: 0.,
+            a1: (k - 1.) / (k + 1.),
+            a2: 0.,
+        }
+    }
+
+    /// Return the magnitude of the frequency response of the section.
+    fn gain(&self, frequency: f32) -> f32 {
+        let w = 2. * PI * frequency;
+        let (c1, s1, c2, s2) = (w.cos(), w.sin(), (2. * w).cos(), (2. * w).sin());
+
+        let num_re = self.b0 + self.b1 * c1 + self.b2 * c2;
+        let num_im = -self.b1 * s1 - self.b2 * s2;
+        let den_re = 1. + self.a1 * c1 + self.a2 * c2;
+        let den_im = -self.a1 * s1 - self.a2 * s2;
+
+        ((num_re * num_re + num_im * num_im) / (den_re * den_re + den_im * den_im)).sqrt()
+    }
+}
+
+/// Infinite impulse response (IIR) filter implemented as a cascade of [`Biquad`] sections.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IirFilter {
+    sections: Vec<Biquad>,
+}
+
+impl IirFilter {
+    /// Creates a new [`IirFilter`] from a cascade of second-order sections.
+    pub fn new(sections: Vec<Biquad>) -> Self {
+        Self { sections }
+    }
+
+    /// Design a Butterworth low-pass filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `order` - Order of the filter. The higher, the sharper the transition.
+    /// * `cutoff` - Normalized cutoff frequency, at which the gain is -3 dB.
+    ///
+    /// # Panics
+    /// - Panic if `order` is 0.
+    /// - Panic if `cutoff` is not in the open interval (0, 0.5).
+    pub fn butterworth_low_pass(order: usize, cutoff: f32) -> Self {
+        Self::butterworth(
+            order,
+            cutoff,
+            Biquad::low_pass,
+            Biquad::first_order_low_pass,
+        )
+    }
+
+    /// Design a Butterworth high-pass filter.
+    ///
+    /// # Arguments
+    ///
+    /// * `order` - Order of the filter. The higher, the sharper the transition.
+    /// * `cutoff` - Normalized cutoff frequency, at which the gain is -3 dB.
+    ///
+    /// # Panics
+    /// - Panic if `order` is 0.
+    /// - Panic if `cutoff` is not in the open interval (0, 0.5).
+    pub fn butterworth_high_pass(order: usize, cutoff: f32) -> Self {
+        Self::butterworth(
+            order,
+            cutoff,
+            Biquad::high_pass,
+            Biquad::first_order_high_pass,
+        )
+    }
+
+    /// Design a band-pass filter as the cascade of a Butterworth high-pass filter at `low` and a
+    /// Butterworth low-pass filter at `high`, both of the given order.
+    ///
+    /// # Panics
+    /// - Panic if `order` is 0.
+    /// - Panic if `low >= high`.
+    /// - Panic if the cutoff frequencies are not in the open interval (0, 0.5).
+    pub fn butterworth_band_pass(order: usize, low: f32, high: f32) -> Self {
+        assert!(low < high);
+
+        let mut sections = Self::butterworth_high_pass(order, low).sections;
+        sections.extend(Self::butterworth_low_pass(order, high).sections);
+
+        Self { sections }
+    }
+
+    /// Return the second-order sections of the filter.
+    pub fn sections(&self) -> &[Biquad] {
+        &self.sections
+    }
+
+    fn butterworth(
+        order: usize,
+        cutoff: f32,
+        second_order: fn(f32, f32) -> Biquad,
+        first_order: fn(f32) -> Biquad,
+    ) -> Self {
+        assert!(order > 0);
+        assert!(cutoff > 0. && cutoff < 0.5);
+
+        // Each pair of complex conjugate poles of the analog prototype gives a second-order
+        // section, whose quality factor depends on the pole angle.
+        let mut sections: Vec<Biquad> = (0..order / 2)
+            .map(|k| {
+                let angle = PI * (2 * k + order + 1) as f32 / (2 * order) as f32;
+                second_order(cutoff, -1. / (2. * angle.cos()))
+            })
+            .collect();
+        if order % 2 == 1 {
+            sections.push(first_order(cutoff));
+        }
+
+        Self { sections }
+    }
+}
+
+impl Filter for IirFilter {
+    fn filter(&self, signal: ArrayView1<f32>) -> Array1<f32> {
+        let mut output = signal.to_owned();
+        for section in self.sections.iter() {
+            // Transposed direct form II
+            let (mut z1, mut z2) = (0., 0.);
+            for x in output.iter_mut() {
+                let y = section.b0 * *x + z1;
+                z1 = section.b1 * *x - section.a1 * y + z2;
+                z2 = section.b2 * *x - section.a2 * y;
+                *x = y;
+            }
+        }
+
+        output
+    }
+
+    fn gain(&self, frequency: f32) -> f32 {
+        self.sections
+            .iter()
+            .map(|section| section.gain(frequency))
+            .product()
+    }
+
+    fn pad_length(&self) -> usize {
+        3 * (2 * self.sections.len() + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Filter, FirFilter, IirFilter};
+    use crate::preprocessors::window::Window;
+    use ndarray::{Array1, Array2, s};
+    use std::f32::consts::{FRAC_1_SQRT_2, PI};
+
+    fn sinusoid(frequency: f32, length: usize) -> Array1<f32> {
+        Array1::from_shape_fn(length, |i| (2. * PI * frequency * i as f32).sin())
+    }
+
+    fn amplitude(signal: &Array1<f32>) -> f32 {
+        signal.iter().fold(0., |acc, &x| f32::max(acc, x.abs()))
+    }
+
+    #[test]
+    fn test_fir_low_pass() {
+        let filter = FirFilter::low_pass(0.1, 51, Window::Hamming);
+        assert!((filter.gain(0.) - 1.).abs() < 1e-5);
+        assert!(filter.gain(0.2) < 0.01);
+
+        let filtered = filter.apply(sinusoid(0.3, 400).view());
+        assert!(amplitude(&filtered.slice(s![51..]).to_owned()) < 0.01);
+    }
+
+    #[test]
+    fn test_fir_high_pass_band_pass() {
+        let high_pass = FirFilter::high_pass(0.1, 51, Window::Blackman);
+        assert!(high_pass.gain(0.) < 1e-4);
+        assert!((high_pass.gain(0.3) - 1.).abs() < 0.01);
+
+        let band_pass = FirFilter::band_pass(0.1, 0.2, 101, Window::Hamming);
+        assert!(band_pass.gain(0.) < 0.01);
+        assert!((band_pass.gain(0.15) - 1.).abs() < 0.01);
+        assert!(band_pass.gain(0.35) < 0.01);
+    }
+
+    #[test]
+    fn test_butterworth() {
+        for order in 1..6 {
+            let low_pass = IirFilter::butterworth_low_pass(order, 0.1);
+            assert_eq!(low_pass.sections().len(), order.div_ceil(2));
+            assert!((low_pass.gain(0.) - 1.).abs() < 1e-4);
+            assert!((low_pass.gain(0.1) - FRAC_1_SQRT_2).abs() < 1e-4);
+
+            let high_pass = IirFilter::butterworth_high_pass(order, 0.1);
+            assert!((high_pass.gain(0.5) - 1.).abs() < 1e-4);
+            assert!((high_pass.gain(0.1) - FRAC_1_SQRT_2).abs() < 1e-4);
+        }
+
+        // Measured gain of the filtered signal matches the frequency response, the amplitude is
+        // measured from the RMS value over whole periods once the transient has vanished.
+        let low_pass = IirFilter::butterworth_low_pass(4, 0.1);
+        let filtered = low_pass.apply(sinusoid(0.1, 2000).view());
+        let measured = (2. * filtered.slice(s![1000..]).mapv(|x| x * x).mean().unwrap()).sqrt();
+        assert!((measured - FRAC_1_SQRT_2).abs() < 0.01);
+
+        let band_pass = IirFilter::butterworth_band_pass(4, 0.1, 0.2);
+        assert!(band_pass.gain(0.02) < 0.01);
+        assert!(band_pass.gain(0.4) < 0.01);
+    }
+
+    #[test]
+    fn test_zero_phase() {
+        let filter = IirFilter::butterworth_low_pass(4, 0.1);
+        let signal = sinusoid(0.02, 500);
+
+        let filtered = filter.apply_zero_phase(signal.view());
+        assert_eq!(filtered.len(), signal.len());
+        // No phase shift in the pass band
+        for i in 50..450 {
+            assert!((filtered[i] - signal[i]).abs() < 0.01);
+        }
+
+        let traces = Array2::from_shape_fn((3, 100), |(i, j)| ((i * 31 + j * 17) % 23) as u8);
+        let filtered = filter.batch_apply_zero_phase(traces.view());
+        for (trace, row) in traces.rows().into_iter().zip(filtered.rows()) {
+            assert_eq!(filter.apply_zero_phase(trace), row);
+        }
+    }
+
+    #[test]
+    fn test_serialize_deserialize_filter() {
+        let filter = IirFilter::butterworth_band_pass(3, 0.05, 0.15);
+        let serialized = serde_json::to_string(&filter).unwrap();
+        let restored: IirFilter = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(filter, restored);
+
+        let filter = FirFilter::low_pass(0.2, 15, Window::Hann);
+        let serialized = serde_json::to_string(&filter).unwrap();
+        let restored: FirFilter = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(filter, restored);
+    }
+}
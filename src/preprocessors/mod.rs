@@ -0,0 +1,702 @@
+use itertools::Itertools;
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Zip, s};
+use num_traits::{AsPrimitive, One, Zero};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    ops::{Div, Range},
+};
+
+use crate::{Sample, processors::MeanVar};
+
+pub mod combination;
+pub mod compression;
+pub mod filter;
+pub mod quality;
+pub mod resync;
+pub mod spectral;
+pub mod static_alignment;
+pub mod window;
+
+/// Convert a trace sample to `f32`.
+fn as_f32<T>(x: T) -> f32
+where
+    T: Sample + Copy,
+{
+    <T as Sample>::Container::from(x).as_()
+}
+
+/// Apply `f` on each row of `traces` in parallel, each row producing `output_length` values.
+fn batch_apply<T, F>(traces: ArrayView2<T>, output_length: usize, f: F) -> Array2<f32>
+where
+    T: Sync,
+    F: Fn(ArrayView1<T>) -> Array1<f32> + Sync,
+{
+    let rows: Vec<Array1<f32>> = (0..traces.shape()[0])
+        .into_par_iter()
+        .map(|i| f(traces.row(i)))
+        .collect();
+
+    let mut output = Array2::zeros((rows.len(), output_length));
+    for (mut output_row, row) in output.rows_mut().into_iter().zip(rows.iter()) {
+        output_row.assign(row);
+    }
+
+    output
+}
+
+/// Computes the centered product of "order" leakage samples
+/// Used particularly when performing high-order SCA
+///
+/// See [`combination::Combination`] for other combining functions and explicit sample tuples.
+#[derive(Debug)]
+pub struct CenteredProduct {
+    /// Sum of traces
+    acc: Array1<i64>,
+    /// Number of traces processed
+    count: usize,
+    /// Mean of traces
+    mean: Array1<f32>,
+    /// Indices of the samples of each product, from the Cartesian product of the intervals
+    combinations: Vec<Vec<usize>>,
+    /// Boolean to ensure that finalize function happened before apply
+    processed: bool,
+}
+
+impl CenteredProduct {
+    /// Creates a new CenteredProduct processor.
+    ///
+    /// # Arguments
+    ///
+    /// * `trace_length`: Number of samples per trace.
+    /// * `intervals` - Intervals to combine
+    pub fn new(trace_length: usize, intervals: Vec<Range<usize>>) -> Self {
+        Self {
+            acc: Array1::zeros(trace_length),
+            count: 0,
+            combinations: intervals.into_iter().multi_cartesian_product().collect(),
+            processed: false,
+            mean: Array1::zeros(trace_length),
+        }
+    }
+
+    /// Processes an input trace to update internal accumulators.
+    pub fn process<T: Into<i64> + Copy>(&mut self, trace: ArrayView1<T>) {
+        let trace_length = self.acc.len();
+        for i in 0..trace_length {
+            let x = trace[i].into();
+            self.acc[i] += x;
+        }
+        self.count += 1
+    }
+
+    /// Compute the mean
+    pub fn finalize(&mut self) {
+        if self.count != 0 {
+            self.mean = self.acc.mapv(|x| x as f32 / self.count as f32)
+        }
+        self.processed = true
+    }
+
+    /// Apply the processing to an input trace
+    /// The centered product subtracts the mean of the traces and then performs products between input samples
+    pub fn apply<T: Into<f32> + Copy>(&self, trace: ArrayView1<T>) -> Array1<f32> {
+        // First we subtract the mean trace
+        let centered_trace: Array1<f32> = trace.mapv(|x| x.into()) - &self.mean;
+
+        // Then we do the products
+        self.combinations
+            .iter()
+            .map(|combination| combination.iter().map(|&i| centered_trace[i]).product())
+            .collect()
+    }
+}
+
+/// Elevates parts of a trace to a certain power
+#[derive(Debug)]
+pub struct Power {
+    intervals: Vec<Range<usize>>,
+    power: i32,
+}
+
+impl Power {
+    /// Creates a new Power processor.
+    ///
+    /// # Arguments
+    ///
+    /// * `intervals` - Intervals to elevate to the power
+    /// * `power` - Power to elevate
+    pub fn new(intervals: Vec<Range<usize>>, power: i32) -> Self {
+        Self { intervals, power }
+    }
+
+    /// Processes an input trace
+    pub fn process<T: Into<i64> + Copy>(&self, trace: ArrayView1<T>) -> Array1<f32> {
+        // Concatenate the slices specified by the ranges
+        let result: Array1<_> = self
+            .intervals
+            .iter()
+            .flat_map(|range| trace.slice(s![range.clone()]).to_owned())
+            .map(|val| val.into() as f32)
+            .collect();
+
+        result.mapv(|result| result.powi(self.power))
+    }
+}
+
+/// Standardization of the traces by removing the mean and scaling to unit variance
+pub struct StandardScaler<T>
+where
+    T: Sample,
+{
+    /// meanVar processor
+    meanvar: MeanVar<T>,
+    /// mean
+    mean: Array1<f32>,
+    /// std
+    std: Array1<f32>,
+}
+
+impl<T> StandardScaler<T>
+where
+    T: Sample + Copy,
+{
+    pub fn new(trace_length: usize) -> Self {
+        Self {
+            meanvar: MeanVar::new(trace_length),
+            mean: Array1::zeros(trace_length),
+            std: Array1::zeros(trace_length),
+        }
+    }
+
+    /// Processes an input trace to update internal accumulators.
+    pub fn process(&mut self, trace: ArrayView1<T>) {
+        self.meanvar.process(trace);
+    }
+
+    /// Compute mean and var
+    pub fn finalize(&mut self) {
+        self.mean = self.meanvar.mean();
+        self.std = self.meanvar.var().mapv(f32::sqrt);
+    }
+
+    /// Apply the processing to an input trace
+    pub fn apply(&self, trace: ArrayView1<T>) -> Array1<f32> {
+        (trace.mapv(|x| <T as Sample>::Container::from(x).as_()) - &self.mean) / &self.std
+    }
+}
+
+pub use dtw::dist;
+
+/// Result of the elastic alignment of a trace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warp<T>
+where
+    T: dtw::SumContainer,
+{
+    /// Trace warped onto the reference trace
+    pub aligned_trace: Array1<T>,
+    /// Warp path, as pairs of (reference trace index, trace index)
+    pub path: Vec<(usize, usize)>,
+    /// Sum of the distances between the matched samples along the warp path
+    pub distance: T::Container,
+}
+
+/// Return the samples of `trace` as a slice, copying them if they are not contiguous.
+fn contiguous<'a, T: Clone>(trace: ArrayView1<'a, T>) -> Cow<'a, [T]> {
+    match trace.to_slice() {
+        Some(samples) => Cow::Borrowed(samples),
+        None => Cow::Owned(trace.to_vec()),
+    }
+}
+
+/// Align traces using elastic alignment[^1]. Elastic alignment is a dynamic alignment algorithm
+/// based on FastDTW.
+///
+/// [^1]: van Woudenberg, J.G.J., Witteman, M.F., Bakker, B. (2011). Improving Differential Power Analysis by Elastic Alignment. In: Kiayias, A. (eds) Topics in Cryptology – CT-RSA 2011. CT-RSA 2011. Lecture Notes in Computer Science, vol 6558. Springer, Berlin, Heidelberg. <https://doi.org/10.1007/978-3-642-19074-2_8>
+///
+/// # Examples
+/// ```rust
+/// use muscat::preprocessors::{ElasticAlignment, dist::euclidean_distance};
+/// use ndarray::array;
+///
+/// let reference_trace = array![77, 117, 5, 51, 91, -12, -33];
+/// let trace_to_align = array![77, 117, 13, 15, 5, 51, 91];
+/// let elastic_alignment = ElasticAlignment::new(reference_trace, 1, euclidean_distance);
+///
+/// let aligned_trace = elastic_alignment.align(trace_to_align.view());
+/// ```
+pub struct ElasticAlignment<T, D>
+where
+    D: Fn(T, T) -> T,
+{
+    reference_trace: Array1<T>,
+    /// FastDTW radius
+    radius: usize,
+    /// Distance function
+    dist: D,
+}
+
+impl<T, D> ElasticAlignment<T, D>
+where
+    T: dtw::Average + dtw::SumContainer + Copy + Zero + 'static,
+    T::Container: Zero + One + Div<Output = T::Container> + AsPrimitive<T>,
+    D: Fn(T, T) -> T,
+{
+    /// Creates a new [`ElasticAlignment`] with the given reference trace and distance function.
+    pub fn new(reference_trace: Array1<T>, radius: usize, dist: D) -> Self {
+        Self {
+            reference_trace,
+            radius,
+            dist,
+        }
+    }
+
+    /// Returns the reference trace.
+    pub fn reference_trace(&self) -> ArrayView1<'_, T> {
+        self.reference_trace.view()
+    }
+
+    /// Warp given trace onto the reference trace using elastic alignment (see
+    /// [`ElasticAlignment`]).
+    ///
+    /// Traces do not need to be contiguous in memory.
+    ///
+    /// # Panics
+    /// Panic if the trace length differs from the reference trace length.
+    pub fn warp_with_cmp<C>(&self, trace: ArrayView1<T>, cmp: &C) -> Warp<T>
+    where
+        C: Fn(&T::Container, &T::Container) -> Ordering,
+    {
+        // The DTW implementation does not support time series of different lengths
+        assert_eq!(
+            trace.len(),
+            self.reference_trace.len(),
+            "trace length differs from the reference trace length"
+        );
+
+        let reference_trace = contiguous(self.reference_trace.view());
+        let samples = contiguous(trace);
+        let path = dtw::fast_dtw_with_cmp(&reference_trace, &samples, self.radius, &self.dist, cmp);
+
+        let distance = path.iter().fold(T::Container::zero(), |acc, &(i, j)| {
+            acc + T::Container::from((self.dist)(reference_trace[i], samples[j]))
+        });
+
+        // Path indices are reference positions first, thus the aligned trace follows the reference
+        let mut aligned_trace = Array1::zeros(reference_trace.len());
+        let mut k = 0;
+        for j in 0..reference_trace.len() {
+            let mut count = T::Container::zero();
+            let mut sum = T::Container::zero();
+
+            while k < path.len() && path[k].0 == j {
+                count = count + T::Container::one();
+                sum = sum + T::Container::from(trace[path[k].1]);
+                k += 1;
+            }
+
+            aligned_trace[j] = (sum / count).as_();
+        }
+
+        Warp {
+            aligned_trace,
+            path,
+            distance,
+        }
+    }
+
+    /// Align given trace using elastic alignment (see [`ElasticAlignment`]).
+    ///
+    /// # Panics
+    /// Panic if the trace length differs from the reference trace length.
+    pub fn align_with_cmp<C>(&self, trace: ArrayView1<T>, cmp: &C) -> Array1<T>
+    where
+        C: Fn(&T::Container, &T::Container) -> Ordering,
+    {
+        self.warp_with_cmp(trace, cmp).aligned_trace
+    }
+
+    /// Warp each trace of the given batch in parallel (see [`ElasticAlignment::warp_with_cmp`]).
+    ///
+    /// # Panics
+    /// Panic if the trace length differs from the reference trace length.
+    pub fn batch_warp_with_cmp<C>(&self, traces: ArrayView2<T>, cmp: &C) -> Vec<Warp<T>>
+    where
+        T: Send + Sync,
+        T::Container: Send,
+        D: Sync,
+        C: Fn(&T::Container, &T::Container) -> Ordering + Sync,
+    {
+        (0..traces.shape()[0])
+            .into_par_iter()
+            .map(|i| self.warp_with_cmp(traces.row(i), cmp))
+            .collect()
+    }
+
+    /// Align each trace of the given batch in parallel (see [`ElasticAlignment::align_with_cmp`]).
+    ///
+    /// # Panics
+    /// Panic if the trace length differs from the reference trace length.
+    pub fn batch_align_with_cmp<C>(&self, traces: ArrayView2<T>, cmp: &C) -> Array2<T>
+    where
+        T: Send + Sync,
+        T::Container: Send,
+        D: Sync,
+        C: Fn(&T::Container, &T::Container) -> Ordering + Sync,
+    {
+        let warps = self.batch_warp_with_cmp(traces, cmp);
+
+        let mut aligned_traces = Array2::zeros((warps.len(), self.reference_trace.len()));
+        for (mut row, warp) in aligned_traces.rows_mut().into_iter().zip(warps.iter()) {
+            row.assign(&warp.aligned_trace);
+        }
+
+        aligned_traces
+    }
+
+    /// Iteratively refine the reference trace: at each iteration, the traces are aligned on the
+    /// current reference trace, and the mean of the aligned traces becomes the new reference
+    /// trace.
+    ///
+    /// Return the mean DTW distance of the traces to the reference trace at each iteration, which
+    /// can be used to monitor convergence.
+    ///
+    /// # Panics
+    /// - Panic if `traces` is empty.
+    /// - Panic if the trace length differs from the reference trace length.
+    pub fn refine_reference_with_cmp<C>(
+        &mut self,
+        traces: ArrayView2<T>,
+        num_iterations: usize,
+        cmp: &C,
+    ) -> Vec<f64>
+    where
+        T: Send + Sync,
+        T::Container: Send + AsPrimitive<f64>,
+        usize: AsPrimitive<T::Container>,
+        D: Sync,
+        C: Fn(&T::Container, &T::Container) -> Ordering + Sync,
+    {
+        assert!(traces.shape()[0] > 0);
+
+        let num_traces: T::Container = traces.shape()[0].as_();
+        (0..num_iterations)
+            .map(|_| {
+                let warps = self.batch_warp_with_cmp(traces, cmp);
+
+                let mut sum = Array1::from_elem(self.reference_trace.len(), T::Container::zero());
+                let mut distance = 0f64;
+                for warp in warps.iter() {
+                    Zip::from(&mut sum)
+                        .and(&warp.aligned_trace)
+                        .for_each(|acc, &x| *acc = *acc + T::Container::from(x));
+                    distance += AsPrimitive::<f64>::as_(warp.distance);
+                }
+                self.reference_trace = sum.mapv(|x| (x / num_traces).as_());
+
+                distance / warps.len() as f64
+            })
+            .collect()
+    }
+}
+
+impl<T, D> ElasticAlignment<T, D>
+where
+    T: dtw::Average + dtw::SumContainer + Copy + Zero + 'static,
+    T::Container: Zero + One + Div<Output = T::Container> + AsPrimitive<T> + Ord,
+    D: Fn(T, T) -> T,
+{
+    /// Align given trace using elastic alignment (see [`ElasticAlignment`]).
+    ///
+    /// NOTE: See [`ElasticAlignment::align_with_cmp`] for types that do not implement [`Ord`].
+    pub fn align(&self, trace: ArrayView1<T>) -> Array1<T> {
+        self.align_with_cmp(trace, &T::Container::cmp)
+    }
+
+    /// Warp given trace using elastic alignment (see [`ElasticAlignment::warp_with_cmp`]).
+    pub fn warp(&self, trace: ArrayView1<T>) -> Warp<T> {
+        self.warp_with_cmp(trace, &T::Container::cmp)
+    }
+
+    /// Align each trace of the given batch in parallel (see [`ElasticAlignment::align`]).
+    pub fn batch_align(&self, traces: ArrayView2<T>) -> Array2<T>
+    where
+        T: Send + Sync,
+        T::Container: Send,
+        D: Sync,
+    {
+        self.batch_align_with_cmp(traces, &T::Container::cmp)
+    }
+
+    /// Iteratively refine the reference trace (see
+    /// [`ElasticAlignment::refine_reference_with_cmp`]).
+    pub fn refine_reference(&mut self, traces: ArrayView2<T>, num_iterations: usize) -> Vec<f64>
+    where
+        T: Send + Sync,
+        T::Container: Send + AsPrimitive<f64>,
+        usize: AsPrimitive<T::Container>,
+        D: Sync,
+    {
+        self.refine_reference_with_cmp(traces, num_iterations, &T::Container::cmp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::preprocessors::{CenteredProduct, ElasticAlignment, Power, StandardScaler, dist};
+    use ndarray::{Array1, Array2, array, s};
+
+    fn round_to_2_digits(x: f32) -> f32 {
+        (x * 100f32).round() / 100f32
+    }
+
+    #[test]
+    fn test_centered_product() {
+        let mut processor = CenteredProduct::new(5, vec![0..2, 3..5]);
+        processor.process(array![0i16, 1i16, 2i16, -3i16, -4i16].view());
+        processor.finalize();
+        assert_eq!(
+            processor.apply(array![0i16, 1i16, 2i16, -3i16, -4i16].view()),
+            array![0f32, 0f32, 0f32, 0f32]
+        );
+        let traces = [
+            array![77, 137, 51, 91],
+            array![72, 61, 91, 83],
+            array![39, 49, 52, 23],
+            array![26, 114, 63, 45],
+            array![30, 8, 97, 91],
+            array![13, 68, 7, 45],
+            array![17, 181, 60, 34],
+            array![43, 88, 76, 78],
+            array![0, 36, 35, 0],
+            array![93, 191, 49, 26],
+        ];
+
+        let mut processor2 = CenteredProduct::new(4, vec![0..1, 1..2, 2..4]);
+        for t in traces.iter() {
+            processor2.process(t.view());
+        }
+        processor2.finalize();
+
+        let expected_results = [
+            array![-11169.72, 61984.08],
+            array![-32942.77, -31440.83],
+            array![-540.46, -2533.96],
+            array![-1521.45, 2049.30],
+            array![36499.88, 36969.02],
+            array![-36199.24, -4675.44],
+            array![-3999.12, 37044.47],
+            array![-189.74, -279.84],
+            array![-54268.83, -121223.88],
+            array![-46231.64, -130058.23],
+        ];
+
+        for (i, t) in traces.iter().enumerate() {
+            assert_eq!(
+                processor2
+                    .apply(t.mapv(|x| x as f32).view())
+                    .mapv(round_to_2_digits),
+                expected_results[i]
+            );
+        }
+    }
+
+    #[test]
+    fn test_power() {
+        let t = array![-1, 2, -3, 4, -5, 6];
+        let processor1 = Power {
+            intervals: vec![0..2, 4..6],
+            power: 1,
+        };
+        let processor2 = Power {
+            intervals: vec![0..2, 4..6],
+            power: 2,
+        };
+        let processor3 = Power {
+            intervals: vec![0..2, 4..6],
+            power: 3,
+        };
+        let expected_results = [
+            array![-1.0, 2.0, -5.0, 6.0],
+            array![1.0, 4.0, 25.0, 36.0],
+            array![-1.0, 8.0, -125.0, 216.0],
+        ];
+
+        assert_eq!(
+            processor1.process(t.view()).mapv(round_to_2_digits),
+            expected_results[0]
+        );
+        assert_eq!(
+            processor2.process(t.view()).mapv(round_to_2_digits),
+            expected_results[1]
+        );
+        assert_eq!(
+            processor3.process(t.view()).mapv(round_to_2_digits),
+            expected_results[2]
+        );
+    }
+
+    #[test]
+    fn test_standard_scaler() {
+        let traces = [
+            array![77, 137, 51, 91],
+            array![72, 61, 91, 83],
+            array![39, 49, 52, 23],
+            array![26, 114, 63, 45],
+            array![30, 8, 97, 91],
+            array![13, 68, 7, 45],
+            array![17, 181, 60, 34],
+            array![43, 88, 76, 78],
+            array![0, 36, 35, 0],
+            array![93, 191, 49, 26],
+        ];
+
+        let mut processor = StandardScaler::new(4);
+        for t in traces.iter() {
+            processor.process(t.view());
+        }
+        processor.finalize();
+
+        let expected_results = [
+            array![1.25, 0.75, -0.28, 1.29],
+            array![1.07, -0.56, 1.32, 1.03],
+            array![-0.07, -0.76, -0.24, -0.94],
+            array![-0.52, 0.36, 0.20, -0.22],
+            array![-0.38, -1.47, 1.55, 1.29],
+            array![-0.97, -0.44, -2.04, -0.22],
+            array![-0.83, 1.51, 0.08, -0.58],
+            array![0.07, -0.09, 0.72, 0.86],
+            array![-1.42, -0.99, -0.92, -1.69],
+            array![1.80, 1.68, -0.36, -0.84],
+        ];
+
+        for (i, t) in traces.iter().enumerate() {
+            assert_eq!(
+                processor.apply(t.view()).mapv(round_to_2_digits),
+                expected_results[i]
+            );
+        }
+    }
+
+    #[test]
+    fn test_elastic_align() {
+        let reference_trace = array![77, 117, 5, 51, 91, -12, -33];
+        let trace = array![77, 117, 13, 15, 5, 51, 91];
+
+        let elastic_alignment =
+            ElasticAlignment::new(reference_trace.clone(), 1, dist::euclidean_distance);
+        assert_eq!(
+            elastic_alignment.align(trace.view()),
+            array![77, 117, 11, 51, 51, 51, 91]
+        );
+    }
+
+    #[test]
+    fn test_elastic_align_same_trace() {
+        let reference_trace = array![77, 117, 5, 51, 91, -12, -33];
+
+        let elastic_alignment =
+            ElasticAlignment::new(reference_trace.clone(), 1, dist::euclidean_distance);
+        assert_eq!(
+            elastic_alignment.align(reference_trace.view()),
+            reference_trace
+        );
+    }
+
+    #[test]
+    fn test_elastic_warp() {
+        let reference_trace = array![77i32, 117, 5, 51, 91, -12, -33];
+        let trace = array![77, 117, 13, 15, 5, 51, 91];
+
+        let elastic_alignment =
+            ElasticAlignment::new(reference_trace.clone(), 1, dist::euclidean_distance);
+        let warp = elastic_alignment.warp(trace.view());
+        assert_eq!(warp.aligned_trace, elastic_alignment.align(trace.view()));
+        assert_eq!(
+            warp.distance,
+            warp.path
+                .iter()
+                .map(|&(i, j)| (reference_trace[i] - trace[j]).abs() as i64)
+                .sum::<i64>()
+        );
+
+        assert_eq!(elastic_alignment.warp(reference_trace.view()).distance, 0);
+    }
+
+    #[test]
+    fn test_elastic_batch_align() {
+        let reference_trace = array![77, 117, 5, 51, 91, -12, -33];
+        let traces = array![
+            [77, 117, 13, 15, 5, 51, 91],
+            [77, 117, 5, 51, 91, -12, -33],
+            [70, 77, 117, 5, 51, 91, -12],
+        ];
+
+        let elastic_alignment =
+            ElasticAlignment::new(reference_trace.clone(), 1, dist::euclidean_distance);
+        let aligned_traces = elastic_alignment.batch_align(traces.view());
+        for (trace, aligned_trace) in traces.rows().into_iter().zip(aligned_traces.rows()) {
+            assert_eq!(elastic_alignment.align(trace), aligned_trace);
+        }
+
+        // Strided views are accepted
+        let interleaved = Array2::from_shape_fn((3, 14), |(i, j)| traces[[i, j / 2]]);
+        let strided = interleaved.slice(s![.., ..;2]);
+        assert!(strided.row(0).as_slice().is_none());
+        assert_eq!(elastic_alignment.batch_align(strided), aligned_traces);
+    }
+
+    #[test]
+    fn test_elastic_refine_reference() {
+        let trace = array![0, 2, 8, 20, 8, 2, 0, 0, 0, 0];
+        let traces = Array2::from_shape_fn((4, 10), |(i, j)| trace[(j + 10 - i) % 10]);
+
+        let mut elastic_alignment =
+            ElasticAlignment::new(Array1::zeros(10), 1, dist::euclidean_distance);
+        let distances = elastic_alignment.refine_reference(traces.view(), 3);
+        assert_eq!(distances.len(), 3);
+        // The refined reference trace is closer to the traces than the initial one
+        assert!(distances[2] < distances[0]);
+        assert_eq!(elastic_alignment.reference_trace().len(), 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "trace length differs")]
+    fn test_elastic_align_shorter_trace() {
+        let elastic_alignment = ElasticAlignment::new(
+            array![77, 117, 5, 51, 91, -12, -33],
+            1,
+            dist::euclidean_distance,
+        );
+        elastic_alignment.align(array![77, 117, 13, 5, 51].view());
+    }
+
+    #[test]
+    #[should_panic(expected = "trace length differs")]
+    fn test_elastic_align_longer_trace() {
+        let elastic_alignment =
+            ElasticAlignment::new(array![77, 117, 5, 51, 91], 1, dist::euclidean_distance);
+        elastic_alignment.align(array![77, 117, 13, 5, 51, 91, -12, -33].view());
+    }
+
+    #[test]
+    #[should_panic(expected = "trace length differs")]
+    fn test_elastic_batch_align_different_trace_length() {
+        let elastic_alignment = ElasticAlignment::new(
+            array![77, 117, 5, 51, 91, -12, -33],
+            1,
+            dist::euclidean_distance,
+        );
+        elastic_alignment.batch_align(array![[77, 117, 13, 5, 51], [117, 5, 51, 91, -12]].view());
+    }
+
+    #[test]
+    #[should_panic(expected = "trace length differs")]
+    fn test_elastic_refine_reference_different_trace_length() {
+        let mut elastic_alignment =
+            ElasticAlignment::new(Array1::zeros(7), 1, dist::euclidean_distance);
+        elastic_alignment.refine_reference(Array2::<i32>::zeros((2, 5)).view(), 1);
+    }
+}
@@ -0,0 +1,441 @@
+//! Detection of corrupted traces before processing.
+//!
+//! Saturated or glitched acquisitions silently bias the statistics computed by processors. A
+//! [`QualityFilter`] checks each trace before it is processed, and can be used as an iterator
+//! adapter through [`FilterQuality::filter_quality`].
+
+use ndarray::{Array1, ArrayView1};
+use num_traits::Bounded;
+use std::fmt;
+
+use super::as_f32;
+use crate::Sample;
+
+/// Reason why a trace was rejected by a [`QualityFilter`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    /// Too many samples are at the bounds of the ADC range.
+    Saturated { count: usize },
+    /// The standard deviation of the trace is below the minimum.
+    FlatLine { std: f32 },
+    /// The z-score of the energy of the trace is above the threshold.
+    EnergyOutlier { z_score: f32 },
+    /// The Mahalanobis distance of the points of interest to their mean is above the threshold.
+    PoiOutlier { distance: f32 },
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Saturated { count } => write!(f, "{count} saturated samples"),
+            Rejection::FlatLine { std } => write!(f, "flat line (standard deviation {std})"),
+            Rejection::EnergyOutlier { z_score } => {
+                write!(f, "energy outlier (z-score {z_score})")
+            }
+            Rejection::PoiOutlier { distance } => {
+                write!(
+                    f,
+                    "outlier on points of interest (Mahalanobis distance {distance})"
+                )
+            }
+        }
+    }
+}
+
+/// Decision of a [`QualityFilter`] on a trace.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Keep,
+    /// The trace is rejected for the given reasons.
+    Reject(Vec<Rejection>),
+}
+
+impl Verdict {
+    /// Returns `true` if the trace is kept.
+    pub fn is_keep(&self) -> bool {
+        matches!(self, Verdict::Keep)
+    }
+}
+
+/// Running mean and variance of a set of values (Welford's algorithm).
+#[derive(Debug, Clone)]
+struct RunningStats {
+    count: usize,
+    mean: Array1<f64>,
+    m2: Array1<f64>,
+}
+
+impl RunningStats {
+    fn new(size: usize) -> Self {
+        Self {
+            count: 0,
+            mean: Array1::zeros(size),
+            m2: Array1::zeros(size),
+        }
+    }
+
+    fn update(&mut self, values: &Array1<f64>) {
+        self.count += 1;
+        let delta = values - &self.mean;
+        self.mean += &(&delta / self.count as f64);
+        self.m2 += &(&delta * &(values - &self.mean));
+    }
+
+    /// Squared distance of `values` to the mean, normalized by the variance of each value.
+    fn normalized_squared_distance(&self, values: &Array1<f64>) -> f64 {
+        values
+            .iter()
+            .zip(self.mean.iter().zip(self.m2.iter()))
+            .map(|(&x, (&mean, &m2))| {
+                let var = m2 / (self.count - 1) as f64;
+                if var > 0. {
+                    (x - mean).powi(2) / var
+                } else {
+                    0.
+                }
+            })
+            .sum()
+    }
+}
+
+/// Outlier detection on the statistics of the kept traces.
+#[derive(Debug, Clone)]
+struct OutlierCheck {
+    threshold: f32,
+    /// Number of traces kept before outliers are rejected
+    warmup: usize,
+    stats: RunningStats,
+}
+
+impl OutlierCheck {
+    fn new(size: usize, threshold: f32, warmup: usize) -> Self {
+        Self {
+            threshold,
+            warmup: warmup.max(2),
+            stats: RunningStats::new(size),
+        }
+    }
+
+    /// Return the distance of `values` to the mean if the check is warmed up.
+    fn distance(&self, values: &Array1<f64>) -> Option<f32> {
+        (self.stats.count >= self.warmup)
+            .then(|| self.stats.normalized_squared_distance(values).sqrt() as f32)
+    }
+}
+
+/// Checks the quality of traces and rejects saturated, flat or outlier traces.
+///
+/// Checks are enabled with the builder methods. Outlier detection relies on the statistics of the
+/// traces kept so far, thus rejected traces do not bias the detection of the following ones.
+///
+/// # Examples
+/// ```rust
+/// use muscat::preprocessors::quality::{FilterQuality, QualityFilter, Rejection, Verdict};
+/// use ndarray::array;
+///
+/// let traces = array![
+///     [12u8, 30, 25, 17],
+///     [255, 255, 24, 16],
+///     [13, 29, 26, 18],
+///     [20, 20, 20, 20],
+/// ];
+///
+/// let mut quality_filter = QualityFilter::new().saturation(0).flat_line(1.);
+/// assert_eq!(quality_filter.check(traces.row(0)), Verdict::Keep);
+/// assert_eq!(
+///     quality_filter.check(traces.row(1)),
+///     Verdict::Reject(vec![Rejection::Saturated { count: 2 }])
+/// );
+///
+/// let mut quality_filter = QualityFilter::new().saturation(0).flat_line(1.);
+/// let kept = traces.rows().into_iter().filter_quality(&mut quality_filter).count();
+/// assert_eq!(kept, 2);
+/// assert_eq!(quality_filter.num_rejected(), 2);
+/// ```
+#[derive(Debug, Clone)]
+pub struct QualityFilter<T> {
+    /// ADC range bounds and maximum number of samples allowed at the bounds
+    saturation: Option<(T, T, usize)>,
+    /// Minimum standard deviation of a trace
+    min_std: Option<f32>,
+    energy: Option<OutlierCheck>,
+    /// Points of interest and outlier detection on them
+    pois: Option<(Vec<usize>, OutlierCheck)>,
+    num_kept: usize,
+    num_rejected: usize,
+}
+
+impl<T> Default for QualityFilter<T> {
+    fn default() -> Self {
+        Self {
+            saturation: None,
+            min_std: None,
+            energy: None,
+            pois: None,
+            num_kept: 0,
+            num_rejected: 0,
+        }
+    }
+}
+
+impl<T> QualityFilter<T>
+where
+    T: Sample + Copy + PartialEq,
+{
+    /// Creates a new [`QualityFilter`] without any check enabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reject traces with more than `max_count` samples at the bounds of the sample type, which
+    /// correspond to the ADC range for `u8` and `i16` traces.
+    pub fn saturation(self, max_count: usize) -> Self
+    where
+        T: Bounded,
+    {
+        self.saturation_range(T::min_value(), T::max_value(), max_count)
+    }
+
+    /// Reject traces with more than `max_count` samples equal to `min` or `max`.
+    pub fn saturation_range(mut self, min: T, max: T, max_count: usize) -> Self {
+        self.saturation = Some((min, max, max_count));
+        self
+    }
+
+    /// Reject traces whose standard deviation is below `min_std`.
+    pub fn flat_line(mut self, min_std: f32) -> Self {
+        self.min_std = Some(min_std);
+        self
+    }
+
+    /// Reject traces whose energy (sum of squared samples) has a z-score above `threshold`
+    /// compared to the kept traces. Traces are not rejected as outliers until `warmup` traces
+    /// have been kept.
+    pub fn energy_outliers(mut self, threshold: f32, warmup: usize) -> Self {
+        self.energy = Some(OutlierCheck::new(1, threshold, warmup));
+        self
+    }
+
+    /// Reject traces whose Mahalanobis distance on the points of interest `pois` is above
+    /// `threshold` compared to the kept traces, assuming independent points of interest. Traces
+    /// are not rejected as outliers until `warmup` traces have been kept.
+    pub fn poi_outliers(mut self, pois: Vec<usize>, threshold: f32, warmup: usize) -> Self {
+        let check = OutlierCheck::new(pois.len(), threshold, warmup);
+        self.pois = Some((pois, check));
+        self
+    }
+
+    /// Returns the number of traces kept so far.
+    pub fn num_kept(&self) -> usize {
+        self.num_kept
+    }
+
+    /// Returns the number of traces rejected so far.
+    pub fn num_rejected(&self) -> usize {
+        self.num_rejected
+    }
+
+    /// Check the given trace. Statistics used by outlier detection are updated if it is kept.
+    ///
+    /// # Panics
+    /// Panic if a point of interest is not within `trace`.
+    pub fn check(&mut self, trace: ArrayView1<T>) -> Verdict {
+        let mut rejections = Vec::new();
+
+        if let Some((min, max, max_count)) = self.saturation {
+            let count = trace.iter().filter(|&&x| x == min || x == max).count();
+            if count > max_count {
+                rejections.push(Rejection::Saturated { count });
+            }
+        }
+
+        if let Some(min_std) = self.min_std {
+            let samples = trace.mapv(|x| as_f32(x) as f64);
+            let std = samples.std(0.) as f32;
+            if std < min_std {
+                rejections.push(Rejection::FlatLine { std });
+            }
+        }
+
+        let energy = self.energy.as_ref().map(|check| {
+            let energy =
+                Array1::from_elem(1, trace.iter().map(|&x| (as_f32(x) as f64).powi(2)).sum());
+            if let Some(z_score) = check.distance(&energy)
+                && z_score > check.threshold
+            {
+                rejections.push(Rejection::EnergyOutlier { z_score });
+            }
+            energy
+        });
+
+        let pois = self.pois.as_ref().map(|(pois, check)| {
+            let values: Array1<f64> = pois.iter().map(|&i| as_f32(trace[i]) as f64).collect();
+            if let Some(distance) = check.distance(&values)
+                && distance > check.threshold
+            {
+                rejections.push(Rejection::PoiOutlier { distance });
+            }
+            values
+        });
+
+        if !rejections.is_empty() {
+            self.num_rejected += 1;
+            return Verdict::Reject(rejections);
+        }
+
+        if let (Some(check), Some(energy)) = (self.energy.as_mut(), energy) {
+            check.stats.update(&energy);
+        }
+        if let (Some((_, check)), Some(values)) = (self.pois.as_mut(), pois) {
+            check.stats.update(&values);
+        }
+        self.num_kept += 1;
+
+        Verdict::Keep
+    }
+}
+
+/// Items from which a trace can be checked by a [`QualityFilter`].
+pub trait TraceItem<T> {
+    /// Returns the trace of the item.
+    fn trace(&self) -> ArrayView1<'_, T>;
+}
+
+impl<T> TraceItem<T> for ArrayView1<'_, T> {
+    fn trace(&self) -> ArrayView1<'_, T> {
+        self.view()
+    }
+}
+
+impl<T> TraceItem<T> for Array1<T> {
+    fn trace(&self) -> ArrayView1<'_, T> {
+        self.view()
+    }
+}
+
+/// Traces zipped with other data, such as plaintexts.
+impl<T, A, B> TraceItem<T> for (A, B)
+where
+    A: TraceItem<T>,
+{
+    fn trace(&self) -> ArrayView1<'_, T> {
+        self.0.trace()
+    }
+}
+
+/// Iterator adapter returned by [`FilterQuality::filter_quality`].
+pub struct QualityFiltered<'a, I, T> {
+    iter: I,
+    quality_filter: &'a mut QualityFilter<T>,
+}
+
+impl<I, T> Iterator for QualityFiltered<'_, I, T>
+where
+    I: Iterator,
+    I::Item: TraceItem<T>,
+    T: Sample + Copy + PartialEq,
+{
+    type Item = I::Item;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter
+            .by_ref()
+            .find(|item| self.quality_filter.check(item.trace()).is_keep())
+    }
+}
+
+/// Extension trait to skip rejected traces of an iterator.
+pub trait FilterQuality: Iterator + Sized {
+    /// Skip the traces rejected by the given [`QualityFilter`].
+    fn filter_quality<T>(
+        self,
+        quality_filter: &mut QualityFilter<T>,
+    ) -> QualityFiltered<'_, Self, T>
+    where
+        Self::Item: TraceItem<T>,
+        T: Sample + Copy + PartialEq,
+    {
+        QualityFiltered {
+            iter: self,
+            quality_filter,
+        }
+    }
+}
+
+impl<I: Iterator> FilterQuality for I {}
+
+#[cfg(test)]
+mod tests {
+    use super::{FilterQuality, QualityFilter, Rejection, Verdict};
+    use ndarray::{Array2, array};
+
+    #[test]
+    fn test_saturation_and_flat_line() {
+        let mut quality_filter = QualityFilter::new().saturation(1).flat_line(0.5);
+
+        assert!(
+            quality_filter
+                .check(array![-32768i16, 5, 7, 1].view())
+                .is_keep()
+        );
+        assert_eq!(
+            quality_filter.check(array![-32768i16, 32767, 7, 1].view()),
+            Verdict::Reject(vec![Rejection::Saturated { count: 2 }])
+        );
+        assert_eq!(
+            quality_filter.check(array![3i16, 3, 3, 3].view()),
+            Verdict::Reject(vec![Rejection::FlatLine { std: 0. }])
+        );
+
+        let mut quality_filter = QualityFilter::new().saturation_range(0u8, 200, 0);
+        assert!(!quality_filter.check(array![3u8, 200, 7].view()).is_keep());
+        assert_eq!(quality_filter.num_rejected(), 1);
+    }
+
+    #[test]
+    fn test_outliers() {
+        let mut traces = Array2::from_shape_fn((50, 8), |(i, j)| ((i * 7 + j * 3) % 11) as i16);
+        // Glitched trace
+        traces.row_mut(30).fill(100);
+
+        let mut quality_filter =
+            QualityFilter::new()
+                .energy_outliers(5., 10)
+                .poi_outliers(vec![2, 5], 6., 10);
+        let verdicts: Vec<_> = traces
+            .rows()
+            .into_iter()
+            .map(|trace| quality_filter.check(trace))
+            .collect();
+
+        for (i, verdict) in verdicts.iter().enumerate() {
+            if i == 30 {
+                let Verdict::Reject(rejections) = verdict else {
+                    panic!("glitched trace not rejected")
+                };
+                assert_eq!(rejections.len(), 2);
+                assert!(matches!(rejections[0], Rejection::EnergyOutlier { .. }));
+                assert!(matches!(rejections[1], Rejection::PoiOutlier { .. }));
+            } else {
+                assert!(verdict.is_keep());
+            }
+        }
+        assert_eq!(quality_filter.num_kept(), 49);
+    }
+
+    #[test]
+    fn test_filter_quality() {
+        let traces = array![[1u8, 2, 3], [255, 2, 3], [4, 5, 6]];
+        let plaintexts = array![[0u8], [1], [2]];
+
+        let mut quality_filter = QualityFilter::new().saturation(0);
+        let kept: Vec<_> = traces
+            .rows()
+            .into_iter()
+            .zip(plaintexts.rows())
+            .filter_quality(&mut quality_filter)
+            .map(|(_, plaintext)| plaintext[0])
+            .collect();
+        assert_eq!(kept, vec![0, 2]);
+    }
+}
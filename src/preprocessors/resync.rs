@@ -0,0 +1,233 @@
+//! Resynchronization of traces on clock cycles.
+//!
+//! Power and EM traces usually exhibit one peak per clock cycle. Detecting these peaks and
+//! extracting one feature per cycle removes random delays and clock jitter, while compressing
+//! traces by a factor equal to the number of samples per cycle.
+
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, s};
+use serde::{Deserialize, Serialize};
+
+use super::{as_f32, batch_apply};
+use crate::Sample;
+
+/// Feature extracted from each clock cycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CycleFeature {
+    /// Value of the trace at the detected peak
+    PeakValue,
+    /// Sum of the samples of the cycle
+    Integral,
+    /// Maximum of the samples of the cycle
+    Max,
+}
+
+/// Return the indices of the peaks of the given signal, in increasing order.
+///
+/// Peaks are local maxima greater or equal to `threshold`. When two peaks are closer than
+/// `min_distance` samples, only the highest one is kept.
+pub fn find_peaks(signal: ArrayView1<f32>, threshold: f32, min_distance: usize) -> Vec<usize> {
+    let n = signal.len();
+
+    let mut candidates: Vec<usize> = (0..n)
+        .filter(|&i| {
+            signal[i] >= threshold
+                && (i == 0 || signal[i] > signal[i - 1])
+                && (i == n - 1 || signal[i] >= signal[i + 1])
+        })
+        .collect();
+
+    // Keep the highest peaks first, discarding their neighbors
+    candidates.sort_by(|&a, &b| signal[b].total_cmp(&signal[a]).then(a.cmp(&b)));
+    let mut removed = vec![false; n];
+    let mut peaks = Vec::new();
+    for i in candidates {
+        if removed[i] {
+            continue;
+        }
+        peaks.push(i);
+
+        let start = i.saturating_sub(min_distance.saturating_sub(1));
+        let end = (i + min_distance).min(n);
+        removed[start..end].iter_mut().for_each(|r| *r = true);
+    }
+
+    peaks.sort_unstable();
+    peaks
+}
+
+/// Resynchronize traces by detecting clock cycle peaks and extracting one feature per cycle.
+///
+/// Each cycle is centered on its peak and spans up to the midpoints with the neighboring peaks.
+/// The resulting trace is indexed by cycle, and has a fixed length of `num_cycles` so that it can
+/// be fed to processors such as [`crate::distinguishers::cpa::CpaProcessor`].
+///
+/// # Examples
+/// ```rust
+/// use muscat::preprocessors::resync::{CycleFeature, PeakResync};
+/// use ndarray::array;
+///
+/// let trace = array![0, 9, 1, 0, 0, 7, 2, 0, 8, 1, 0, 0];
+/// let resync = PeakResync::new(5., 2, CycleFeature::PeakValue, 4);
+///
+/// assert_eq!(resync.apply(trace.view()), array![9., 7., 8., 0.]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeakResync {
+    /// Minimum value of a peak
+    threshold: f32,
+    /// Minimum number of samples between two peaks
+    min_distance: usize,
+    /// Feature extracted from each cycle
+    feature: CycleFeature,
+    /// Number of cycles of the resynchronized traces
+    num_cycles: usize,
+}
+
+impl PeakResync {
+    /// Creates a new [`PeakResync`] preprocessor.
+    ///
+    /// # Arguments
+    ///
+    /// * `threshold` - Minimum value of a peak.
+    /// * `min_distance` - Minimum number of samples between two peaks, which should be slightly
+    ///   lower than the number of samples per clock cycle.
+    /// * `feature` - Feature extracted from each cycle.
+    /// * `num_cycles` - Number of cycles of the resynchronized traces.
+    pub fn new(
+        threshold: f32,
+        min_distance: usize,
+        feature: CycleFeature,
+        num_cycles: usize,
+    ) -> Self {
+        Self {
+            threshold,
+            min_distance,
+            feature,
+            num_cycles,
+        }
+    }
+
+    /// Returns the number of cycles of the resynchronized traces.
+    pub fn num_cycles(&self) -> usize {
+        self.num_cycles
+    }
+
+    /// Return the indices of the clock cycle peaks of the given trace (see [`find_peaks`]).
+    pub fn find_peaks<T>(&self, trace: ArrayView1<T>) -> Vec<usize>
+    where
+        T: Sample + Copy,
+    {
+        find_peaks(trace.mapv(as_f32).view(), self.threshold, self.min_distance)
+    }
+
+    /// Return the feature of every cycle detected in the given trace. The number of cycles
+    /// depends on the trace.
+    pub fn cycle_features<T>(&self, trace: ArrayView1<T>) -> Array1<f32>
+    where
+        T: Sample + Copy,
+    {
+        let trace = trace.mapv(as_f32);
+        let peaks = find_peaks(trace.view(), self.threshold, self.min_distance);
+
+        peaks
+            .iter()
+            .enumerate()
+            .map(|(i, &peak)| {
+                let start = if i == 0 {
+                    // Mirror the distance to the next peak, if any
+                    peaks
+                        .get(1)
+                        .map_or(0, |&next| peak.saturating_sub((next - peak) / 2))
+                } else {
+                    (peaks[i - 1] + peak).div_ceil(2)
+                };
+                let end = if i == peaks.len() - 1 {
+                    // Mirror the distance to the previous peak, if any
+                    let half_period = if i == 0 { 0 } else { (peak - peaks[i - 1]) / 2 };
+                    (peak + half_period + 1).min(trace.len())
+                } else {
+                    (peak + peaks[i + 1]).div_ceil(2)
+                };
+
+                let cycle = trace.slice(s![start..end]);
+                match self.feature {
+                    CycleFeature::PeakValue => trace[peak],
+                    CycleFeature::Integral => cycle.sum(),
+                    CycleFeature::Max => cycle.fold(f32::NEG_INFINITY, |acc, &x| acc.max(x)),
+                }
+            })
+            .collect()
+    }
+
+    /// Resynchronize the given trace. Only the first `num_cycles` cycles are kept, and missing
+    /// cycles are filled with zeros.
+    pub fn apply<T>(&self, trace: ArrayView1<T>) -> Array1<f32>
+    where
+        T: Sample + Copy,
+    {
+        let features = self.cycle_features(trace);
+
+        let mut resynchronized = Array1::zeros(self.num_cycles);
+        for (x, &feature) in resynchronized.iter_mut().zip(features.iter()) {
+            *x = feature;
+        }
+
+        resynchronized
+    }
+
+    /// Resynchronize each trace of the given batch in parallel (see [`PeakResync::apply`]).
+    pub fn batch_apply<T>(&self, traces: ArrayView2<T>) -> Array2<f32>
+    where
+        T: Sample + Copy + Sync,
+    {
+        batch_apply(traces, self.num_cycles, |trace| self.apply(trace))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CycleFeature, PeakResync, find_peaks};
+    use ndarray::{Array1, Array2, array};
+
+    #[test]
+    fn test_find_peaks() {
+        let signal = array![0., 5., 1., 6., 0., 0., 3., 0., 9., 8., 0.];
+        assert_eq!(find_peaks(signal.view(), 2., 1), vec![1, 3, 6, 8]);
+        assert_eq!(find_peaks(signal.view(), 4., 1), vec![1, 3, 8]);
+        // Peaks closer than 3 samples to a higher peak are discarded
+        assert_eq!(find_peaks(signal.view(), 2., 3), vec![3, 8]);
+    }
+
+    #[test]
+    fn test_peak_resync_jitter() {
+        // Same cycle values with different clock periods and initial delays
+        let cycles = [5i16, 8, 3, 7, 6];
+        let make_trace = |delay: usize, period: usize| {
+            let mut trace = Array1::zeros(delay + period * cycles.len() + 3);
+            for (i, &c) in cycles.iter().enumerate() {
+                trace[delay + i * period + period / 2] = 10 + c;
+                trace[delay + i * period + period / 2 + 1] = c;
+            }
+            trace
+        };
+
+        let resync = PeakResync::new(10., 4, CycleFeature::PeakValue, 6);
+        let expected = array![15., 18., 13., 17., 16., 0.];
+        assert_eq!(resync.apply(make_trace(0, 8).view()), expected);
+        assert_eq!(resync.apply(make_trace(13, 9).view()), expected);
+        assert_eq!(resync.apply(make_trace(5, 10).view()), expected);
+
+        let resync = PeakResync::new(10., 4, CycleFeature::Integral, 5);
+        assert_eq!(
+            resync.apply(make_trace(3, 8).view()),
+            array![20., 26., 16., 24., 22.]
+        );
+
+        let traces = Array2::from_shape_fn((2, 43), |(_, j)| make_trace(0, 8)[j]);
+        let resync = PeakResync::new(10., 4, CycleFeature::Max, 5);
+        assert_eq!(
+            resync.batch_apply(traces.view()).row(1),
+            array![15., 18., 13., 17., 16.]
+        );
+    }
+}
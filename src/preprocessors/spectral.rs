@@ -0,0 +1,276 @@
+//! Frequency domain transformations of traces.
+//!
+//! Transformed traces are [`Array1<f32>`], thus they can be fed to any processor to run attacks
+//! or leakage assessment in the frequency domain, which is more robust to misalignment than the
+//! time domain.
+
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, s};
+use realfft::{RealFftPlanner, RealToComplex};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use super::{as_f32, batch_apply, window::Window};
+use crate::Sample;
+
+/// Scale of the spectrum bins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpectrumScale {
+    /// Magnitude of the Fourier coefficients `|X_k|`
+    Magnitude,
+    /// Power of the Fourier coefficients `|X_k|²`
+    Power,
+}
+
+/// Computes the spectrum of traces using a real FFT.
+///
+/// The spectrum of a trace of length `n` has `n / 2 + 1` frequency bins, bin `k` corresponding to
+/// the frequency `k / n` (normalized to the sampling frequency).
+///
+/// # Examples
+/// ```rust
+/// use muscat::leakage_detection::SnrProcessor;
+/// use muscat::preprocessors::{
+///     spectral::{Spectrum, SpectrumScale},
+///     window::Window,
+/// };
+/// use ndarray::array;
+///
+/// let traces = array![
+///     [77u8, 137, 51, 91, 13, 64],
+///     [72, 61, 91, 83, 21, 2],
+///     [39, 49, 52, 23, 54, 190],
+///     [26, 114, 63, 45, 12, 32],
+/// ];
+/// let spectrum = Spectrum::new(traces.shape()[1], Window::Hann, SpectrumScale::Magnitude);
+///
+/// let mut snr = SnrProcessor::new(spectrum.num_bins(), 2);
+/// for (i, trace) in traces.rows().into_iter().enumerate() {
+///     snr.process(spectrum.apply(trace).view(), i % 2);
+/// }
+/// let snr = snr.snr();
+/// ```
+pub struct Spectrum {
+    fft: Arc<dyn RealToComplex<f32>>,
+    /// Window coefficients
+    window: Array1<f32>,
+    scale: SpectrumScale,
+}
+
+impl Spectrum {
+    /// Creates a new [`Spectrum`] preprocessor.
+    ///
+    /// # Arguments
+    ///
+    /// * `trace_length` - Number of samples per trace.
+    /// * `window` - Window applied to the traces before the FFT.
+    /// * `scale` - Scale of the spectrum bins.
+    pub fn new(trace_length: usize, window: Window, scale: SpectrumScale) -> Self {
+        Self {
+            fft: RealFftPlanner::new().plan_fft_forward(trace_length),
+            window: window.coefficients(trace_length),
+            scale,
+        }
+    }
+
+    /// Returns the trace length handled.
+    pub fn trace_length(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Returns the number of frequency bins of the spectrum.
+    pub fn num_bins(&self) -> usize {
+        self.trace_length() / 2 + 1
+    }
+
+    /// Compute the spectrum of the given trace.
+    ///
+    /// # Panics
+    /// Panic if `trace.shape()[0] != self.trace_length()`.
+    pub fn apply<T>(&self, trace: ArrayView1<T>) -> Array1<f32>
+    where
+        T: Sample + Copy,
+    {
+        assert_eq!(trace.shape()[0], self.trace_length());
+
+        spectrum(self.fft.as_ref(), self.window.view(), trace, self.scale)
+    }
+
+    /// Compute the spectrum of each trace of the given batch in parallel.
+    ///
+    /// # Panics
+    /// Panic if `traces.shape()[1] != self.trace_length()`.
+    pub fn batch_apply<T>(&self, traces: ArrayView2<T>) -> Array2<f32>
+    where
+        T: Sample + Copy + Sync,
+    {
+        batch_apply(traces, self.num_bins(), |trace| self.apply(trace))
+    }
+}
+
+/// Computes the spectrogram of traces using a short-time Fourier transform (STFT).
+///
+/// Traces are split in frames of `window_length` samples spaced by `hop` samples, and the spectrum
+/// of each frame is computed (see [`Spectrum`]). Trailing samples that do not fill a whole frame
+/// are ignored.
+pub struct Spectrogram {
+    fft: Arc<dyn RealToComplex<f32>>,
+    /// Window coefficients
+    window: Array1<f32>,
+    /// Number of samples between the start of two consecutive frames
+    hop: usize,
+    scale: SpectrumScale,
+}
+
+impl Spectrogram {
+    /// Creates a new [`Spectrogram`] preprocessor.
+    ///
+    /// # Arguments
+    ///
+    /// * `window_length` - Number of samples per frame.
+    /// * `hop` - Number of samples between the start of two consecutive frames.
+    /// * `window` - Window applied to each frame before the FFT.
+    /// * `scale` - Scale of the spectrum bins.
+    ///
+    /// # Panics
+    /// Panic if `window_length` or `hop` is 0.
+    pub fn new(window_length: usize, hop: usize, window: Window, scale: SpectrumScale) -> Self {
+        assert!(window_length > 0);
+        assert!(hop > 0);
+
+        Self {
+            fft: RealFftPlanner::new().plan_fft_forward(window_length),
+            window: window.coefficients(window_length),
+            hop,
+            scale,
+        }
+    }
+
+    /// Returns the number of samples per frame.
+    pub fn window_length(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Returns the number of frequency bins of each frame.
+    pub fn num_bins(&self) -> usize {
+        self.window_length() / 2 + 1
+    }
+
+    /// Returns the number of frames of the spectrogram of a trace of the given length.
+    pub fn num_frames(&self, trace_length: usize) -> usize {
+        if trace_length < self.window_length() {
+            0
+        } else {
+            (trace_length - self.window_length()) / self.hop + 1
+        }
+    }
+
+    /// Compute the spectrogram of the given trace as a `(num_frames, num_bins)` matrix.
+    ///
+    /// To feed the spectrogram to a processor, it can be flattened with
+    /// [`ndarray::ArrayBase::flatten`].
+    pub fn apply<T>(&self, trace: ArrayView1<T>) -> Array2<f32>
+    where
+        T: Sample + Copy,
+    {
+        let num_frames = self.num_frames(trace.shape()[0]);
+
+        let mut spectrogram = Array2::zeros((num_frames, self.num_bins()));
+        for (frame, mut row) in spectrogram.rows_mut().into_iter().enumerate() {
+            let start = frame * self.hop;
+            row.assign(&spectrum(
+                self.fft.as_ref(),
+                self.window.view(),
+                trace.slice(s![start..start + self.window_length()]),
+                self.scale,
+            ));
+        }
+
+        spectrogram
+    }
+}
+
+/// Compute the spectrum of `signal` multiplied by `window`.
+fn spectrum<T>(
+    fft: &dyn RealToComplex<f32>,
+    window: ArrayView1<f32>,
+    signal: ArrayView1<T>,
+    scale: SpectrumScale,
+) -> Array1<f32>
+where
+    T: Sample + Copy,
+{
+    let mut input = fft.make_input_vec();
+    for (x, (&sample, &w)) in input.iter_mut().zip(signal.iter().zip(window.iter())) {
+        *x = as_f32(sample) * w;
+    }
+
+    let mut output = fft.make_output_vec();
+    // Buffer lengths are the ones expected by the plan, thus this cannot fail
+    fft.process(&mut input, &mut output).unwrap();
+
+    output
+        .iter()
+        .map(|c| match scale {
+            SpectrumScale::Magnitude => c.norm(),
+            SpectrumScale::Power => c.norm_sqr(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Spectrogram, Spectrum, SpectrumScale};
+    use crate::preprocessors::window::Window;
+    use ndarray::{Array1, Array2, Axis};
+    use std::f32::consts::PI;
+
+    #[test]
+    fn test_spectrum() {
+        // Sinusoid of 8 periods over 64 samples with an offset of 3
+        let trace = Array1::from_shape_fn(64, |i| 3. + (2. * PI * 8. * i as f32 / 64.).cos());
+
+        let spectrum = Spectrum::new(64, Window::Rectangular, SpectrumScale::Magnitude);
+        assert_eq!(spectrum.num_bins(), 33);
+
+        let magnitude = spectrum.apply(trace.view());
+        assert_eq!(magnitude.len(), 33);
+        assert!((magnitude[0] - 3. * 64.).abs() < 1e-3);
+        assert!((magnitude[8] - 32.).abs() < 1e-3);
+        for (k, &x) in magnitude.iter().enumerate() {
+            if k != 0 && k != 8 {
+                assert!(x < 1e-3);
+            }
+        }
+
+        let power = Spectrum::new(64, Window::Rectangular, SpectrumScale::Power);
+        assert!((power.apply(trace.view())[8] - 32. * 32.).abs() < 1e-1);
+    }
+
+    #[test]
+    fn test_spectrum_batch_apply() {
+        let traces = Array2::from_shape_fn((5, 16), |(i, j)| ((i * 7 + j * 13) % 17) as i16);
+
+        let spectrum = Spectrum::new(16, Window::Hann, SpectrumScale::Power);
+        let spectra = spectrum.batch_apply(traces.view());
+        assert_eq!(spectra.shape(), &[5, 9]);
+        for (trace, row) in traces.rows().into_iter().zip(spectra.axis_iter(Axis(0))) {
+            assert_eq!(spectrum.apply(trace), row);
+        }
+    }
+
+    #[test]
+    fn test_spectrogram() {
+        let trace = Array1::from_shape_fn(100, |i| (2. * PI * i as f32 / 4.).sin());
+
+        let spectrogram = Spectrogram::new(16, 8, Window::Rectangular, SpectrumScale::Magnitude);
+        assert_eq!(spectrogram.num_frames(100), 11);
+        assert_eq!(spectrogram.num_frames(10), 0);
+
+        let frames = spectrogram.apply(trace.view());
+        assert_eq!(frames.shape(), &[11, 9]);
+        // A quarter of the sampling frequency falls in bin 4 of every frame
+        for frame in frames.rows() {
+            assert!((frame[4] - 8.).abs() < 1e-3);
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Static alignment of traces by cross-correlation against a reference pattern.
+
+use ndarray::{Array1, ArrayView1, s};
+use num_traits::Zero;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex, num_complex::Complex};
+use std::{ops::Range, sync::Arc};
+
+use super::as_f32;
+use crate::Sample;
+
+/// Position of the reference pattern found in a trace.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Alignment {
+    /// Number of samples the trace has to be shifted by to be aligned with the reference trace.
+    /// A positive shift moves the trace to the right.
+    pub shift: isize,
+    /// Normalized cross-correlation between the pattern and the trace at the best position, in
+    /// [-1, 1].
+    pub score: f32,
+}
+
+/// Align traces by shifting them so that a reference pattern is at the same position in every
+/// trace.
+///
+/// The pattern is located in each trace within a search window as the position maximizing the
+/// normalized cross-correlation with the pattern. The cross-correlation is computed with FFTs,
+/// making the cost independent of the pattern length.
+///
+/// This is a lot cheaper than [`super::ElasticAlignment`] when traces are only shifted, such as
+/// traces acquired with a jittery trigger.
+///
+/// # Examples
+/// ```rust
+/// use muscat::preprocessors::static_alignment::StaticAlignment;
+/// use ndarray::array;
+///
+/// let reference_trace = array![0, 0, 0, 5, 9, 2, 0, 0, 0, 0];
+/// let trace_to_align = array![0, 0, 0, 0, 0, 5, 9, 2, 0, 0];
+/// let static_alignment = StaticAlignment::new(reference_trace.view(), 2..7, 0..10, Some(0.9));
+///
+/// let (aligned_trace, alignment) = static_alignment.align(trace_to_align.view()).unwrap();
+/// assert_eq!(alignment.shift, -2);
+/// assert_eq!(aligned_trace, array![0, 0, 0, 5, 9, 2, 0, 0, 0, 0]);
+/// ```
+pub struct StaticAlignment {
+    /// Position of the pattern in the reference trace
+    pattern_offset: usize,
+    /// Length of the pattern
+    pattern_length: usize,
+    /// Euclidean norm of the centered pattern
+    pattern_norm: f32,
+    /// Conjugate of the FFT of the zero-padded centered pattern
+    pattern_fft: Vec<Complex<f32>>,
+    /// Range of samples of the traces in which the pattern is searched
+    search_window: Range<usize>,
+    /// Minimum score for a trace to be aligned
+    threshold: Option<f32>,
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+}
+
+impl StaticAlignment {
+    /// Creates a new [`StaticAlignment`].
+    ///
+    /// # Arguments
+    ///
+    /// * `reference_trace` - Trace to which other traces are aligned.
+    /// * `pattern` - Range of samples of the reference trace used as the pattern.
+    /// * `search_window` - Range of samples of the traces in which the pattern is searched.
+    /// * `threshold` - Minimum normalized cross-correlation for a trace to be aligned, traces
+    ///   below it are rejected by [`StaticAlignment::align`].
+    ///
+    /// # Panics
+    /// - Panic if `pattern` is empty or not within `reference_trace`.
+    /// - Panic if `search_window` is shorter than `pattern`.
+    pub fn new<T>(
+        reference_trace: ArrayView1<T>,
+        pattern: Range<usize>,
+        search_window: Range<usize>,
+        threshold: Option<f32>,
+    ) -> Self
+    where
+        T: Sample + Copy,
+    {
+        assert!(!pattern.is_empty());
+        assert!(pattern.end <= reference_trace.len());
+        assert!(search_window.len() >= pattern.len());
+
+        let pattern_length = pattern.len();
+        let pattern_offset = pattern.start;
+        let pattern = reference_trace.slice(s![pattern]).mapv(as_f32);
+        let pattern = &pattern - pattern.mean().unwrap();
+        let pattern_norm = pattern.dot(&pattern).sqrt();
+
+        // Linear (non-circular) cross-correlation of the search window with the pattern
+        let fft_length = search_window.len() + pattern_length - 1;
+        let mut planner = RealFftPlanner::new();
+        let fft = planner.plan_fft_forward(fft_length);
+        let ifft = planner.plan_fft_inverse(fft_length);
+
+        let mut input = fft.make_input_vec();
+        input[..pattern_length].copy_from_slice(pattern.as_slice().unwrap());
+        let mut pattern_fft = fft.make_output_vec();
+        // Buffer lengths are the ones expected by the plan, thus this cannot fail
+        fft.process(&mut input, &mut pattern_fft).unwrap();
+        pattern_fft.iter_mut().for_each(|c| *c = c.conj());
+
+        Self {
+            pattern_offset,
+            pattern_length,
+            pattern_norm,
+            pattern_fft,
+            search_window,
+            threshold,
+            fft,
+            ifft,
+        }
+    }
+
+    /// Locate the reference pattern in the given trace.
+    ///
+    /// # Panics
+    /// Panic if the search window is not within `trace`.
+    pub fn find<T>(&self, trace: ArrayView1<T>) -> Alignment
+    where
+        T: Sample + Copy,
+    {
+        assert!(self.search_window.end <= trace.len());
+
+        let window = trace.slice(s![self.search_window.clone()]).mapv(as_f32);
+
+        // Sliding dot products between the window and the pattern
+        let mut input = self.fft.make_input_vec();
+        input[..window.len()].copy_from_slice(window.as_slice().unwrap());
+        let mut spectrum = self.fft.make_output_vec();
+        self.fft.process(&mut input, &mut spectrum).unwrap();
+        for (x, y) in spectrum.iter_mut().zip(self.pattern_fft.iter()) {
+            *x *= y;
+        }
+        // The imaginary parts of the DC and Nyquist bins must be zero for the inverse real FFT
+        spectrum[0].im = 0.;
+        if input.len() % 2 == 0 {
+            spectrum.last_mut().unwrap().im = 0.;
+        }
+        let mut dot_products = self.ifft.make_output_vec();
+        self.ifft.process(&mut spectrum, &mut dot_products).unwrap();
+        let scale = input.len() as f32;
+
+        // Sliding sums and sums of squares of the window, accumulated in f64 for precision
+        let mut sums = vec![0f64; window.len() + 1];
+        let mut sums_squares = vec![0f64; window.len() + 1];
+        for (i, &x) in window.iter().enumerate() {
+            sums[i + 1] = sums[i] + x as f64;
+            sums_squares[i + 1] = sums_squares[i] + (x as f64) * (x as f64);
+        }
+
+        let m = self.pattern_length;
+        let mut best = Alignment {
+            shift: 0,
+            score: f32::NEG_INFINITY,
+        };
+        for p in 0..=window.len() - m {
+            let sum = sums[p + m] - sums[p];
+            let sum_squares = sums_squares[p + m] - sums_squares[p];
+            let norm = (sum_squares - sum * sum / m as f64).max(0.).sqrt() as f32;
+
+            let score = if norm == 0. || self.pattern_norm == 0. {
+                0.
+            } else {
+                dot_products[p] / scale / (norm * self.pattern_norm)
+            };
+
+            if score > best.score {
+                best = Alignment {
+                    shift: self.pattern_offset as isize - (self.search_window.start + p) as isize,
+                    score,
+                };
+            }
+        }
+
+        best
+    }
+
+    /// Align the given trace by shifting it so that the reference pattern is at the same position
+    /// as in the reference trace. Samples shifted in are set to zero.
+    ///
+    /// Return `None` if the best score is below the threshold.
+    ///
+    /// # Panics
+    /// Panic if the search window is not within `trace`.
+    pub fn align<T>(&self, trace: ArrayView1<T>) -> Option<(Array1<T>, Alignment)>
+    where
+        T: Sample + Copy + Zero,
+    {
+        let alignment = self.find(trace);
+        if self
+            .threshold
+            .is_some_and(|threshold| alignment.score < threshold)
+        {
+            return None;
+        }
+
+        Some((shift(trace, alignment.shift), alignment))
+    }
+}
+
+/// Shift the given trace by `shift` samples, filling with zeros.
+fn shift<T>(trace: ArrayView1<T>, shift: isize) -> Array1<T>
+where
+    T: Copy + Zero,
+{
+    let n = trace.len() as isize;
+    Array1::from_shape_fn(trace.len(), |i| {
+        let j = i as isize - shift;
+        if 0 <= j && j < n {
+            trace[j as usize]
+        } else {
+            T::zero()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StaticAlignment;
+    use ndarray::{Array1, s};
+
+    fn pseudo_random_trace(length: usize, seed: usize) -> Array1<i16> {
+        Array1::from_shape_fn(length, |i| ((i * 7919 + seed * 104729) % 201) as i16 - 100)
+    }
+
+    #[test]
+    fn test_static_alignment() {
+        let reference_trace = pseudo_random_trace(200, 0);
+        let static_alignment =
+            StaticAlignment::new(reference_trace.view(), 80..120, 40..160, Some(0.9));
+
+        for shift in [-30isize, -7, 0, 3, 25] {
+            // Trace delayed by -shift samples
+            let trace = Array1::from_shape_fn(200, |i| {
+                reference_trace[(i as isize + shift).rem_euclid(200) as usize]
+            });
+
+            let alignment = static_alignment.find(trace.view());
+            assert_eq!(alignment.shift, shift);
+            assert!((alignment.score - 1.).abs() < 1e-4);
+
+            let (aligned_trace, _) = static_alignment.align(trace.view()).unwrap();
+            assert_eq!(
+                aligned_trace.slice(s![40..160]),
+                reference_trace.slice(s![40..160])
+            );
+        }
+    }
+
+    #[test]
+    fn test_static_alignment_threshold() {
+        let reference_trace = pseudo_random_trace(200, 0);
+        let static_alignment =
+            StaticAlignment::new(reference_trace.view(), 80..120, 40..160, Some(0.9));
+
+        let trace = Array1::from_shape_fn(200, |i| ((i * i) % 13) as i16);
+        assert!(static_alignment.find(trace.view()).score < 0.9);
+        assert!(static_alignment.align(trace.view()).is_none());
+    }
+}
@@ -0,0 +1,66 @@
+//! Window functions used by spectral analysis and filter design.
+
+use ndarray::Array1;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+/// Window function[^1] applied to a signal before computing its spectrum or to the impulse
+/// response of a filter.
+///
+/// [^1]: <https://en.wikipedia.org/wiki/Window_function>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Window {
+    Rectangular,
+    Hann,
+    Hamming,
+    Blackman,
+}
+
+impl Window {
+    /// Return the symmetric window coefficients of the given length.
+    pub fn coefficients(&self, length: usize) -> Array1<f32> {
+        match length {
+            0 => return Array1::zeros(0),
+            1 => return Array1::ones(1),
+            _ => {}
+        }
+
+        let n = (length - 1) as f32;
+        Array1::from_shape_fn(length, |i| {
+            let x = 2. * PI * i as f32 / n;
+            match self {
+                Window::Rectangular => 1.,
+                Window::Hann => 0.5 - 0.5 * x.cos(),
+                Window::Hamming => 0.54 - 0.46 * x.cos(),
+                Window::Blackman => 0.42 - 0.5 * x.cos() + 0.08 * (2. * x).cos(),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Window;
+
+    #[test]
+    fn test_window_coefficients() {
+        for window in [
+            Window::Rectangular,
+            Window::Hann,
+            Window::Hamming,
+            Window::Blackman,
+        ] {
+            let coefficients = window.coefficients(9);
+            assert_eq!(coefficients.len(), 9);
+            // Symmetric with a unit peak in the middle
+            for i in 0..9 {
+                assert!((coefficients[i] - coefficients[8 - i]).abs() < 1e-6);
+            }
+            assert!((coefficients[4] - 1.).abs() < 1e-6);
+        }
+
+        assert!(Window::Hann.coefficients(9)[0].abs() < 1e-6);
+        assert_eq!(Window::Hann.coefficients(1).to_vec(), [1.]);
+        assert!(Window::Hann.coefficients(0).is_empty());
+    }
+}
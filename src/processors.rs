@@ -8,7 +8,7 @@ use std::iter::zip;
 use crate::Sample;
 
 /// Processes traces to calculate mean and variance.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct MeanVar<T>
 where
     T: Sample,
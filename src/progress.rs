@@ -0,0 +1,255 @@
+//! Progress reporting and cancellation of long computations.
+//!
+//! The `*_with_progress` variants of the parallel helpers (such as
+//! [`crate::distinguishers::cpa::cpa_with_progress`]) report the number of traces processed to a
+//! [`Progress`], and stop early once it is cancelled.
+//!
+//! With the `progress_bar` feature, [`indicatif::ProgressBar`] implements [`Progress`], so that
+//! [`crate::util::progress_bar`] can display the throughput and the remaining time of a
+//! computation.
+//!
+//! # Examples
+//! ```
+//! use muscat::leakage_detection::ttest_with_progress;
+//! use muscat::progress::ProgressCounter;
+//! use ndarray::array;
+//!
+//! let traces = array![[77, 137, 51, 91], [72, 61, 91, 83], [39, 49, 52, 23], [26, 114, 63, 45]];
+//! let trace_classes = array![true, false, false, true];
+//!
+//! let progress = ProgressCounter::new();
+//! let ttest = ttest_with_progress(traces.view(), trace_classes.view(), 2, &progress).unwrap();
+//! assert_eq!(progress.processed(), 4);
+//! ```
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+#[cfg(feature = "progress_bar")]
+use indicatif::ProgressBar;
+use rayon::iter::{ParallelBridge, ParallelIterator};
+
+use crate::Error;
+
+/// Receives the progress of a computation, and tells whether it should be cancelled.
+///
+/// Methods can be called concurrently from the worker threads of the computation.
+pub trait Progress: Sync {
+    /// Called before processing, with the total number of traces to process.
+    fn start(&self, _total: usize) {}
+
+    /// Called each time `count` more traces have been processed.
+    fn advance(&self, count: usize);
+
+    /// Returns `true` if the computation should stop. It is checked before processing each batch
+    /// of traces.
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+
+    /// Called once processing is over, whether it completed or was cancelled.
+    fn finish(&self) {}
+}
+
+/// A [`Progress`] that reports nothing and is never cancelled.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn advance(&self, _count: usize) {}
+}
+
+/// A [`Progress`] counting the traces processed, that can be read and cancelled from another
+/// thread.
+#[derive(Debug, Default)]
+pub struct ProgressCounter {
+    total: AtomicUsize,
+    processed: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl ProgressCounter {
+    /// Creates a new [`ProgressCounter`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the total number of traces to process.
+    pub fn total(&self) -> usize {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of traces processed so far.
+    pub fn processed(&self) -> usize {
+        self.processed.load(Ordering::Relaxed)
+    }
+
+    /// Cancel the computation.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Progress for ProgressCounter {
+    fn start(&self, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+    }
+
+    fn advance(&self, count: usize) {
+        self.processed.fetch_add(count, Ordering::Relaxed);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Resets the progress bar when a computation starts, and sets its length to the number of traces
+/// to process.
+#[cfg(feature = "progress_bar")]
+impl Progress for ProgressBar {
+    fn start(&self, total: usize) {
+        self.reset();
+        self.set_length(total as u64);
+    }
+
+    fn advance(&self, count: usize) {
+        self.inc(count as u64);
+    }
+
+    fn finish(&self) {
+        ProgressBar::finish(self);
+    }
+}
+
+/// Run `compute` without reporting progress.
+///
+/// [`NoProgress`] is never cancelled, thus `compute` cannot fail with [`Error::Cancelled`].
+pub(crate) fn without_progress<R>(compute: impl FnOnce(&NoProgress) -> Result<R, Error>) -> R {
+    compute(&NoProgress).expect("NoProgress is never cancelled")
+}
+
+/// Fold `batches` of `num_traces` traces in parallel into accumulators created by `identity` and
+/// combined with `combine`, reporting progress to `progress`.
+///
+/// `fold` processes a batch and returns its number of traces. Once `progress` is cancelled, the
+/// remaining batches are skipped and [`Error::Cancelled`] is returned.
+///
+/// # Panics
+/// Panic if `batches` is empty.
+pub(crate) fn par_fold_batches<B, A, P, I, F, C>(
+    batches: impl Iterator<Item = B> + Send,
+    num_traces: usize,
+    progress: &P,
+    identity: I,
+    fold: F,
+    combine: C,
+) -> Result<A, Error>
+where
+    B: Send,
+    A: Send,
+    P: Progress + ?Sized,
+    I: Fn() -> A + Send + Sync,
+    F: Fn(&mut A, B) -> usize + Send + Sync,
+    C: Fn(A, A) -> A + Send + Sync,
+{
+    let skipped = AtomicBool::new(false);
+
+    progress.start(num_traces);
+    // From benchmarks fold + reduce_with is faster than map + reduce/reduce_with and fold + reduce
+    let result = batches
+        .par_bridge()
+        .fold(identity, |mut acc, batch| {
+            if progress.is_cancelled() {
+                skipped.store(true, Ordering::Relaxed);
+            } else {
+                progress.advance(fold(&mut acc, batch));
+            }
+
+            acc
+        })
+        .reduce_with(combine)
+        .unwrap();
+    progress.finish();
+
+    if skipped.load(Ordering::Relaxed) {
+        return Err(Error::Cancelled);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NoProgress, Progress, ProgressCounter, par_fold_batches};
+    use crate::Error;
+
+    #[test]
+    fn test_par_fold_batches() {
+        let progress = ProgressCounter::new();
+        let sum = par_fold_batches(
+            (0..100)
+                .collect::<Vec<usize>>()
+                .chunks(7)
+                .map(<[usize]>::to_vec),
+            100,
+            &progress,
+            || 0,
+            |acc, batch| {
+                *acc += batch.iter().sum::<usize>();
+                batch.len()
+            },
+            |a, b| a + b,
+        )
+        .unwrap();
+
+        assert_eq!(sum, 4950);
+        assert_eq!(progress.total(), 100);
+        assert_eq!(progress.processed(), 100);
+
+        let sum = par_fold_batches(
+            0..10,
+            10,
+            &NoProgress,
+            || 0,
+            |acc, x| {
+                *acc += x;
+                1
+            },
+            |a, b| a + b,
+        );
+        assert_eq!(sum.unwrap(), 45);
+    }
+
+    #[test]
+    fn test_par_fold_batches_cancelled() {
+        /// Cancels the computation once `limit` traces have been processed.
+        struct Limit(ProgressCounter, usize);
+
+        impl Progress for Limit {
+            fn advance(&self, count: usize) {
+                self.0.advance(count);
+            }
+
+            fn is_cancelled(&self) -> bool {
+                self.0.processed() >= self.1
+            }
+        }
+
+        let progress = Limit(ProgressCounter::new(), 10);
+        let result = par_fold_batches(0..1000, 1000, &progress, || (), |_, _| 1, |_, _| ());
+
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert!(progress.0.processed() < 1000);
+    }
+
+    #[cfg(feature = "progress_bar")]
+    #[test]
+    fn test_progress_bar() {
+        let progress_bar = indicatif::ProgressBar::hidden();
+        par_fold_batches(0..10, 10, &progress_bar, || (), |_, _| 1, |_, _| ()).unwrap();
+
+        assert_eq!(progress_bar.length(), Some(10));
+        assert_eq!(progress_bar.position(), 10);
+        assert!(progress_bar.is_finished());
+    }
+}
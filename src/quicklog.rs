@@ -1,51 +1,77 @@
 //! Load and iterate over traces and data from [quicklog](https://github.com/Ledger-Donjon/quicklog) log files.
+//!
+//! Logs can also be produced with [`LogWriter`].
 
-use ndarray::Array1;
-use npyz::{Deserialize, NpyFile};
+use ndarray::{Array1, Array2, ArrayView1};
+use npyz::{AutoSerialize, Deserialize, NpyFile, WriteOptions, WriterBuilder};
+use serde_json::{Map, Value};
 use std::{
-    fs::File,
-    io::{self, BufRead, BufReader, Lines, Seek, SeekFrom},
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Lines, Read, Seek, SeekFrom, Write},
     marker::PhantomData,
-    path::Path,
+    panic,
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Condvar, Mutex,
+        mpsc::{self, Receiver, SyncSender},
+    },
+    thread::{self, JoinHandle},
 };
 use thiserror::Error;
 
-use crate::{trace::Trace, util::read_array1_from_npy_file};
+use crate::trace::{Column, Trace, TraceSet};
 
-/// Returns traces database directory from `TRACESDIR` environment variable, or `None` if it is not
-/// defined.
-fn get_traces_dir() -> Option<String> {
-    match std::env::var("TRACESDIR") {
-        Ok(dir) => Some(dir),
-        Err(_) => None,
+/// Returns traces database directory from `TRACESDIR` environment variable.
+pub fn traces_dir_from_env() -> Result<PathBuf, LogError> {
+    std::env::var_os("TRACESDIR")
+        .map(PathBuf::from)
+        .ok_or(LogError::MissingTracesDir)
+}
+
+/// Returns the given traces database directory, or the one of the `TRACESDIR` environment variable
+/// if `None`.
+fn resolve_traces_dir(traces_dir: Option<&Path>) -> Result<PathBuf, LogError> {
+    match traces_dir {
+        Some(traces_dir) => Ok(traces_dir.to_path_buf()),
+        None => traces_dir_from_env(),
     }
 }
 
-/// Returns the path of a trace or trace batch in the database from an id.
-pub fn path_from_string_id(id: &[u8]) -> String {
-    let traces_dir = get_traces_dir().expect("TRACESDIR is not defined");
-    Path::new(&traces_dir)
+/// Returns the path of a trace or trace batch in the database from an id, using the `TRACESDIR`
+/// environment variable as traces database directory.
+pub fn path_from_string_id(id: &[u8]) -> Result<PathBuf, LogError> {
+    Ok(path_in_traces_dir(&traces_dir_from_env()?, id))
+}
+
+/// Returns the path of a trace or trace batch in the given traces database directory from an id.
+///
+/// The first four bytes of the id are used as nested subdirectories.
+pub fn path_in_traces_dir(traces_dir: &Path, id: &[u8]) -> PathBuf {
+    traces_dir
         .join(format!("{:02x}", id[0]))
         .join(format!("{:02x}", id[1]))
         .join(format!("{:02x}", id[2]))
         .join(format!("{:02x}", id[3]))
         .join(hex::encode(&id[4..]) + ".npy")
-        .to_str()
-        .unwrap()
-        .to_string()
 }
 
-pub fn guess_leakages_size<T: Deserialize>(path: &str) -> usize {
-    let record = FileRecordIterator::<T>::new(path)
-        .expect("Failed to open log")
+/// Returns the number of samples of the first trace of the log at `path`.
+///
+/// Traces are loaded from `traces_dir`, or from the `TRACESDIR` environment variable if `None`.
+pub fn guess_leakages_size<T: Deserialize>(
+    path: &str,
+    traces_dir: Option<&Path>,
+) -> Result<usize, LogError> {
+    let record = FileRecordIterator::<T>::new(path)?
         .next()
-        .expect("Failed to fetch first log entry")
-        .expect("Failed to parse first log entry");
-    let leakage = record.load_trace().expect("Failed to load first log trace");
-    leakage.len()
+        .ok_or(LogError::NoRecords)??;
+    let traces_dir = resolve_traces_dir(traces_dir)?;
+    let leakage = record.load_trace_from(&traces_dir)?;
+    Ok(leakage.len())
 }
 
-/// Parsing records log can produce to types of errors, wrapped into this single error type.
+/// Errors raised when reading or writing quicklog logs and traces.
 #[derive(Error, Debug)]
 pub enum LogError {
     #[error("IO error")]
@@ -54,6 +80,62 @@ pub enum LogError {
     JsonError(#[from] serde_json::Error),
     #[error("No records")]
     NoRecords,
+    #[error("TRACESDIR is not defined")]
+    MissingTracesDir,
+    #[error("Trace file {0} not found")]
+    MissingTraceFile(PathBuf),
+    #[error("Invalid npy data in {path}: {reason}")]
+    InvalidNpy { path: PathBuf, reason: String },
+    #[error("Record has no {0} field")]
+    MissingField(String),
+    #[error("Invalid {0} field in record")]
+    InvalidField(String),
+    #[error("Inconsistent length of {0} across records")]
+    LengthMismatch(String),
+}
+
+/// Read the whole file at `path`, raising [`LogError::MissingTraceFile`] if it does not exist.
+fn read_trace_file(path: &Path) -> Result<Vec<u8>, LogError> {
+    fs::read(path).map_err(|err| match err.kind() {
+        io::ErrorKind::NotFound => LogError::MissingTraceFile(path.to_path_buf()),
+        _ => err.into(),
+    })
+}
+
+/// Read the 1D npy array at offset `toff` of `bytes`, read from the file at `path`.
+pub fn array_from_bytes<T: Deserialize>(
+    bytes: &[u8],
+    toff: usize,
+    path: &Path,
+) -> Result<Array1<T>, LogError> {
+    let invalid = |reason: String| LogError::InvalidNpy {
+        path: path.to_path_buf(),
+        reason,
+    };
+
+    let chunk = bytes
+        .get(toff..)
+        .ok_or_else(|| invalid(format!("offset {toff} is out of the file")))?;
+    array_from_reader(chunk, path)
+}
+
+/// Read a 1D `.npy` array from `reader`, reading only the bytes of the array.
+fn array_from_reader<T: Deserialize, R: Read>(
+    reader: R,
+    path: &Path,
+) -> Result<Array1<T>, LogError> {
+    let invalid = |reason: String| LogError::InvalidNpy {
+        path: path.to_path_buf(),
+        reason,
+    };
+
+    let npy = NpyFile::new(reader).map_err(|err| invalid(err.to_string()))?;
+    let data = npy.data::<T>().map_err(|err| invalid(err.to_string()))?;
+    let data = data
+        .collect::<io::Result<Vec<T>>>()
+        .map_err(|err| invalid(err.to_string()))?;
+
+    Ok(Array1::from_vec(data))
 }
 
 /// Opens a log file and allows iterating over the records.
@@ -67,17 +149,17 @@ pub struct Log<T> {
 
 impl<T: Deserialize> Log<T> {
     /// Opens a log file and parses all the entries. Gets the length of leakages
-    /// by reading the first trace.
+    /// by reading the first trace from the `TRACESDIR` traces database.
     pub fn new(path: &str) -> Result<Self, LogError> {
-        let mut records = Vec::new();
-        for entry in FileRecordIterator::new(path)? {
-            match entry {
-                Ok(record) => records.push(record),
-                Err(e) => return Err(e),
-            }
-        }
+        Self::with_traces_dir(path, &traces_dir_from_env()?)
+    }
+
+    /// Opens a log file and parses all the entries. Gets the length of leakages
+    /// by reading the first trace from the `traces_dir` traces database.
+    pub fn with_traces_dir(path: &str, traces_dir: &Path) -> Result<Self, LogError> {
+        let records = FileRecordIterator::new(path)?.collect::<Result<Vec<_>, _>>()?;
         if let Some(record) = records.first() {
-            let leakage_size = record.load_trace()?.len();
+            let leakage_size = record.load_trace_from(traces_dir)?.len();
             Ok(Self {
                 records,
                 leakage_size,
@@ -118,69 +200,139 @@ pub struct Record<T> {
     phantom: PhantomData<T>,
 }
 
-impl<T: Deserialize> Record<T> {
-    /// Returns a bytes identifier from the record data
+impl<T> Record<T> {
+    /// Returns a bytes identifier from the record data, or `None` if the record has no such field.
     ///
     /// # Arguments
     ///
     /// * `key` - String key, such as "bid" or "tid".
-    pub fn get_id(&self, key: &str) -> Option<Vec<u8>> {
+    pub fn get_id(&self, key: &str) -> Result<Option<Vec<u8>>, LogError> {
         self.data
             .get(key)
-            .map(|id| hex::decode(id.as_str().unwrap()).expect("Invalid id hex string"))
+            .map(|id| {
+                id.as_str()
+                    .and_then(|id| hex::decode(id).ok())
+                    .filter(|id| id.len() > 4)
+                    .ok_or_else(|| LogError::InvalidField(key.to_string()))
+            })
+            .transpose()
     }
 
     /// Returns the trace id, if defined.
-    pub fn tid(&self) -> Option<Vec<u8>> {
+    pub fn tid(&self) -> Result<Option<Vec<u8>>, LogError> {
         self.get_id("tid")
     }
 
     /// Returns the trace batch id, if defined.
-    pub fn bid(&self) -> Option<Vec<u8>> {
+    pub fn bid(&self) -> Result<Option<Vec<u8>>, LogError> {
         self.get_id("bid")
     }
 
     /// Returns the offset of the trace data in its batch file.
-    ///
-    /// Panics if the trace is not stored in a batch
-    pub fn toff(&self) -> u64 {
+    pub fn toff(&self) -> Result<u64, LogError> {
         self.data
             .get("toff")
-            .expect("Trace offset missing from record")
+            .ok_or_else(|| LogError::MissingField("toff".to_string()))?
             .as_u64()
-            .expect("Invalid trace offset format")
+            .ok_or_else(|| LogError::InvalidField("toff".to_string()))
     }
 
-    /// Returns bytes encoded in hex in a given field of the record. Panics if the data does not
-    /// exist, is not a string or is not hex encoded.
+    /// Returns the JSON value of a field of the record. Fields of nested objects are accessed by
+    /// joining the keys with dots, such as `"inputs.plaintext"`, unless the record has a field
+    /// with the exact key.
     ///
     /// # Arguments
     ///
     /// * `key` - JSON field key
-    pub fn bytes(&self, key: &str) -> Vec<u8> {
-        hex::decode(self.data[key].as_str().unwrap()).expect("Failed to parse bytes in record")
+    pub fn field(&self, key: &str) -> Result<&Value, LogError> {
+        if let Some(value) = self.data.get(key) {
+            return Ok(value);
+        }
+
+        key.split('.')
+            .try_fold(&self.data, |value, key| value.get(key))
+            .ok_or_else(|| LogError::MissingField(key.to_string()))
     }
 
-    pub fn load_trace(&self) -> Result<Array1<T>, LogError> {
-        if let Some(bid) = self.bid() {
+    /// Returns bytes encoded in hex in a given field of the record.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - JSON field key, see [`Record::field`]
+    pub fn bytes(&self, key: &str) -> Result<Vec<u8>, LogError> {
+        self.field(key)?
+            .as_str()
+            .and_then(|bytes| hex::decode(bytes).ok())
+            .ok_or_else(|| LogError::InvalidField(key.to_string()))
+    }
+
+    /// Returns exactly `N` bytes encoded in hex in a given field of the record.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - JSON field key, see [`Record::field`]
+    pub fn byte_array<const N: usize>(&self, key: &str) -> Result<[u8; N], LogError> {
+        self.bytes(key)?
+            .try_into()
+            .map_err(|_| LogError::InvalidField(key.to_string()))
+    }
+
+    /// Returns the integer in a given field of the record.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - JSON field key, see [`Record::field`]
+    pub fn integer(&self, key: &str) -> Result<i64, LogError> {
+        self.field(key)?
+            .as_i64()
+            .ok_or_else(|| LogError::InvalidField(key.to_string()))
+    }
+
+    /// Returns the boolean in a given field of the record.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - JSON field key, see [`Record::field`]
+    pub fn bool(&self, key: &str) -> Result<bool, LogError> {
+        self.field(key)?
+            .as_bool()
+            .ok_or_else(|| LogError::InvalidField(key.to_string()))
+    }
+
+    /// Returns the path of the file holding the trace in the given traces database, along with the
+    /// offset of the trace in this file.
+    fn trace_location(&self, traces_dir: &Path) -> Result<(PathBuf, u64), LogError> {
+        if let Some(bid) = self.bid()? {
             // Trace is stored in a batch
-            let toff = self.toff();
-            let path = path_from_string_id(&bid);
-            let mut f = File::open(path)?;
-            f.seek(SeekFrom::Start(toff)).unwrap();
-            let buf = BufReader::new(f);
-            let npy = NpyFile::new(buf).unwrap();
-            Ok(read_array1_from_npy_file(npy))
-        } else if let Some(_tid) = self.tid() {
+            Ok((path_in_traces_dir(traces_dir, &bid), self.toff()?))
+        } else if let Some(tid) = self.tid()? {
             // Trace is stored in a single file
-            todo!()
+            Ok((path_in_traces_dir(traces_dir, &tid), 0))
         } else {
-            // Cannot find a trace in this record
-            todo!()
+            Err(LogError::MissingField("bid or tid".to_string()))
         }
     }
 }
 
+impl<T: Deserialize> Record<T> {
+    /// Load the trace of the record from the `TRACESDIR` traces database.
+    pub fn load_trace(&self) -> Result<Array1<T>, LogError> {
+        self.load_trace_from(&traces_dir_from_env()?)
+    }
+
+    /// Load the trace of the record from the given traces database.
+    pub fn load_trace_from(&self, traces_dir: &Path) -> Result<Array1<T>, LogError> {
+        let (path, toff) = self.trace_location(traces_dir)?;
+        let mut file = File::open(&path).map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => LogError::MissingTraceFile(path.clone()),
+            _ => err.into(),
+        })?;
+        file.seek(SeekFrom::Start(toff))?;
+        // Batch files hold many traces, only the one at `toff` is read
+        array_from_reader(BufReader::new(file), &path)
+    }
+}
+
 pub struct FileRecordIterator<T> {
     lines: Lines<BufReader<File>>,
     phantom: PhantomData<T>,
@@ -221,36 +373,45 @@ impl<T> Iterator for FileRecordIterator<T> {
     }
 }
 
+/// Loads traces of records, keeping the last batch file read in memory.
 pub struct CachedLoader {
-    current_path: Option<String>,
+    /// Traces database directory, `TRACESDIR` if `None`
+    traces_dir: Option<PathBuf>,
+    current_path: Option<PathBuf>,
     current_data: Vec<u8>,
 }
 
 impl CachedLoader {
+    /// Creates a new [`CachedLoader`] loading traces from the `TRACESDIR` traces database.
     pub fn new() -> Self {
         Self {
+            traces_dir: None,
             current_path: None,
             current_data: Vec::new(),
         }
     }
 
+    /// Creates a new [`CachedLoader`] loading traces from the given traces database.
+    pub fn with_traces_dir<P: AsRef<Path>>(traces_dir: P) -> Self {
+        Self {
+            traces_dir: Some(traces_dir.as_ref().to_path_buf()),
+            ..Self::new()
+        }
+    }
+
     pub fn load_trace<T: Deserialize>(
         &mut self,
         record: &Record<T>,
     ) -> Result<Array1<T>, LogError> {
-        if let Some(bid) = record.bid() {
-            let path = path_from_string_id(&bid);
-            if self.current_path != Some(path.clone()) {
-                self.current_data = std::fs::read(&path).unwrap();
-                self.current_path = Some(path)
-            }
-            let toff = record.toff();
-            let chunk = &self.current_data.as_slice()[toff as usize..];
-            let npy = NpyFile::new(chunk).unwrap();
-            Ok(read_array1_from_npy_file(npy))
-        } else {
-            record.load_trace()
+        let traces_dir = resolve_traces_dir(self.traces_dir.as_deref())?;
+        let (path, toff) = record.trace_location(&traces_dir)?;
+        if self.current_path.as_ref() != Some(&path) {
+            // Forget the previous file first, so that it is not used if reading fails
+            self.current_path = None;
+            self.current_data = read_trace_file(&path)?;
+            self.current_path = Some(path.clone());
         }
+        array_from_bytes(&self.current_data, toff as usize, &path)
     }
 }
 
@@ -265,6 +426,7 @@ impl Default for CachedLoader {
 ///
 /// This can be created by [BatchIterator].
 pub struct Batch<T, U> {
+    path: PathBuf,
     file: Vec<u8>,
     toffs_and_values: Vec<(usize, U)>,
     phantom: PhantomData<T>,
@@ -273,6 +435,7 @@ pub struct Batch<T, U> {
 impl<T, U> Batch<T, U> {
     pub fn new() -> Self {
         Self {
+            path: PathBuf::new(),
             file: Vec::new(),
             toffs_and_values: Vec::new(),
             phantom: PhantomData,
@@ -287,12 +450,13 @@ impl<T, U> Default for Batch<T, U> {
 }
 
 impl<T: Deserialize, U> IntoIterator for Batch<T, U> {
-    type Item = Trace<T, U>;
+    type Item = Result<Trace<T, U>, LogError>;
 
     type IntoIter = BatchTraceIterator<T, U>;
 
     fn into_iter(self) -> Self::IntoIter {
         BatchTraceIterator {
+            path: self.path,
             bytes: self.file,
             iter: self.toffs_and_values.into_iter(),
             phantom: PhantomData,
@@ -311,6 +475,8 @@ where
 {
     /// A closure applied to records to select trace associated data
     f: F,
+    /// Traces database directory, `TRACESDIR` if `None`
+    traces_dir: Option<PathBuf>,
     /// First record of the next batch
     first: Option<Record<T>>,
     /// Internal state iterator
@@ -323,14 +489,53 @@ where
     I: Iterator<Item = Record<T>>,
     F: Fn(Record<T>) -> U,
 {
+    /// Creates a new [`BatchIterator`] loading batches from the `TRACESDIR` traces database.
     pub fn new(mut iter: I, f: F) -> Self {
         Self {
             f,
+            traces_dir: None,
             first: iter.next(),
             records: iter,
             phantom: PhantomData,
         }
     }
+
+    /// Load batches from the given traces database instead of `TRACESDIR`.
+    pub fn with_traces_dir<P: AsRef<Path>>(mut self, traces_dir: P) -> Self {
+        self.traces_dir = Some(traces_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Read the batch starting with the record `first`, consuming the following records of the
+    /// same batch.
+    fn read_batch(&mut self, first: Record<T>) -> Result<Batch<T, U>, LogError> {
+        let fid = first
+            .bid()?
+            .ok_or_else(|| LogError::MissingField("bid".to_string()))?;
+
+        // Records of the batch are consumed before reading it, so that the next batch is read
+        // even if this one fails
+        let mut records = vec![first];
+        for next in self.records.by_ref() {
+            if next.bid().ok().flatten().as_ref() != Some(&fid) {
+                self.first = Some(next);
+                break;
+            }
+            records.push(next);
+        }
+
+        let traces_dir = resolve_traces_dir(self.traces_dir.as_deref())?;
+        let path = path_in_traces_dir(&traces_dir, &fid);
+        Ok(Batch {
+            file: read_trace_file(&path)?,
+            path,
+            toffs_and_values: records
+                .into_iter()
+                .map(|record| Ok((record.toff()? as usize, (self.f)(record))))
+                .collect::<Result<_, LogError>>()?,
+            phantom: PhantomData,
+        })
+    }
 }
 
 impl<T, U, I, F> Iterator for BatchIterator<T, U, I, F>
@@ -339,34 +544,11 @@ where
     I: Iterator<Item = Record<T>>,
     F: Fn(Record<T>) -> U,
 {
-    type Item = Batch<T, U>;
+    type Item = Result<Batch<T, U>, LogError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if let Some(first) = self.first.take() {
-            let fid = first.bid().unwrap();
-            let path = path_from_string_id(&fid);
-            let mut batch = Batch {
-                file: std::fs::read(path).unwrap(),
-                toffs_and_values: vec![(first.toff() as usize, (self.f)(first))],
-                phantom: PhantomData,
-            };
-            loop {
-                if let Some(next) = self.records.next() {
-                    if next.bid().unwrap() != fid {
-                        self.first = Some(next);
-                        return Some(batch);
-                    } else {
-                        batch
-                            .toffs_and_values
-                            .push((next.toff() as usize, (self.f)(next)));
-                    }
-                } else {
-                    return Some(batch);
-                }
-            }
-        } else {
-            None
-        }
+        let first = self.first.take().or_else(|| self.records.next())?;
+        Some(self.read_batch(first))
     }
 }
 
@@ -389,23 +571,1052 @@ where
 }
 
 pub struct BatchTraceIterator<T: Deserialize, U> {
+    path: PathBuf,
     bytes: Vec<u8>,
     iter: std::vec::IntoIter<(usize, U)>,
     phantom: PhantomData<T>,
 }
 
 impl<T: Deserialize, U> Iterator for BatchTraceIterator<T, U> {
-    type Item = Trace<T, U>;
+    type Item = Result<Trace<T, U>, LogError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.iter
-            .next()
-            .map(|(toff, value)| Trace::new(array_from_bytes(&self.bytes, toff), value))
+        self.iter.next().map(|(toff, value)| {
+            Ok(Trace::new(
+                array_from_bytes(&self.bytes, toff, &self.path)?,
+                value,
+            ))
+        })
     }
 }
 
-pub fn array_from_bytes<T: Deserialize>(bytes: &[u8], toff: usize) -> Array1<T> {
-    let chunk = &bytes[toff..];
-    let npy = NpyFile::new(chunk).unwrap();
-    read_array1_from_npy_file(npy)
+/// Job sent to the batch reading threads of [`PrefetchedBatches`]: the index of the batch in the
+/// log, and the path of the batch file along with the offsets and values of its traces.
+type BatchJob<U> = (usize, Result<(PathBuf, Vec<(usize, U)>), LogError>);
+
+/// Batch read by the reading threads of [`PrefetchedBatches`], along with its index in the log.
+type ReadBatch<T, U> = (usize, Result<Batch<T, U>, LogError>);
+
+/// Limits the batches dispatched to the reading threads of [`PrefetchedBatches`] to the `prefetch`
+/// batches following the next one to yield, so that batches read out of order do not accumulate
+/// while waiting for an earlier one.
+struct PrefetchWindow {
+    /// Index of the first batch that cannot be dispatched yet, `None` once the consumer is dropped
+    end: Mutex<Option<usize>>,
+    advanced: Condvar,
+}
+
+impl PrefetchWindow {
+    fn new(prefetch: usize) -> Self {
+        Self {
+            end: Mutex::new(Some(prefetch)),
+            advanced: Condvar::new(),
+        }
+    }
+
+    /// Wait until the batch at `index` can be dispatched. Returns `false` if the consumer has been
+    /// dropped.
+    fn wait(&self, index: usize) -> bool {
+        let mut end = self.end.lock().unwrap();
+        loop {
+            match *end {
+                None => return false,
+                Some(end) if index < end => return true,
+                Some(_) => end = self.advanced.wait(end).unwrap(),
+            }
+        }
+    }
+
+    /// Allow one more batch to be dispatched, after a batch has been yielded.
+    fn advance(&self) {
+        if let Some(end) = self.end.lock().unwrap().as_mut() {
+            *end += 1;
+        }
+        self.advanced.notify_all();
+    }
+
+    /// Stop dispatching batches.
+    fn close(&self) {
+        *self.end.lock().unwrap() = None;
+        self.advanced.notify_all();
+    }
+}
+
+/// Reads the batches of a log in background threads.
+///
+/// Records are parsed lazily by a thread grouping them by batch, while batch files are read by a
+/// pool of threads. At most `prefetch` batches are read ahead of the consumer, which bounds memory
+/// usage. Batches are yielded in the order of the log, and can be handed to a rayon pipeline with
+/// [`rayon::iter::ParallelBridge::par_bridge`].
+///
+/// # Examples
+/// ```rust,no_run
+/// use muscat::distinguishers::cpa::CpaProcessor;
+/// use muscat::leakage_model::aes::sbox;
+/// use muscat::quicklog::{BatchPrefetcher, LogError};
+/// use rayon::iter::{ParallelBridge, ParallelIterator};
+///
+/// let leakage_model = |plaintext: usize, guess: usize| sbox((plaintext ^ guess) as u8) as usize;
+/// let batches = BatchPrefetcher::new()
+///     .traces_dir("traces")
+///     .num_threads(4)
+///     .batches::<u8, _, _>("log.jsonl", |record| record.bytes("plaintext").unwrap()[0])
+///     .unwrap();
+/// let cpa = batches
+///     .par_bridge()
+///     .map(|batch| {
+///         let mut cpa = CpaProcessor::new(5000, 256);
+///         for trace in batch? {
+///             let trace = trace?;
+///             cpa.update(trace.leakage.view(), trace.value, leakage_model);
+///         }
+///         Ok::<_, LogError>(cpa)
+///     })
+///     .try_reduce(|| CpaProcessor::new(5000, 256), |a, b| Ok(a.combine(b)))
+///     .unwrap()
+///     .finalize(leakage_model);
+/// ```
+#[derive(Debug, Clone)]
+pub struct BatchPrefetcher {
+    /// Traces database directory, `TRACESDIR` if `None`
+    traces_dir: Option<PathBuf>,
+    num_threads: usize,
+    prefetch: usize,
+}
+
+impl BatchPrefetcher {
+    /// Creates a new [`BatchPrefetcher`] loading batches from the `TRACESDIR` traces database,
+    /// with 4 reading threads and 8 batches read ahead.
+    pub fn new() -> Self {
+        Self {
+            traces_dir: None,
+            num_threads: 4,
+            prefetch: 8,
+        }
+    }
+
+    /// Load batches from the given traces database instead of `TRACESDIR`.
+    pub fn traces_dir<P: AsRef<Path>>(mut self, traces_dir: P) -> Self {
+        self.traces_dir = Some(traces_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Set the number of threads reading batch files.
+    ///
+    /// # Panics
+    /// Panic if `num_threads` is 0.
+    pub fn num_threads(mut self, num_threads: usize) -> Self {
+        assert!(num_threads > 0);
+        self.num_threads = num_threads;
+        self
+    }
+
+    /// Set the maximum number of batches read ahead of the consumer.
+    ///
+    /// # Panics
+    /// Panic if `prefetch` is 0.
+    pub fn prefetch(mut self, prefetch: usize) -> Self {
+        assert!(prefetch > 0);
+        self.prefetch = prefetch;
+        self
+    }
+
+    /// Start reading the batches of the log at `path` in background threads. `f` is applied to
+    /// records to select the data associated to each trace.
+    pub fn batches<T, U, F>(&self, path: &str, f: F) -> Result<PrefetchedBatches<T, U>, LogError>
+    where
+        T: Deserialize + Send + 'static,
+        U: Send + 'static,
+        F: Fn(Record<T>) -> U + Send + 'static,
+    {
+        let records = FileRecordIterator::<T>::new(path)?;
+        let traces_dir = resolve_traces_dir(self.traces_dir.as_deref())?;
+
+        let (jobs_sender, jobs) = mpsc::sync_channel::<BatchJob<U>>(self.prefetch);
+        let (results_sender, results) = mpsc::sync_channel::<ReadBatch<T, U>>(self.prefetch);
+
+        let window = Arc::new(PrefetchWindow::new(self.prefetch));
+        let mut threads = vec![thread::spawn({
+            let window = Arc::clone(&window);
+            move || group_batches(records, &traces_dir, f, &window, jobs_sender)
+        })];
+        let jobs = Arc::new(Mutex::new(jobs));
+        for _ in 0..self.num_threads {
+            let jobs = Arc::clone(&jobs);
+            let results_sender = results_sender.clone();
+            threads.push(thread::spawn(move || read_batches(&jobs, results_sender)));
+        }
+
+        Ok(PrefetchedBatches {
+            results: Some(results),
+            pending: BTreeMap::new(),
+            next_index: 0,
+            window,
+            threads,
+        })
+    }
+}
+
+impl Default for BatchPrefetcher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Group consecutive records of the same batch and send them as jobs once they fit in `window`,
+/// until all the records have been read or the consumer or the reading threads have stopped.
+fn group_batches<T, U, F>(
+    records: FileRecordIterator<T>,
+    traces_dir: &Path,
+    f: F,
+    window: &PrefetchWindow,
+    jobs: SyncSender<BatchJob<U>>,
+) where
+    F: Fn(Record<T>) -> U,
+{
+    let mut index = 0;
+    let mut send = |job| {
+        let sent = window.wait(index) && jobs.send((index, job)).is_ok();
+        index += 1;
+        sent
+    };
+
+    // Id and traces of the batch being grouped
+    let mut current: Option<(Vec<u8>, Vec<_>)> = None;
+    for record in records {
+        let located = record.and_then(|record| {
+            let bid = record
+                .bid()?
+                .ok_or_else(|| LogError::MissingField("bid".to_string()))?;
+            let toff = record.toff()? as usize;
+            Ok((bid, toff, record))
+        });
+
+        match located {
+            Ok((bid, toff, record)) => match current.as_mut() {
+                Some((current_bid, traces)) if *current_bid == bid => {
+                    traces.push((toff, f(record)));
+                }
+                _ => {
+                    let previous = current.replace((bid, vec![(toff, f(record))]));
+                    if let Some((bid, traces)) = previous
+                        && !send(Ok((path_in_traces_dir(traces_dir, &bid), traces)))
+                    {
+                        return;
+                    }
+                }
+            },
+            Err(err) => {
+                if let Some((bid, traces)) = current.take()
+                    && !send(Ok((path_in_traces_dir(traces_dir, &bid), traces)))
+                {
+                    return;
+                }
+                if !send(Err(err)) {
+                    return;
+                }
+            }
+        }
+    }
+
+    if let Some((bid, traces)) = current {
+        send(Ok((path_in_traces_dir(traces_dir, &bid), traces)));
+    }
+}
+
+/// Read the batch files of received jobs, until all the jobs have been processed or the consumer
+/// has been dropped.
+fn read_batches<T, U>(jobs: &Mutex<Receiver<BatchJob<U>>>, results: SyncSender<ReadBatch<T, U>>) {
+    loop {
+        // The lock is released before reading the file, so that other threads can receive jobs
+        let job = jobs.lock().unwrap().recv();
+        let Ok((index, job)) = job else {
+            return;
+        };
+
+        let batch = job.and_then(|(path, toffs_and_values)| {
+            Ok(Batch {
+                file: read_trace_file(&path)?,
+                path,
+                toffs_and_values,
+                phantom: PhantomData,
+            })
+        });
+        if results.send((index, batch)).is_err() {
+            return;
+        }
+    }
+}
+
+/// Iterator over the batches of a log read in background threads, created by
+/// [`BatchPrefetcher::batches`].
+///
+/// Dropping the iterator stops the background threads.
+pub struct PrefetchedBatches<T, U> {
+    /// Batches read by the background threads, `None` once they have all stopped
+    results: Option<Receiver<ReadBatch<T, U>>>,
+    /// Batches received ahead of the next batch of the log
+    pending: BTreeMap<usize, Result<Batch<T, U>, LogError>>,
+    /// Index of the next batch to yield
+    next_index: usize,
+    /// Bounds `pending` by the number of batches read ahead
+    window: Arc<PrefetchWindow>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl<T, U> Iterator for PrefetchedBatches<T, U> {
+    type Item = Result<Batch<T, U>, LogError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(batch) = self.pending.remove(&self.next_index) {
+                self.next_index += 1;
+                self.window.advance();
+                return Some(batch);
+            }
+
+            match self.results.as_ref()?.recv() {
+                Ok((index, batch)) => {
+                    self.pending.insert(index, batch);
+                }
+                Err(_) => {
+                    // All the threads have stopped, propagate their panics if any
+                    self.results = None;
+                    for thread in self.threads.drain(..) {
+                        if let Err(panic) = thread.join() {
+                            panic::resume_unwind(panic);
+                        }
+                    }
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+impl<T, U> Drop for PrefetchedBatches<T, U> {
+    fn drop(&mut self) {
+        // Threads stop as soon as they fail to send their next job or batch
+        self.window.close();
+        self.results = None;
+        self.pending.clear();
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Length of the trace and batch ids generated by [`LogWriter`].
+const ID_LENGTH: usize = 16;
+
+/// Source of a column of [`TraceSetLoader`] in the records.
+enum ColumnSource {
+    Bytes,
+    Bool,
+    Integer,
+    /// Whether the bytes of the field equal the given value
+    Equals(Vec<u8>),
+}
+
+/// Values of a column of [`TraceSetLoader`], accumulated while reading the records.
+enum ColumnValues {
+    /// Concatenated byte strings, along with their length
+    Bytes(Vec<u8>, Option<usize>),
+    Bool(Vec<bool>),
+    Integer(Vec<i64>),
+}
+
+/// A predicate selecting the records loaded by [`TraceSetLoader`].
+type RecordFilter<T> = Box<dyn Fn(&Record<T>) -> bool>;
+
+/// Loads the traces of a log into a [`TraceSet`], mapping record fields to its columns.
+///
+/// # Examples
+/// ```rust,no_run
+/// use muscat::quicklog::TraceSetLoader;
+/// use muscat::trace::names;
+///
+/// let traces = TraceSetLoader::<u8>::new()
+///     .traces_dir("traces")
+///     .bytes_column(names::PLAINTEXT, "plaintext")
+///     .bytes_column(names::KEY, "key")
+///     .fixed_column(names::FIXED, "plaintext", &[0; 16])
+///     .filter(|record| record.integer("campaign").is_ok_and(|campaign| campaign == 2))
+///     .load("log.jsonl")
+///     .unwrap();
+/// ```
+pub struct TraceSetLoader<T> {
+    /// Traces database directory, `TRACESDIR` if `None`
+    traces_dir: Option<PathBuf>,
+    /// Name, record field key and source of each column
+    columns: Vec<(String, String, ColumnSource)>,
+    filters: Vec<RecordFilter<T>>,
+}
+
+impl<T: Deserialize> TraceSetLoader<T> {
+    /// Creates a new [`TraceSetLoader`] loading traces from the `TRACESDIR` traces database, without
+    /// any column.
+    pub fn new() -> Self {
+        Self {
+            traces_dir: None,
+            columns: Vec::new(),
+            filters: Vec::new(),
+        }
+    }
+
+    /// Load traces from the given traces database instead of `TRACESDIR`.
+    pub fn traces_dir<P: AsRef<Path>>(mut self, traces_dir: P) -> Self {
+        self.traces_dir = Some(traces_dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Add a [`Column::Bytes`] column from the hex-encoded bytes of the `key` field (see
+    /// [`Record::bytes`]).
+    pub fn bytes_column(mut self, name: &str, key: &str) -> Self {
+        self.columns
+            .push((name.to_string(), key.to_string(), ColumnSource::Bytes));
+        self
+    }
+
+    /// Add a [`Column::Bool`] column from the boolean `key` field (see [`Record::bool`]).
+    pub fn bool_column(mut self, name: &str, key: &str) -> Self {
+        self.columns
+            .push((name.to_string(), key.to_string(), ColumnSource::Bool));
+        self
+    }
+
+    /// Add a [`Column::Integer`] column from the integer `key` field (see [`Record::integer`]).
+    pub fn integer_column(mut self, name: &str, key: &str) -> Self {
+        self.columns
+            .push((name.to_string(), key.to_string(), ColumnSource::Integer));
+        self
+    }
+
+    /// Add a [`Column::Bool`] column telling whether the bytes of the `key` field equal `fixed`,
+    /// such as the fixed input of a fixed vs. random acquisition.
+    pub fn fixed_column(mut self, name: &str, key: &str, fixed: &[u8]) -> Self {
+        self.columns.push((
+            name.to_string(),
+            key.to_string(),
+            ColumnSource::Equals(fixed.to_vec()),
+        ));
+        self
+    }
+
+    /// Only load the records matching `predicate`. Several filters can be added, in which case
+    /// records must match all of them.
+    pub fn filter<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Record<T>) -> bool + 'static,
+    {
+        self.filters.push(Box::new(predicate));
+        self
+    }
+
+    /// Load the traces and columns of the records of the log at `path`.
+    pub fn load(&self, path: &str) -> Result<TraceSet<T>, LogError> {
+        let mut loader = CachedLoader {
+            traces_dir: Some(resolve_traces_dir(self.traces_dir.as_deref())?),
+            ..CachedLoader::new()
+        };
+        let mut num_traces = 0;
+        let mut samples = Vec::new();
+        let mut trace_length = None;
+        let mut values: Vec<ColumnValues> = self
+            .columns
+            .iter()
+            .map(|(_, _, source)| match source {
+                ColumnSource::Bytes => ColumnValues::Bytes(Vec::new(), None),
+                ColumnSource::Bool | ColumnSource::Equals(_) => ColumnValues::Bool(Vec::new()),
+                ColumnSource::Integer => ColumnValues::Integer(Vec::new()),
+            })
+            .collect();
+
+        for record in FileRecordIterator::<T>::new(path)? {
+            let record = record?;
+            if !self.filters.iter().all(|filter| filter(&record)) {
+                continue;
+            }
+
+            let trace = loader.load_trace(&record)?;
+            if *trace_length.get_or_insert(trace.len()) != trace.len() {
+                return Err(LogError::LengthMismatch("trace".to_string()));
+            }
+            samples.extend(trace);
+            num_traces += 1;
+
+            for ((_, key, source), values) in self.columns.iter().zip(values.iter_mut()) {
+                match (source, values) {
+                    (ColumnSource::Bytes, ColumnValues::Bytes(values, length)) => {
+                        let bytes = record.bytes(key)?;
+                        if *length.get_or_insert(bytes.len()) != bytes.len() {
+                            return Err(LogError::LengthMismatch(key.clone()));
+                        }
+                        values.extend(bytes);
+                    }
+                    (ColumnSource::Bool, ColumnValues::Bool(values)) => {
+                        values.push(record.bool(key)?);
+                    }
+                    (ColumnSource::Equals(fixed), ColumnValues::Bool(values)) => {
+                        values.push(record.bytes(key)? == *fixed);
+                    }
+                    (ColumnSource::Integer, ColumnValues::Integer(values)) => {
+                        values.push(record.integer(key)?);
+                    }
+                    _ => unreachable!(),
+                }
+            }
+        }
+
+        // All the traces have the same length, thus this cannot fail
+        let trace_length = trace_length.unwrap_or(0);
+        let traces = Array2::from_shape_vec((num_traces, trace_length), samples).unwrap();
+
+        let mut trace_set = TraceSet::new(traces);
+        for ((name, _, _), values) in self.columns.iter().zip(values) {
+            let column = match values {
+                ColumnValues::Bytes(values, length) => Column::Bytes(
+                    Array2::from_shape_vec((num_traces, length.unwrap_or(0)), values).unwrap(),
+                ),
+                ColumnValues::Bool(values) => Column::Bool(Array1::from_vec(values)),
+                ColumnValues::Integer(values) => Column::Integer(Array1::from_vec(values)),
+            };
+            trace_set.insert_column(name.as_str(), column);
+        }
+
+        Ok(trace_set)
+    }
+}
+
+impl<T: Deserialize> Default for TraceSetLoader<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Data of a record to be written by [`LogWriter`].
+///
+/// # Examples
+/// ```rust
+/// use muscat::quicklog::RecordData;
+///
+/// let data = RecordData::new()
+///     .bytes("plaintext", &[0x00, 0x11, 0x22])
+///     .field("index", 7);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RecordData {
+    fields: Map<String, Value>,
+}
+
+impl RecordData {
+    /// Creates a new [`RecordData`] without any field.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a field holding bytes, encoded in hex as read by [`Record::bytes`].
+    pub fn bytes(self, key: &str, bytes: &[u8]) -> Self {
+        self.field(key, hex::encode(bytes))
+    }
+
+    /// Set a field holding any JSON value.
+    pub fn field<V: Into<Value>>(mut self, key: &str, value: V) -> Self {
+        self.fields.insert(key.to_string(), value.into());
+        self
+    }
+}
+
+impl From<Map<String, Value>> for RecordData {
+    fn from(fields: Map<String, Value>) -> Self {
+        Self { fields }
+    }
+}
+
+/// Writes quicklog logs, storing traces in batch files of a traces database.
+///
+/// Each trace is stored as a npy array in a batch file, whose path in the database is derived from
+/// its id (see [`path_in_traces_dir`]). Records hold the trace id (`tid`), the batch id (`bid`) and
+/// the offset of the trace in the batch file (`toff`), along with the data given to
+/// [`LogWriter::write`].
+///
+/// Records are buffered until the batch is full or [`LogWriter::flush`] is called. The batch file is
+/// then written to a temporary file and renamed, before the records are appended to the log, so
+/// that the log never refers to a missing or partially written batch. Pending records are flushed
+/// when the writer is dropped, ignoring errors.
+///
+/// # Examples
+/// ```rust,no_run
+/// use muscat::quicklog::{LogWriter, RecordData};
+/// use ndarray::array;
+///
+/// let mut writer = LogWriter::<u8>::create("log.jsonl", "traces", 1000).unwrap();
+/// writer
+///     .write(
+///         array![1, 2, 3].view(),
+///         RecordData::new().bytes("plaintext", &[0x00, 0x11]),
+///     )
+///     .unwrap();
+/// writer.flush().unwrap();
+/// ```
+pub struct LogWriter<T> {
+    log: File,
+    traces_dir: PathBuf,
+    batch_size: usize,
+    /// Id of the batch being built
+    batch_id: [u8; ID_LENGTH],
+    /// Content of the batch file being built
+    batch: Vec<u8>,
+    /// Records of the traces of the batch being built
+    records: Vec<Map<String, Value>>,
+    phantom: PhantomData<T>,
+}
+
+impl<T: AutoSerialize + Copy> LogWriter<T> {
+    /// Opens the log file at `path` for appending, creating it if needed, and stores traces in the
+    /// traces database directory `traces_dir` by batches of `batch_size` traces.
+    ///
+    /// Readers must be given `traces_dir` as traces database directory, either explicitly or with
+    /// the `TRACESDIR` environment variable.
+    ///
+    /// # Panics
+    /// Panic if `batch_size` is 0.
+    pub fn create<P: AsRef<Path>, Q: AsRef<Path>>(
+        path: P,
+        traces_dir: Q,
+        batch_size: usize,
+    ) -> Result<Self, LogError> {
+        assert!(batch_size > 0);
+
+        let log = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            log,
+            traces_dir: traces_dir.as_ref().to_path_buf(),
+            batch_size,
+            batch_id: rand::random(),
+            batch: Vec::new(),
+            records: Vec::new(),
+            phantom: PhantomData,
+        })
+    }
+
+    /// Add a trace and its record to the log.
+    pub fn write(&mut self, trace: ArrayView1<T>, data: RecordData) -> Result<(), LogError> {
+        let toff = self.batch.len();
+        let mut writer = WriteOptions::new()
+            .default_dtype()
+            .shape(&[trace.len() as u64])
+            .writer(&mut self.batch)
+            .begin_nd()?;
+        writer.extend(trace.iter().copied())?;
+        writer.finish()?;
+
+        let mut record = data.fields;
+        let tid: [u8; ID_LENGTH] = rand::random();
+        record.insert("tid".to_string(), hex::encode(tid).into());
+        record.insert("bid".to_string(), hex::encode(self.batch_id).into());
+        record.insert("toff".to_string(), toff.into());
+        self.records.push(record);
+
+        if self.records.len() >= self.batch_size {
+            self.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of records not yet written to the log.
+    pub fn num_pending(&self) -> usize {
+        self.records.len()
+    }
+}
+
+impl<T> LogWriter<T> {
+    /// Write the current batch file and append its records to the log. The next traces are stored
+    /// in a new batch.
+    ///
+    /// If appending the records fails, the log is truncated back to its previous length and the
+    /// records are kept to be written by the next flush. If the log cannot be truncated, the
+    /// records are discarded so that they are not written twice.
+    pub fn flush(&mut self) -> Result<(), LogError> {
+        if self.records.is_empty() {
+            return Ok(());
+        }
+
+        let path = path_in_traces_dir(&self.traces_dir, &self.batch_id);
+        // The parent is within the traces directory, thus this cannot fail
+        fs::create_dir_all(path.parent().unwrap())?;
+        let tmp_path = path.with_extension("npy.tmp");
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(&self.batch)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+
+        let mut lines = String::new();
+        for record in self.records.iter() {
+            lines += &serde_json::to_string(record)?;
+            lines.push('\n');
+        }
+        let log_length = self.log.metadata()?.len();
+        let written = self
+            .log
+            .write_all(lines.as_bytes())
+            .and_then(|()| self.log.sync_data());
+        if let Err(err) = written {
+            if self.log.set_len(log_length).is_err() {
+                self.new_batch();
+            }
+            return Err(err.into());
+        }
+
+        self.new_batch();
+
+        Ok(())
+    }
+
+    /// Discard the current batch and start a new one.
+    fn new_batch(&mut self) {
+        self.batch_id = rand::random();
+        self.batch.clear();
+        self.records.clear();
+    }
+}
+
+impl<T> Drop for LogWriter<T> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        BatchIter, BatchPrefetcher, CachedLoader, FileRecordIterator, LogError, LogWriter, Record,
+        RecordData, TraceSetLoader, array_from_reader, path_in_traces_dir,
+    };
+    use crate::trace::{Column, names};
+    use ndarray::array;
+    use serde_json::json;
+    use std::{fs, io::Cursor, path::Path};
+
+    fn read_records(path: &Path) -> Vec<Record<i16>> {
+        FileRecordIterator::new(path.to_str().unwrap())
+            .unwrap()
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    #[test]
+    fn test_log_writer() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("log.jsonl");
+        let traces_dir = dir.path().join("traces");
+
+        let traces = [array![1i16, -2, 3], array![4, 5, -6], array![7, 8, 9]];
+        let mut writer = LogWriter::<i16>::create(&log_path, &traces_dir, 2).unwrap();
+        for (i, trace) in traces.iter().enumerate() {
+            writer
+                .write(
+                    trace.view(),
+                    RecordData::new()
+                        .bytes("plaintext", &[i as u8, 0xff])
+                        .field("index", i),
+                )
+                .unwrap();
+        }
+        // The first batch is full and has been written
+        assert_eq!(writer.num_pending(), 1);
+        drop(writer);
+
+        let records = read_records(&log_path);
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].bid().unwrap(), records[1].bid().unwrap());
+        assert_ne!(records[1].bid().unwrap(), records[2].bid().unwrap());
+        assert_ne!(records[0].tid().unwrap(), records[1].tid().unwrap());
+
+        let mut loader = CachedLoader::with_traces_dir(&traces_dir);
+        for (i, (record, trace)) in records.iter().zip(traces.iter()).enumerate() {
+            assert_eq!(record.bytes("plaintext").unwrap(), vec![i as u8, 0xff]);
+            assert_eq!(record.data["index"], i);
+            assert_eq!(&record.load_trace_from(&traces_dir).unwrap(), trace);
+            assert_eq!(&loader.load_trace(record).unwrap(), trace);
+        }
+
+        let batches: Vec<_> = records
+            .into_iter()
+            .batches(|record| record.bytes("plaintext").unwrap()[0])
+            .with_traces_dir(&traces_dir)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(batches.len(), 2);
+        let batch_traces: Vec<_> = batches.into_iter().flatten().map(Result::unwrap).collect();
+        for (i, (loaded, trace)) in batch_traces.iter().zip(traces.iter()).enumerate() {
+            assert_eq!(&loaded.leakage, trace);
+            assert_eq!(loaded.value, i as u8);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_log_writer_write_error() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // Writes to /dev/full fail, and it cannot be truncated
+        let mut writer = LogWriter::<i16>::create("/dev/full", dir.path(), 1).unwrap();
+        let result = writer.write(array![1i16, 2].view(), RecordData::new());
+        assert!(matches!(result, Err(LogError::IoError(_))));
+        assert_eq!(writer.num_pending(), 0);
+    }
+
+    #[test]
+    fn test_load_trace_from_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("log.jsonl");
+        let traces_dir = dir.path().join("traces");
+
+        let mut writer = LogWriter::<i16>::create(&log_path, &traces_dir, 3).unwrap();
+        for i in 0..3 {
+            writer
+                .write(array![i, -i, 2 * i].view(), RecordData::new())
+                .unwrap();
+        }
+        drop(writer);
+        let records = read_records(&log_path);
+        let (path, toff) = records[1].trace_location(&traces_dir).unwrap();
+        let (_, next_toff) = records[2].trace_location(&traces_dir).unwrap();
+
+        // Only the bytes of the array are read, not the rest of the batch
+        let mut reader = Cursor::new(fs::read(&path).unwrap());
+        reader.set_position(toff);
+        let trace = array_from_reader::<i16, _>(&mut reader, &path).unwrap();
+        assert_eq!(trace, array![1, -1, 2]);
+        assert_eq!(reader.position(), next_toff);
+        assert_eq!(records[1].load_trace_from(&traces_dir).unwrap(), trace);
+    }
+
+    #[test]
+    fn test_log_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("log.jsonl");
+        let traces_dir = dir.path().join("traces");
+
+        let mut writer = LogWriter::<i16>::create(&log_path, &traces_dir, 1).unwrap();
+        writer
+            .write(array![1i16, 2].view(), RecordData::new())
+            .unwrap();
+        writer
+            .write(array![3i16, 4].view(), RecordData::new())
+            .unwrap();
+        drop(writer);
+        fs::write(&log_path, fs::read_to_string(&log_path).unwrap() + "{}\n").unwrap();
+        let records = read_records(&log_path);
+
+        // Corrupt the first batch and remove the second one
+        let first_batch = path_in_traces_dir(&traces_dir, &records[0].bid().unwrap().unwrap());
+        fs::write(&first_batch, b"\x93NUMPY garbage").unwrap();
+        fs::remove_file(path_in_traces_dir(
+            &traces_dir,
+            &records[1].bid().unwrap().unwrap(),
+        ))
+        .unwrap();
+
+        let mut loader = CachedLoader::with_traces_dir(&traces_dir);
+        assert!(matches!(
+            loader.load_trace(&records[0]),
+            Err(LogError::InvalidNpy { path, .. }) if path == first_batch
+        ));
+        assert!(matches!(
+            loader.load_trace(&records[1]),
+            Err(LogError::MissingTraceFile(_))
+        ));
+        assert!(matches!(
+            loader.load_trace(&records[2]),
+            Err(LogError::MissingField(_))
+        ));
+
+        // Errors are reported for each batch, without stopping the iteration
+        let batches: Vec<_> = records
+            .into_iter()
+            .batches(|_| ())
+            .with_traces_dir(&traces_dir)
+            .collect();
+        assert_eq!(batches.len(), 3);
+        let mut batches = batches.into_iter();
+        let first_batch = batches.next().unwrap().unwrap();
+        assert!(first_batch.into_iter().all(|trace| trace.is_err()));
+        assert!(batches.all(|batch| batch.is_err()));
+    }
+
+    #[test]
+    fn test_batch_prefetcher() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("log.jsonl");
+        let traces_dir = dir.path().join("traces");
+
+        let mut writer = LogWriter::<i16>::create(&log_path, &traces_dir, 3).unwrap();
+        for i in 0..20i16 {
+            writer
+                .write(array![i, -i].view(), RecordData::new().field("index", i))
+                .unwrap();
+        }
+        drop(writer);
+        // Remove the third batch
+        let records = read_records(&log_path);
+        fs::remove_file(path_in_traces_dir(
+            &traces_dir,
+            &records[6].bid().unwrap().unwrap(),
+        ))
+        .unwrap();
+
+        let prefetcher = BatchPrefetcher::new()
+            .traces_dir(&traces_dir)
+            .num_threads(3)
+            .prefetch(1);
+        let mut prefetched = prefetcher
+            .batches::<i16, _, _>(log_path.to_str().unwrap(), |record| {
+                record.data["index"].as_i64().unwrap()
+            })
+            .unwrap();
+        let mut batches = Vec::new();
+        while let Some(batch) = prefetched.next() {
+            batches.push(batch);
+            // Batches read out of order are not accumulated beyond the prefetch limit
+            assert!(prefetched.pending.len() <= 1);
+        }
+        assert_eq!(batches.len(), 7);
+
+        let mut expected = 0;
+        for (i, batch) in batches.into_iter().enumerate() {
+            if i == 2 {
+                assert!(matches!(batch, Err(LogError::MissingTraceFile(_))));
+                expected += 3;
+                continue;
+            }
+            for trace in batch.unwrap() {
+                let trace = trace.unwrap();
+                assert_eq!(trace.value, expected);
+                assert_eq!(trace.leakage, array![expected as i16, -expected as i16]);
+                expected += 1;
+            }
+        }
+        assert_eq!(expected, 20);
+
+        // Dropping the iterator early stops the threads
+        let mut batches = prefetcher
+            .batches::<i16, _, _>(log_path.to_str().unwrap(), |_| ())
+            .unwrap();
+        assert!(batches.next().unwrap().is_ok());
+        drop(batches);
+    }
+
+    #[test]
+    fn test_record_accessors() {
+        let record = Record::<u8> {
+            data: json!({
+                "plaintext": "00112233",
+                "campaign": 3,
+                "inputs": {"fixed": true, "key": "zz"},
+                "inputs.iv": "aabb",
+            }),
+            phantom: Default::default(),
+        };
+
+        assert_eq!(
+            record.byte_array::<4>("plaintext").unwrap(),
+            [0x00, 0x11, 0x22, 0x33]
+        );
+        assert!(matches!(
+            record.byte_array::<16>("plaintext"),
+            Err(LogError::InvalidField(_))
+        ));
+        assert_eq!(record.integer("campaign").unwrap(), 3);
+        assert!(record.bool("inputs.fixed").unwrap());
+        assert!(matches!(
+            record.bytes("inputs.key"),
+            Err(LogError::InvalidField(key)) if key == "inputs.key"
+        ));
+        assert_eq!(record.bytes("inputs.iv").unwrap(), vec![0xaa, 0xbb]);
+        assert!(matches!(
+            record.bool("inputs.random"),
+            Err(LogError::MissingField(_))
+        ));
+    }
+
+    #[test]
+    fn test_trace_set_loader() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("log.jsonl");
+        let traces_dir = dir.path().join("traces");
+
+        let mut writer = LogWriter::<u8>::create(&log_path, &traces_dir, 4).unwrap();
+        for i in 0..10u8 {
+            let plaintext = if i % 3 == 0 { [0; 2] } else { [i, i + 1] };
+            writer
+                .write(
+                    array![i, 2 * i, 3 * i].view(),
+                    RecordData::new()
+                        .bytes("plaintext", &plaintext)
+                        .field("index", i)
+                        .field("meta", json!({"odd": i % 2 == 1})),
+                )
+                .unwrap();
+        }
+        drop(writer);
+
+        let traces = TraceSetLoader::<u8>::new()
+            .traces_dir(&traces_dir)
+            .bytes_column(names::PLAINTEXT, "plaintext")
+            .fixed_column(names::FIXED, "plaintext", &[0, 0])
+            .integer_column("index", "index")
+            .bool_column("odd", "meta.odd")
+            .filter(|record| record.integer("index").unwrap() >= 3)
+            .load(log_path.to_str().unwrap())
+            .unwrap();
+
+        assert_eq!(traces.len(), 7);
+        assert_eq!(traces.traces().row(0), array![3, 6, 9]);
+        let record = traces.get(1);
+        assert_eq!(record.bytes(names::PLAINTEXT).unwrap(), array![4, 5]);
+        assert_eq!(record.bool(names::FIXED), Some(false));
+        assert_eq!(traces.get(3).bool(names::FIXED), Some(true));
+        assert_eq!(record.integer("index"), Some(4));
+        assert_eq!(
+            traces.column("odd").unwrap().to_owned(),
+            Column::Bool(array![true, false, true, false, true, false, true])
+        );
+
+        let result = TraceSetLoader::<u8>::new()
+            .traces_dir(&traces_dir)
+            .integer_column("missing", "missing")
+            .load(log_path.to_str().unwrap());
+        assert!(matches!(result, Err(LogError::MissingField(_))));
+    }
+
+    #[test]
+    fn test_trace_set_loader_empty_traces() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("log.jsonl");
+        let traces_dir = dir.path().join("traces");
+
+        let mut writer = LogWriter::<u8>::create(&log_path, &traces_dir, 2).unwrap();
+        for i in 0..3u8 {
+            writer
+                .write(
+                    array![].view(),
+                    RecordData::new().bytes("plaintext", &[i, i]),
+                )
+                .unwrap();
+        }
+        drop(writer);
+
+        let traces = TraceSetLoader::<u8>::new()
+            .traces_dir(&traces_dir)
+            .bytes_column(names::PLAINTEXT, "plaintext")
+            .load(log_path.to_str().unwrap())
+            .unwrap();
+        assert_eq!(traces.len(), 3);
+        assert_eq!(traces.traces().shape(), [3, 0]);
+        assert_eq!(traces.get(2).bytes(names::PLAINTEXT).unwrap(), array![2, 2]);
+    }
 }
@@ -0,0 +1,227 @@
+//! Statistical tools to quantify the estimation error of processors results.
+
+use ndarray::{Array, Dimension, Zip};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+/// Point estimates along with their confidence interval.
+#[derive(Debug, Clone)]
+pub struct ConfidenceInterval<D>
+where
+    D: Dimension,
+{
+    /// Point estimates
+    pub estimate: Array<f32, D>,
+    /// Lower bounds of the confidence interval
+    pub lower: Array<f32, D>,
+    /// Upper bounds of the confidence interval
+    pub upper: Array<f32, D>,
+    /// Confidence level of the interval (e.g. 0.95)
+    pub confidence: f32,
+}
+
+/// Return the quantile function (inverse of the cumulative distribution function) of the standard
+/// normal distribution evaluated at `p`.
+///
+/// It uses the rational approximation from Peter J. Acklam, whose relative error is lower than
+/// 1.15e-9.
+///
+/// # Panics
+/// Panic if `p` is not in the open interval (0, 1).
+pub fn normal_quantile(p: f64) -> f64 {
+    assert!(p > 0. && p < 1.);
+
+    const A: [f64; 6] = [
+        -3.969683028665376e+01,
+        2.209460984245205e+02,
+        -2.759285104469687e+02,
+        1.38357751867269e+02,
+        -3.066479806614716e+01,
+        2.506628277459239e+00,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e+01,
+        1.615858368580409e+02,
+        -1.556989798598866e+02,
+        6.680131188771972e+01,
+        -1.328068155288572e+01,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-03,
+        -3.223964580411365e-01,
+        -2.400758277161838e+00,
+        -2.549732539343734e+00,
+        4.374664141464968e+00,
+        2.938163982698783e+00,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-03,
+        3.224671290700398e-01,
+        2.445134137142996e+00,
+        3.754408661907416e+00,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    if p < P_LOW {
+        let q = (-2. * p.ln()).sqrt();
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.)
+    } else if p <= 1. - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.)
+    } else {
+        -normal_quantile(1. - p)
+    }
+}
+
+/// Compute the confidence interval of Pearson correlation coefficients estimated on `num_traces`
+/// traces using the Fisher z-transformation[^1].
+///
+/// [^1]: <https://en.wikipedia.org/wiki/Fisher_transformation>
+///
+/// # Panics
+/// Panic if `confidence` is not in the open interval (0, 1).
+pub fn fisher_confidence_interval<D>(
+    corr: Array<f32, D>,
+    num_traces: usize,
+    confidence: f32,
+) -> ConfidenceInterval<D>
+where
+    D: Dimension,
+{
+    assert!(confidence > 0. && confidence < 1.);
+
+    let q = normal_quantile((1. + confidence as f64) / 2.) as f32;
+    // Standard error of the z-transformed coefficient. With 3 traces or less the interval spans
+    // the whole [-1, 1] range.
+    let std_err = if num_traces > 3 {
+        1. / ((num_traces - 3) as f32).sqrt()
+    } else {
+        f32::INFINITY
+    };
+
+    let mut lower = Array::zeros(corr.raw_dim());
+    let mut upper = Array::zeros(corr.raw_dim());
+    Zip::from(&mut lower)
+        .and(&mut upper)
+        .and(&corr)
+        .for_each(|lower, upper, &r| {
+            // Clamp to avoid infinite z for perfect correlations
+            let z = r.clamp(-1. + f32::EPSILON, 1. - f32::EPSILON).atanh();
+            *lower = (z - q * std_err).tanh();
+            *upper = (z + q * std_err).tanh();
+        });
+
+    ConfidenceInterval {
+        estimate: corr,
+        lower,
+        upper,
+        confidence,
+    }
+}
+
+/// Compute a percentile bootstrap confidence interval of a statistic by resampling with
+/// replacement sub-processors.
+///
+/// Each resample draws `processors.len()` sub-processors with replacement, merges them with
+/// `combine` and evaluates `statistic` on the result. The point estimate is the statistic of all
+/// the sub-processors merged together. Sub-processors should thus be computed on disjoint sets
+/// of traces of similar sizes, such as the batches used by the parallel helpers.
+///
+/// Resamples are computed in parallel and the statistic of every resample is kept in memory
+/// until the percentiles are computed.
+///
+/// # Panics
+/// - Panic if `processors` is empty.
+/// - Panic if `num_resamples` is 0.
+/// - Panic if `confidence` is not in the open interval (0, 1).
+pub fn bootstrap<P, D, C, S>(
+    processors: &[P],
+    num_resamples: usize,
+    confidence: f32,
+    seed: u64,
+    combine: C,
+    statistic: S,
+) -> ConfidenceInterval<D>
+where
+    P: Clone + Sync,
+    D: Dimension,
+    C: Fn(P, P) -> P + Sync,
+    S: Fn(&P) -> Array<f32, D> + Sync,
+{
+    assert!(!processors.is_empty());
+    assert!(num_resamples > 0);
+    assert!(confidence > 0. && confidence < 1.);
+
+    let merge = |indices: &mut dyn Iterator<Item = usize>| {
+        let first = processors[indices.next().unwrap()].clone();
+        indices.fold(first, |acc, i| combine(acc, processors[i].clone()))
+    };
+
+    let estimate = statistic(&merge(&mut (0..processors.len())));
+
+    let resamples: Vec<Array<f32, D>> = (0..num_resamples)
+        .into_par_iter()
+        .map(|resample| {
+            // Seed each resample independently so that results do not depend on scheduling
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(resample as u64));
+            statistic(&merge(
+                &mut (0..processors.len()).map(|_| rng.random_range(0..processors.len())),
+            ))
+        })
+        .collect();
+
+    let alpha = 1. - confidence;
+    let lower_idx = ((alpha / 2. * num_resamples as f32) as usize).min(num_resamples - 1);
+    let upper_idx = (((1. - alpha / 2.) * num_resamples as f32) as usize).min(num_resamples - 1);
+
+    let mut lower = Array::zeros(estimate.raw_dim());
+    let mut upper = Array::zeros(estimate.raw_dim());
+    // Iterate over all the resamples in lockstep, in logical order
+    let mut resamples_iter: Vec<_> = resamples.iter().map(|resample| resample.iter()).collect();
+    let mut values = Vec::with_capacity(num_resamples);
+    Zip::from(&mut lower)
+        .and(&mut upper)
+        .for_each(|lower, upper| {
+            values.clear();
+            values.extend(resamples_iter.iter_mut().map(|iter| *iter.next().unwrap()));
+            values.sort_by(f32::total_cmp);
+
+            *lower = values[lower_idx];
+            *upper = values[upper_idx];
+        });
+
+    ConfidenceInterval {
+        estimate,
+        lower,
+        upper,
+        confidence,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fisher_confidence_interval, normal_quantile};
+    use ndarray::array;
+
+    #[test]
+    fn test_normal_quantile() {
+        assert!((normal_quantile(0.5)).abs() < 1e-9);
+        assert!((normal_quantile(0.975) - 1.959963984540054).abs() < 1e-8);
+        assert!((normal_quantile(0.005) + 2.5758293035489).abs() < 1e-8);
+    }
+
+    #[test]
+    fn test_fisher_confidence_interval() {
+        let ci = fisher_confidence_interval(array![0., 0.5, -0.3], 103, 0.95);
+
+        let expected_lower = [-0.19352, 0.33931, -0.46644];
+        let expected_upper = [0.19352, 0.63234, -0.11304];
+        for i in 0..3 {
+            assert!((ci.lower[i] - expected_lower[i]).abs() < 1e-4);
+            assert!((ci.upper[i] - expected_upper[i]).abs() < 1e-4);
+        }
+    }
+}
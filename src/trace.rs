@@ -1,6 +1,9 @@
-//! Defines the [`Trace`] storage structure.
+//! Defines the [`Trace`] and [`TraceSet`] storage structures.
 
-use ndarray::Array1;
+use ndarray::{Array1, Array2, ArrayView1, ArrayView2, Axis, Slice};
+use rand::{Rng, seq::SliceRandom};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, ops::Range, sync::Arc};
 
 /// A side channel leakage record associated to its leakage data.
 ///
@@ -23,3 +26,598 @@ impl<T, U> Trace<T, U> {
         self.leakage.len()
     }
 }
+
+/// Conventional names of the metadata columns of a [`TraceSet`].
+pub mod names {
+    pub const PLAINTEXT: &str = "plaintext";
+    pub const CIPHERTEXT: &str = "ciphertext";
+    pub const KEY: &str = "key";
+    pub const MASK: &str = "mask";
+    /// Fixed (`true`) or random (`false`) input, as used by fixed vs. random t-tests
+    pub const FIXED: &str = "fixed";
+}
+
+/// Metadata column of a [`TraceSet`], holding one value per trace.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Column {
+    /// Byte strings, one row per trace, such as plaintexts, ciphertexts, keys or masks
+    Bytes(Array2<u8>),
+    /// Flags, such as fixed vs. random inputs
+    Bool(Array1<bool>),
+    /// Integers, such as class labels
+    Integer(Array1<i64>),
+}
+
+impl Column {
+    /// Returns the number of traces described by the column.
+    pub fn len(&self) -> usize {
+        self.view().len()
+    }
+
+    /// Returns `true` if the column is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a view of the column.
+    pub fn view(&self) -> ColumnView<'_> {
+        match self {
+            Column::Bytes(x) => ColumnView::Bytes(x.view()),
+            Column::Bool(x) => ColumnView::Bool(x.view()),
+            Column::Integer(x) => ColumnView::Integer(x.view()),
+        }
+    }
+}
+
+/// View of a [`Column`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColumnView<'a> {
+    Bytes(ArrayView2<'a, u8>),
+    Bool(ArrayView1<'a, bool>),
+    Integer(ArrayView1<'a, i64>),
+}
+
+impl<'a> ColumnView<'a> {
+    /// Returns the number of traces described by the column.
+    pub fn len(&self) -> usize {
+        match self {
+            ColumnView::Bytes(x) => x.shape()[0],
+            ColumnView::Bool(x) => x.len(),
+            ColumnView::Integer(x) => x.len(),
+        }
+    }
+
+    /// Returns `true` if the column is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the values of the given range of traces, without copy.
+    pub fn slice(self, range: Range<usize>) -> Self {
+        let slice = Slice::from(range);
+        match self {
+            ColumnView::Bytes(x) => ColumnView::Bytes(x.slice_axis_move(Axis(0), slice)),
+            ColumnView::Bool(x) => ColumnView::Bool(x.slice_axis_move(Axis(0), slice)),
+            ColumnView::Integer(x) => ColumnView::Integer(x.slice_axis_move(Axis(0), slice)),
+        }
+    }
+
+    /// Returns a column with the values of the traces at the given indices.
+    pub fn select(&self, indices: &[usize]) -> Column {
+        match self {
+            ColumnView::Bytes(x) => Column::Bytes(x.select(Axis(0), indices)),
+            ColumnView::Bool(x) => Column::Bool(x.select(Axis(0), indices)),
+            ColumnView::Integer(x) => Column::Integer(x.select(Axis(0), indices)),
+        }
+    }
+
+    /// Copy the column.
+    pub fn to_owned(&self) -> Column {
+        match self {
+            ColumnView::Bytes(x) => Column::Bytes(x.to_owned()),
+            ColumnView::Bool(x) => Column::Bool(x.to_owned()),
+            ColumnView::Integer(x) => Column::Integer(x.to_owned()),
+        }
+    }
+}
+
+/// A set of traces along with named metadata columns.
+///
+/// Traces are stored as an `Array2<T>` with one trace per row, and each metadata [`Column`] holds
+/// one value per trace. Conventional column names are defined in [`names`].
+///
+/// Slicing and splitting return [`TraceSetView`]s which do not copy the traces.
+///
+/// # Examples
+/// ```rust
+/// use muscat::distinguishers::cpa::CpaProcessor;
+/// use muscat::leakage_model::aes::sbox;
+/// use muscat::trace::{Column, TraceSet, names};
+/// use ndarray::array;
+///
+/// let traces = TraceSet::new(array![
+///     [77u8, 137, 51, 91],
+///     [72, 61, 91, 83],
+///     [39, 49, 52, 23],
+///     [26, 114, 63, 45],
+/// ])
+/// .with_column(names::PLAINTEXT, Column::Bytes(array![[1], [2], [1], [2]]));
+///
+/// let leakage_model = |plaintext: usize, guess: usize| sbox((plaintext ^ guess) as u8) as usize;
+/// let (train, test) = traces.split_at(3);
+///
+/// let mut processor = CpaProcessor::new(train.trace_length(), 256);
+/// for record in train.iter() {
+///     let plaintext = record.bytes(names::PLAINTEXT).unwrap();
+///     processor.update(record.trace(), plaintext[0] as usize, leakage_model);
+/// }
+/// assert_eq!(test.len(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceSet<T> {
+    traces: Array2<T>,
+    columns: BTreeMap<String, Column>,
+}
+
+impl<T> TraceSet<T> {
+    /// Creates a new [`TraceSet`] without metadata columns.
+    pub fn new(traces: Array2<T>) -> Self {
+        Self {
+            traces,
+            columns: BTreeMap::new(),
+        }
+    }
+
+    /// Insert a metadata column, replacing and returning the column with the same name if any.
+    ///
+    /// # Panics
+    /// Panic if the column length differs from the number of traces.
+    pub fn insert_column(&mut self, name: impl Into<String>, column: Column) -> Option<Column> {
+        assert_eq!(column.len(), self.len());
+
+        self.columns.insert(name.into(), column)
+    }
+
+    /// Builder variant of [`TraceSet::insert_column`].
+    ///
+    /// # Panics
+    /// Panic if the column length differs from the number of traces.
+    pub fn with_column(mut self, name: impl Into<String>, column: Column) -> Self {
+        self.insert_column(name, column);
+        self
+    }
+
+    /// Remove and return the metadata column with the given name.
+    pub fn remove_column(&mut self, name: &str) -> Option<Column> {
+        self.columns.remove(name)
+    }
+
+    /// Returns a view of the trace set.
+    pub fn view(&self) -> TraceSetView<'_, T> {
+        TraceSetView {
+            traces: self.traces.view(),
+            columns: Arc::new(
+                self.columns
+                    .iter()
+                    .map(|(name, column)| (name.as_str(), column.view()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Returns the number of traces.
+    pub fn len(&self) -> usize {
+        self.traces.shape()[0]
+    }
+
+    /// Returns `true` if the set contains no trace.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of samples per trace.
+    pub fn trace_length(&self) -> usize {
+        self.traces.shape()[1]
+    }
+
+    /// Returns the traces, one per row.
+    pub fn traces(&self) -> ArrayView2<'_, T> {
+        self.traces.view()
+    }
+
+    /// Consumes the set, returning the traces and the metadata columns by name.
+    pub fn into_parts(self) -> (Array2<T>, BTreeMap<String, Column>) {
+        (self.traces, self.columns)
+    }
+
+    /// Returns the names of the metadata columns.
+    pub fn column_names(&self) -> impl Iterator<Item = &str> {
+        self.columns.keys().map(String::as_str)
+    }
+
+    /// Returns the metadata column with the given name.
+    pub fn column(&self, name: &str) -> Option<ColumnView<'_>> {
+        self.columns.get(name).map(Column::view)
+    }
+
+    /// Returns the given range of traces (see [`TraceSetView::slice`]).
+    pub fn slice(&self, range: Range<usize>) -> TraceSetView<'_, T> {
+        self.view().slice(range)
+    }
+
+    /// Split the set in two at the given trace index (see [`TraceSetView::split_at`]).
+    pub fn split_at(&self, index: usize) -> (TraceSetView<'_, T>, TraceSetView<'_, T>) {
+        self.view().split_at(index)
+    }
+
+    /// Returns the trace at the given index and its metadata.
+    ///
+    /// # Panics
+    /// Panic if `index >= self.len()`.
+    pub fn get(&self, index: usize) -> TraceRecord<'_, T> {
+        self.view().get(index)
+    }
+
+    /// Returns a set with `f` applied to every sample, such as a conversion to another sample
+    /// type, and the same metadata.
+    pub fn mapv<U, F>(&self, f: F) -> TraceSet<U>
+    where
+        T: Clone,
+        F: FnMut(T) -> U,
+    {
+        TraceSet {
+            traces: self.traces.mapv(f),
+            columns: self.columns.clone(),
+        }
+    }
+
+    /// Iterate over the traces and their metadata.
+    pub fn iter(&self) -> TraceRecords<'_, T> {
+        self.view().into_iter()
+    }
+
+    /// Shuffle the traces and their metadata in place.
+    pub fn shuffle<R>(&mut self, rng: &mut R)
+    where
+        T: Clone,
+        R: Rng + ?Sized,
+    {
+        let mut indices: Vec<usize> = (0..self.len()).collect();
+        indices.shuffle(rng);
+        *self = self.select(&indices);
+    }
+
+    /// Returns a set with the traces at the given indices (see [`TraceSetView::select`]).
+    pub fn select(&self, indices: &[usize]) -> TraceSet<T>
+    where
+        T: Clone,
+    {
+        self.view().select(indices)
+    }
+
+    /// Returns a set with the traces for which `predicate` returns `true` (see
+    /// [`TraceSetView::filter`]).
+    pub fn filter<F>(&self, predicate: F) -> TraceSet<T>
+    where
+        T: Clone,
+        F: FnMut(&TraceRecord<T>) -> bool,
+    {
+        self.view().filter(predicate)
+    }
+}
+
+impl<T> From<Array2<T>> for TraceSet<T> {
+    fn from(traces: Array2<T>) -> Self {
+        Self::new(traces)
+    }
+}
+
+/// View of a [`TraceSet`].
+#[derive(Debug, PartialEq)]
+pub struct TraceSetView<'a, T> {
+    traces: ArrayView2<'a, T>,
+    /// Shared with the [`TraceRecord`]s of the view
+    columns: Arc<BTreeMap<&'a str, ColumnView<'a>>>,
+}
+
+impl<T> Clone for TraceSetView<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            traces: self.traces,
+            columns: self.columns.clone(),
+        }
+    }
+}
+
+impl<'a, T> TraceSetView<'a, T> {
+    /// Returns the number of traces.
+    pub fn len(&self) -> usize {
+        self.traces.shape()[0]
+    }
+
+    /// Returns `true` if the view contains no trace.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of samples per trace.
+    pub fn trace_length(&self) -> usize {
+        self.traces.shape()[1]
+    }
+
+    /// Returns the traces, one per row.
+    pub fn traces(&self) -> ArrayView2<'a, T> {
+        self.traces
+    }
+
+    /// Returns the names of the metadata columns.
+    pub fn column_names(&self) -> impl Iterator<Item = &'a str> {
+        self.columns.keys().copied()
+    }
+
+    /// Returns the metadata column with the given name.
+    pub fn column(&self, name: &str) -> Option<ColumnView<'a>> {
+        self.columns.get(name).copied()
+    }
+
+    /// Returns the given range of traces, without copy.
+    ///
+    /// # Panics
+    /// Panic if `range` is out of bounds.
+    pub fn slice(&self, range: Range<usize>) -> TraceSetView<'a, T> {
+        TraceSetView {
+            traces: self
+                .traces
+                .slice_axis_move(Axis(0), Slice::from(range.clone())),
+            columns: Arc::new(
+                self.columns
+                    .iter()
+                    .map(|(&name, column)| (name, column.slice(range.clone())))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Split the view in two at the given trace index, without copy. The first view contains the
+    /// traces `[0, index)` and the second one the traces `[index, len)`.
+    ///
+    /// Combined with [`TraceSet::shuffle`], this gives a train/test split.
+    ///
+    /// # Panics
+    /// Panic if `index > self.len()`.
+    pub fn split_at(&self, index: usize) -> (TraceSetView<'a, T>, TraceSetView<'a, T>) {
+        (self.slice(0..index), self.slice(index..self.len()))
+    }
+
+    /// Returns the trace at the given index and its metadata.
+    ///
+    /// # Panics
+    /// Panic if `index >= self.len()`.
+    pub fn get(&self, index: usize) -> TraceRecord<'a, T> {
+        TraceRecord {
+            index,
+            trace: self.traces.index_axis_move(Axis(0), index),
+            columns: self.columns.clone(),
+        }
+    }
+
+    /// Returns a set with the traces at the given indices.
+    ///
+    /// # Panics
+    /// Panic if an index is out of bounds.
+    pub fn select(&self, indices: &[usize]) -> TraceSet<T>
+    where
+        T: Clone,
+    {
+        TraceSet {
+            traces: self.traces.select(Axis(0), indices),
+            columns: self
+                .columns
+                .iter()
+                .map(|(&name, column)| (name.to_string(), column.select(indices)))
+                .collect(),
+        }
+    }
+
+    /// Returns a set with the traces for which `predicate` returns `true`.
+    pub fn filter<F>(&self, mut predicate: F) -> TraceSet<T>
+    where
+        T: Clone,
+        F: FnMut(&TraceRecord<T>) -> bool,
+    {
+        let indices: Vec<usize> = (0..self.len())
+            .filter(|&i| predicate(&self.get(i)))
+            .collect();
+
+        self.select(&indices)
+    }
+
+    /// Copy the view into a new [`TraceSet`].
+    pub fn to_owned(&self) -> TraceSet<T>
+    where
+        T: Clone,
+    {
+        TraceSet {
+            traces: self.traces.to_owned(),
+            columns: self
+                .columns
+                .iter()
+                .map(|(&name, column)| (name.to_string(), column.to_owned()))
+                .collect(),
+        }
+    }
+
+    /// Iterate over the traces and their metadata.
+    pub fn iter(&self) -> TraceRecords<'a, T> {
+        self.clone().into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for TraceSetView<'a, T> {
+    type Item = TraceRecord<'a, T>;
+    type IntoIter = TraceRecords<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        TraceRecords {
+            indices: 0..self.len(),
+            view: self,
+        }
+    }
+}
+
+/// Iterator over the [`TraceRecord`]s of a [`TraceSetView`].
+pub struct TraceRecords<'a, T> {
+    view: TraceSetView<'a, T>,
+    indices: Range<usize>,
+}
+
+impl<'a, T> Iterator for TraceRecords<'a, T> {
+    type Item = TraceRecord<'a, T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.indices.next().map(|i| self.view.get(i))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.indices.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for TraceRecords<'_, T> {}
+
+/// A trace of a [`TraceSet`] and its metadata.
+#[derive(Debug)]
+pub struct TraceRecord<'a, T> {
+    index: usize,
+    trace: ArrayView1<'a, T>,
+    columns: Arc<BTreeMap<&'a str, ColumnView<'a>>>,
+}
+
+impl<'a, T> TraceRecord<'a, T> {
+    /// Returns the index of the trace in the set or view it comes from.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the trace.
+    pub fn trace(&self) -> ArrayView1<'a, T> {
+        self.trace
+    }
+
+    /// Returns the value of the given bytes column.
+    pub fn bytes(&self, name: &str) -> Option<ArrayView1<'a, u8>> {
+        match *self.columns.get(name)? {
+            ColumnView::Bytes(x) => Some(x.index_axis_move(Axis(0), self.index)),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the given bool column.
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        match self.columns.get(name)? {
+            ColumnView::Bool(x) => Some(x[self.index]),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of the given integer column.
+    pub fn integer(&self, name: &str) -> Option<i64> {
+        match self.columns.get(name)? {
+            ColumnView::Integer(x) => Some(x[self.index]),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Column, ColumnView, TraceSet, names};
+    use ndarray::{Array2, array};
+    use rand::{SeedableRng, rngs::StdRng};
+
+    fn trace_set() -> TraceSet<i16> {
+        TraceSet::new(Array2::from_shape_fn((6, 3), |(i, j)| (i * 10 + j) as i16))
+            .with_column(
+                names::PLAINTEXT,
+                Column::Bytes(Array2::from_shape_fn((6, 2), |(i, j)| (i * 2 + j) as u8)),
+            )
+            .with_column(
+                names::FIXED,
+                Column::Bool(array![true, false, true, false, true, false]),
+            )
+            .with_column("label", Column::Integer(array![0, 1, 2, 3, 4, 5]))
+    }
+
+    #[test]
+    fn test_trace_set_views() {
+        let set = trace_set();
+        assert_eq!(set.len(), 6);
+        assert_eq!(set.trace_length(), 3);
+        assert_eq!(
+            set.column_names().collect::<Vec<_>>(),
+            vec!["fixed", "label", "plaintext"]
+        );
+
+        let (train, test) = set.split_at(4);
+        assert_eq!(train.len(), 4);
+        assert_eq!(test.len(), 2);
+        assert_eq!(test.traces(), array![[40, 41, 42], [50, 51, 52]]);
+        let plaintexts = array![[8, 9], [10, 11]];
+        assert_eq!(
+            test.column(names::PLAINTEXT),
+            Some(ColumnView::Bytes(plaintexts.view()))
+        );
+        // Views do not copy the traces
+        assert_eq!(test.traces().as_ptr(), set.traces().row(4).as_ptr());
+
+        let record = test.get(1);
+        assert_eq!(record.trace(), array![50, 51, 52]);
+        assert_eq!(record.bytes(names::PLAINTEXT).unwrap(), array![10, 11]);
+        assert_eq!(record.bool(names::FIXED), Some(false));
+        assert_eq!(record.integer("label"), Some(5));
+        assert_eq!(record.integer(names::FIXED), None);
+        assert_eq!(record.bytes(names::KEY), None);
+
+        assert_eq!(set.slice(1..3).to_owned(), set.select(&[1, 2]));
+        assert_eq!(set.iter().count(), 6);
+
+        let traces_ptr = set.traces().as_ptr();
+        let (traces, columns) = set.into_parts();
+        assert_eq!(traces.as_ptr(), traces_ptr);
+        assert_eq!(columns["label"], Column::Integer(array![0, 1, 2, 3, 4, 5]));
+    }
+
+    #[test]
+    fn test_trace_set_filter_shuffle() {
+        let set = trace_set();
+
+        let fixed = set.filter(|record| record.bool(names::FIXED).unwrap());
+        assert_eq!(fixed.len(), 3);
+        let labels = array![0, 2, 4];
+        assert_eq!(
+            fixed.column("label"),
+            Some(ColumnView::Integer(labels.view()))
+        );
+
+        let mut shuffled = set.clone();
+        shuffled.shuffle(&mut StdRng::seed_from_u64(0));
+        // Metadata stays attached to its trace
+        for record in shuffled.iter() {
+            let label = record.integer("label").unwrap();
+            assert_eq!(record.trace()[0], label as i16 * 10);
+            assert_eq!(record.bytes(names::PLAINTEXT).unwrap()[0], label as u8 * 2);
+        }
+        let mut labels: Vec<_> = shuffled
+            .iter()
+            .map(|r| r.integer("label").unwrap())
+            .collect();
+        labels.sort();
+        assert_eq!(labels, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_trace_set_column_length() {
+        trace_set().insert_column(names::KEY, Column::Bool(array![true]));
+    }
+}
@@ -0,0 +1,734 @@
+//! Read and write traces in the Riscure Inspector trace set format (`.trs`).
+//!
+//! A `.trs` file starts with a header made of tag-length-value objects, followed by the traces.
+//! Each trace is stored as an optional title, a fixed number of data bytes (such as the plaintext
+//! and ciphertext) and the samples, in little-endian.
+
+use ndarray::{Array1, ArrayView1};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    marker::PhantomData,
+    path::Path,
+};
+use thiserror::Error;
+
+use crate::trace::Trace;
+
+/// Header tags.
+mod tag {
+    pub const NUMBER_OF_TRACES: u8 = 0x41;
+    pub const NUMBER_OF_SAMPLES: u8 = 0x42;
+    pub const SAMPLE_CODING: u8 = 0x43;
+    pub const DATA_LENGTH: u8 = 0x44;
+    pub const TITLE_SPACE: u8 = 0x45;
+    pub const GLOBAL_TITLE: u8 = 0x46;
+    pub const DESCRIPTION: u8 = 0x47;
+    pub const X_SCALE: u8 = 0x4B;
+    pub const Y_SCALE: u8 = 0x4C;
+    pub const TRACE_BLOCK: u8 = 0x5F;
+}
+
+/// Errors raised when reading or writing `.trs` files.
+#[derive(Error, Debug)]
+pub enum TrsError {
+    #[error("IO error")]
+    IoError(#[from] io::Error),
+    #[error("Missing header tag 0x{0:02x}")]
+    MissingTag(u8),
+    #[error("Invalid length {length} for header tag 0x{tag:02x}")]
+    InvalidTagLength { tag: u8, length: usize },
+    #[error("Unsupported sample coding 0x{0:02x}")]
+    UnsupportedSampleCoding(u8),
+    #[error("Sample coding mismatch: expected {expected:?}, found {found:?}")]
+    SampleCodingMismatch {
+        expected: SampleCoding,
+        found: SampleCoding,
+    },
+    #[error("{field} {value} exceeds the maximum {max} of the format")]
+    ValueTooLarge {
+        field: &'static str,
+        value: usize,
+        max: usize,
+    },
+    #[error("Trace size overflows")]
+    TraceSizeOverflow,
+    #[error("Cannot allocate a buffer for traces of {0} bytes")]
+    AllocationFailed(usize),
+    #[error("{num_traces} traces of {trace_size} bytes exceed the {remaining} bytes of the file")]
+    FileTooShort {
+        num_traces: usize,
+        trace_size: usize,
+        remaining: u64,
+    },
+    #[error("Invalid {field} length: expected {expected}, found {found}")]
+    LengthMismatch {
+        field: &'static str,
+        expected: usize,
+        found: usize,
+    },
+}
+
+/// Encoding of the samples of a trace set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleCoding {
+    Byte,
+    Short,
+    Int,
+    Float,
+}
+
+impl SampleCoding {
+    /// Returns the size of a sample in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            SampleCoding::Byte => 1,
+            SampleCoding::Short => 2,
+            SampleCoding::Int | SampleCoding::Float => 4,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Result<Self, TrsError> {
+        match byte {
+            0x01 => Ok(SampleCoding::Byte),
+            0x02 => Ok(SampleCoding::Short),
+            0x04 => Ok(SampleCoding::Int),
+            0x14 => Ok(SampleCoding::Float),
+            _ => Err(TrsError::UnsupportedSampleCoding(byte)),
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            SampleCoding::Byte => 0x01,
+            SampleCoding::Short => 0x02,
+            SampleCoding::Int => 0x04,
+            SampleCoding::Float => 0x14,
+        }
+    }
+}
+
+/// Sample types that can be stored in `.trs` files.
+pub trait TrsSample: Sized + Copy {
+    /// Sample coding of the type.
+    const CODING: SampleCoding;
+
+    /// Decode a sample from its little-endian representation.
+    fn from_le_bytes(bytes: &[u8]) -> Self;
+
+    /// Append the little-endian representation of the sample to `bytes`.
+    fn extend_le_bytes(self, bytes: &mut Vec<u8>);
+}
+
+macro_rules! impl_trs_sample {
+    ($($t:ty => $coding:expr),*) => {
+        $(
+            impl TrsSample for $t {
+                const CODING: SampleCoding = $coding;
+
+                fn from_le_bytes(bytes: &[u8]) -> Self {
+                    <$t>::from_le_bytes(bytes.try_into().unwrap())
+                }
+
+                fn extend_le_bytes(self, bytes: &mut Vec<u8>) {
+                    bytes.extend_from_slice(&self.to_le_bytes());
+                }
+            }
+        )*
+    };
+}
+
+impl_trs_sample! {
+    i8 => SampleCoding::Byte,
+    i16 => SampleCoding::Short,
+    i32 => SampleCoding::Int,
+    f32 => SampleCoding::Float
+}
+
+/// Header of a `.trs` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrsHeader {
+    /// Number of traces
+    pub num_traces: usize,
+    /// Number of samples per trace
+    pub num_samples: usize,
+    pub sample_coding: SampleCoding,
+    /// Number of data bytes per trace
+    pub data_length: usize,
+    /// Number of title bytes per trace
+    pub title_length: usize,
+    pub global_title: Option<String>,
+    pub description: Option<String>,
+    /// Time between two samples, in seconds
+    pub x_scale: Option<f32>,
+    /// Scale to apply to the samples to get the measured value
+    pub y_scale: Option<f32>,
+}
+
+impl TrsHeader {
+    /// Creates a new header for traces of `num_samples` samples of type `T`, each associated
+    /// with `data_length` data bytes.
+    pub fn new<T: TrsSample>(num_samples: usize, data_length: usize) -> Self {
+        Self {
+            num_traces: 0,
+            num_samples,
+            sample_coding: T::CODING,
+            data_length,
+            title_length: 0,
+            global_title: None,
+            description: None,
+            x_scale: None,
+            y_scale: None,
+        }
+    }
+
+    /// Parse a header, leaving `reader` at the start of the first trace.
+    fn read<R: Read>(reader: &mut R) -> Result<Self, TrsError> {
+        let mut num_traces = None;
+        let mut num_samples = None;
+        let mut sample_coding = None;
+        let mut header = Self::new::<i8>(0, 0);
+
+        loop {
+            let tag = read_u8(reader)?;
+            let mut length = read_u8(reader)? as usize;
+            if length & 0x80 != 0 {
+                // Long form: the length is encoded on the given number of bytes
+                let num_bytes = length & 0x7f;
+                let mut bytes = [0u8; 8];
+                if num_bytes > bytes.len() {
+                    return Err(TrsError::InvalidTagLength { tag, length });
+                }
+                reader.read_exact(&mut bytes[..num_bytes])?;
+                length = u64::from_le_bytes(bytes) as usize;
+            }
+            // Do not trust the length to allocate the value, as the file may be truncated
+            let mut value = Vec::new();
+            reader
+                .by_ref()
+                .take(length as u64)
+                .read_to_end(&mut value)?;
+            if value.len() != length {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+
+            match tag {
+                tag::NUMBER_OF_TRACES => num_traces = Some(read_int(tag, &value)?),
+                tag::NUMBER_OF_SAMPLES => num_samples = Some(read_int(tag, &value)?),
+                tag::SAMPLE_CODING => {
+                    let [coding] = value[..] else {
+                        return Err(TrsError::InvalidTagLength { tag, length });
+                    };
+                    sample_coding = Some(SampleCoding::from_byte(coding)?);
+                }
+                tag::DATA_LENGTH => header.data_length = read_int(tag, &value)?,
+                tag::TITLE_SPACE => header.title_length = read_int(tag, &value)?,
+                tag::GLOBAL_TITLE => {
+                    header.global_title = Some(String::from_utf8_lossy(&value).into_owned())
+                }
+                tag::DESCRIPTION => {
+                    header.description = Some(String::from_utf8_lossy(&value).into_owned())
+                }
+                tag::X_SCALE => header.x_scale = Some(read_f32(tag, &value)?),
+                tag::Y_SCALE => header.y_scale = Some(read_f32(tag, &value)?),
+                tag::TRACE_BLOCK => break,
+                // Other tags are not needed to read the traces
+                _ => {}
+            }
+        }
+
+        header.num_traces = num_traces.ok_or(TrsError::MissingTag(tag::NUMBER_OF_TRACES))?;
+        header.num_samples = num_samples.ok_or(TrsError::MissingTag(tag::NUMBER_OF_SAMPLES))?;
+        header.sample_coding = sample_coding.ok_or(TrsError::MissingTag(tag::SAMPLE_CODING))?;
+
+        Ok(header)
+    }
+
+    /// Size of a trace in bytes, including its title and data.
+    fn trace_size(&self) -> Result<usize, TrsError> {
+        self.num_samples
+            .checked_mul(self.sample_coding.size())
+            .and_then(|size| size.checked_add(self.title_length))
+            .and_then(|size| size.checked_add(self.data_length))
+            .ok_or(TrsError::TraceSizeOverflow)
+    }
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> Result<u8, TrsError> {
+    let mut byte = [0u8];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+/// Decode a little-endian integer of 1 to 4 bytes.
+fn read_int(tag: u8, value: &[u8]) -> Result<usize, TrsError> {
+    if value.is_empty() || value.len() > 4 {
+        return Err(TrsError::InvalidTagLength {
+            tag,
+            length: value.len(),
+        });
+    }
+
+    let mut bytes = [0u8; 4];
+    bytes[..value.len()].copy_from_slice(value);
+    Ok(u32::from_le_bytes(bytes) as usize)
+}
+
+fn read_f32(tag: u8, value: &[u8]) -> Result<f32, TrsError> {
+    let bytes = value.try_into().map_err(|_| TrsError::InvalidTagLength {
+        tag,
+        length: value.len(),
+    })?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+/// Streaming reader of `.trs` files, iterating over the traces and their data bytes.
+///
+/// `T` is the sample type, which must match the sample coding of the file.
+///
+/// # Examples
+/// ```rust,no_run
+/// use muscat::trs::TrsReader;
+///
+/// let reader = TrsReader::<i16, _>::open("traces.trs").unwrap();
+/// println!("{} traces", reader.header().num_traces);
+/// for trace in reader {
+///     let trace = trace.unwrap();
+///     let (leakage, data) = (trace.leakage, trace.value);
+/// }
+/// ```
+pub struct TrsReader<T, R> {
+    reader: R,
+    header: TrsHeader,
+    /// Number of traces read so far
+    position: usize,
+    /// Size of a trace in bytes
+    trace_size: usize,
+    /// Buffer holding a whole trace
+    buffer: Vec<u8>,
+    phantom: PhantomData<T>,
+}
+
+impl<T: TrsSample> TrsReader<T, BufReader<File>> {
+    /// Opens the `.trs` file at the given path and parses its header.
+    ///
+    /// Returns [`TrsError::FileTooShort`] if the file cannot hold the traces of the header.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, TrsError> {
+        let file = File::open(path)?;
+        let file_length = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let header = TrsHeader::read(&mut reader)?;
+
+        let trace_size = header.trace_size()?;
+        let remaining = file_length.saturating_sub(reader.stream_position()?);
+        let fits = (header.num_traces as u64)
+            .checked_mul(trace_size as u64)
+            .is_some_and(|size| size <= remaining);
+        if !fits {
+            return Err(TrsError::FileTooShort {
+                num_traces: header.num_traces,
+                trace_size,
+                remaining,
+            });
+        }
+
+        Self::with_header(reader, header)
+    }
+}
+
+impl<T: TrsSample, R: Read> TrsReader<T, R> {
+    /// Creates a new [`TrsReader`] from a reader positioned at the start of a `.trs` file.
+    pub fn new(mut reader: R) -> Result<Self, TrsError> {
+        let header = TrsHeader::read(&mut reader)?;
+        Self::with_header(reader, header)
+    }
+
+    /// Creates a new [`TrsReader`] from a reader positioned at the first trace of a `.trs` file
+    /// with the given header.
+    fn with_header(reader: R, header: TrsHeader) -> Result<Self, TrsError> {
+        if header.sample_coding != T::CODING {
+            return Err(TrsError::SampleCodingMismatch {
+                expected: T::CODING,
+                found: header.sample_coding,
+            });
+        }
+
+        // The trace size comes from the file, so that the allocation may fail
+        let trace_size = header.trace_size()?;
+        let mut buffer = Vec::new();
+        buffer
+            .try_reserve_exact(trace_size)
+            .map_err(|_| TrsError::AllocationFailed(trace_size))?;
+
+        Ok(Self {
+            reader,
+            trace_size,
+            buffer,
+            header,
+            position: 0,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Returns the header of the file.
+    pub fn header(&self) -> &TrsHeader {
+        &self.header
+    }
+
+    /// Read the next trace, or return `None` if all the traces have been read.
+    ///
+    /// After an error, no more traces are read.
+    pub fn read_trace(&mut self) -> Result<Option<Trace<T, Vec<u8>>>, TrsError> {
+        if self.position == self.header.num_traces {
+            return Ok(None);
+        }
+        // The buffer is only filled as bytes are read, which bounds memory usage by the size of
+        // the file
+        self.buffer.clear();
+        let read = self
+            .reader
+            .by_ref()
+            .take(self.trace_size as u64)
+            .read_to_end(&mut self.buffer);
+        match read {
+            Ok(length) if length == self.trace_size => self.position += 1,
+            result => {
+                self.position = self.header.num_traces;
+                let err = result.err().unwrap_or(io::ErrorKind::UnexpectedEof.into());
+                return Err(err.into());
+            }
+        }
+
+        let data_start = self.header.title_length;
+        let samples_start = data_start + self.header.data_length;
+        let data = self.buffer[data_start..samples_start].to_vec();
+        let leakage: Array1<T> = self.buffer[samples_start..]
+            .chunks_exact(T::CODING.size())
+            .map(T::from_le_bytes)
+            .collect();
+
+        Ok(Some(Trace::new(leakage, data)))
+    }
+}
+
+impl<T: TrsSample, R: Read> Iterator for TrsReader<T, R> {
+    type Item = Result<Trace<T, Vec<u8>>, TrsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_trace().transpose()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.header.num_traces - self.position;
+        (remaining, Some(remaining))
+    }
+}
+
+/// Writer of `.trs` files.
+///
+/// The number of traces is written in the header by [`TrsWriter::finish`], which must be called
+/// once all the traces have been written.
+///
+/// # Examples
+/// ```rust,no_run
+/// use muscat::trs::{TrsHeader, TrsWriter};
+/// use ndarray::array;
+///
+/// let header = TrsHeader::new::<f32>(3, 16);
+/// let mut writer = TrsWriter::create("preprocessed.trs", header).unwrap();
+/// writer.write_trace(array![0.5f32, 1.5, -2.].view(), &[0; 16]).unwrap();
+/// writer.finish().unwrap();
+/// ```
+pub struct TrsWriter<T, W: Write + Seek> {
+    writer: W,
+    header: TrsHeader,
+    /// Position of the number of traces in the header
+    num_traces_position: u64,
+    buffer: Vec<u8>,
+    phantom: PhantomData<T>,
+}
+
+impl<T: TrsSample> TrsWriter<T, BufWriter<File>> {
+    /// Creates a `.trs` file at the given path and writes its header.
+    pub fn create<P: AsRef<Path>>(path: P, header: TrsHeader) -> Result<Self, TrsError> {
+        Self::new(BufWriter::new(File::create(path)?), header)
+    }
+}
+
+impl<T: TrsSample, W: Write + Seek> TrsWriter<T, W> {
+    /// Creates a new [`TrsWriter`] and writes the header. The number of traces of `header` is
+    /// ignored.
+    pub fn new(mut writer: W, mut header: TrsHeader) -> Result<Self, TrsError> {
+        if header.sample_coding != T::CODING {
+            return Err(TrsError::SampleCodingMismatch {
+                expected: T::CODING,
+                found: header.sample_coding,
+            });
+        }
+        check_max("number of samples", header.num_samples, u32::MAX as usize)?;
+        check_max("data length", header.data_length, u16::MAX as usize)?;
+        check_max("title length", header.title_length, u8::MAX as usize)?;
+        let trace_size = header.trace_size()?;
+        header.num_traces = 0;
+
+        let start = writer.stream_position()?;
+        let mut bytes = Vec::new();
+        write_tag(&mut bytes, tag::NUMBER_OF_TRACES, &0u32.to_le_bytes());
+        write_tag(
+            &mut bytes,
+            tag::NUMBER_OF_SAMPLES,
+            &(header.num_samples as u32).to_le_bytes(),
+        );
+        write_tag(&mut bytes, tag::SAMPLE_CODING, &[T::CODING.to_byte()]);
+        write_tag(
+            &mut bytes,
+            tag::DATA_LENGTH,
+            &(header.data_length as u16).to_le_bytes(),
+        );
+        write_tag(&mut bytes, tag::TITLE_SPACE, &[header.title_length as u8]);
+        if let Some(global_title) = &header.global_title {
+            write_tag(&mut bytes, tag::GLOBAL_TITLE, global_title.as_bytes());
+        }
+        if let Some(description) = &header.description {
+            write_tag(&mut bytes, tag::DESCRIPTION, description.as_bytes());
+        }
+        if let Some(x_scale) = header.x_scale {
+            write_tag(&mut bytes, tag::X_SCALE, &x_scale.to_le_bytes());
+        }
+        if let Some(y_scale) = header.y_scale {
+            write_tag(&mut bytes, tag::Y_SCALE, &y_scale.to_le_bytes());
+        }
+        write_tag(&mut bytes, tag::TRACE_BLOCK, &[]);
+        writer.write_all(&bytes)?;
+
+        Ok(Self {
+            writer,
+            // The number of traces is the value of the first tag
+            num_traces_position: start + 2,
+            buffer: Vec::with_capacity(trace_size),
+            header,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Returns the header of the file, with the number of traces written so far.
+    pub fn header(&self) -> &TrsHeader {
+        &self.header
+    }
+
+    /// Write a trace and its data bytes. Trace titles are filled with zeros.
+    pub fn write_trace(&mut self, leakage: ArrayView1<T>, data: &[u8]) -> Result<(), TrsError> {
+        check_max(
+            "number of traces",
+            self.header.num_traces + 1,
+            u32::MAX as usize,
+        )?;
+        if leakage.len() != self.header.num_samples {
+            return Err(TrsError::LengthMismatch {
+                field: "trace",
+                expected: self.header.num_samples,
+                found: leakage.len(),
+            });
+        }
+        if data.len() != self.header.data_length {
+            return Err(TrsError::LengthMismatch {
+                field: "data",
+                expected: self.header.data_length,
+                found: data.len(),
+            });
+        }
+
+        self.buffer.clear();
+        self.buffer.resize(self.header.title_length, 0);
+        self.buffer.extend_from_slice(data);
+        for &x in leakage.iter() {
+            x.extend_le_bytes(&mut self.buffer);
+        }
+        self.writer.write_all(&self.buffer)?;
+        self.header.num_traces += 1;
+
+        Ok(())
+    }
+
+    /// Write the number of traces in the header and flush the file. Returns the underlying writer.
+    pub fn finish(mut self) -> Result<W, TrsError> {
+        let end = self.writer.stream_position()?;
+        self.writer
+            .seek(SeekFrom::Start(self.num_traces_position))?;
+        self.writer
+            .write_all(&(self.header.num_traces as u32).to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Check that the header `field` fits in the integer type of the format.
+fn check_max(field: &'static str, value: usize, max: usize) -> Result<(), TrsError> {
+    if value > max {
+        return Err(TrsError::ValueTooLarge { field, value, max });
+    }
+
+    Ok(())
+}
+
+/// Append a tag-length-value object to `bytes`.
+fn write_tag(bytes: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    bytes.push(tag);
+    if value.len() < 0x80 {
+        bytes.push(value.len() as u8);
+    } else {
+        bytes.push(0x84);
+        bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    }
+    bytes.extend_from_slice(value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SampleCoding, TrsError, TrsHeader, TrsReader, TrsWriter};
+    use ndarray::array;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_trs_round_trip() {
+        let mut header = TrsHeader::new::<i16>(4, 2);
+        header.title_length = 3;
+        header.description = Some("x".repeat(200));
+        header.x_scale = Some(1e-9);
+
+        let traces = [
+            (array![1i16, -2, 300, -32768], [0xaa, 0x01]),
+            (array![5, 6, 7, 8], [0xbb, 0x02]),
+            (array![-1, 0, 1, 32767], [0xcc, 0x03]),
+        ];
+        let mut writer = TrsWriter::new(Cursor::new(Vec::new()), header.clone()).unwrap();
+        for (leakage, data) in traces.iter() {
+            writer.write_trace(leakage.view(), data).unwrap();
+        }
+        assert!(matches!(
+            writer.write_trace(array![1i16].view(), &[0, 0]),
+            Err(TrsError::LengthMismatch { field: "trace", .. })
+        ));
+        let bytes = writer.finish().unwrap().into_inner();
+
+        let reader = TrsReader::<i16, _>::new(Cursor::new(&bytes)).unwrap();
+        header.num_traces = 3;
+        assert_eq!(reader.header(), &header);
+        assert_eq!(reader.size_hint(), (3, Some(3)));
+        let read: Vec<_> = reader.map(Result::unwrap).collect();
+        assert_eq!(read.len(), 3);
+        for (trace, (leakage, data)) in read.iter().zip(traces.iter()) {
+            assert_eq!(&trace.leakage, leakage);
+            assert_eq!(&trace.value, data);
+        }
+
+        assert!(matches!(
+            TrsReader::<f32, _>::new(Cursor::new(&bytes)),
+            Err(TrsError::SampleCodingMismatch {
+                expected: SampleCoding::Float,
+                found: SampleCoding::Short
+            })
+        ));
+    }
+
+    #[test]
+    fn test_trs_read_header() {
+        // NT = 1, NS = 2, SC = float, DS = 1, unknown tag, TB
+        let mut bytes = vec![
+            0x41, 0x04, 0x01, 0x00, 0x00, 0x00, 0x42, 0x01, 0x02, 0x43, 0x01, 0x14, 0x44, 0x02,
+            0x01, 0x00, 0x4e, 0x01, 0x00, 0x4c, 0x04,
+        ];
+        bytes.extend_from_slice(&0.5f32.to_le_bytes());
+        bytes.extend_from_slice(&[0x5f, 0x00, 0x42]);
+        bytes.extend_from_slice(&1.5f32.to_le_bytes());
+        bytes.extend_from_slice(&(-2f32).to_le_bytes());
+
+        let mut reader = TrsReader::<f32, _>::new(Cursor::new(bytes)).unwrap();
+        assert_eq!(reader.header().num_samples, 2);
+        assert_eq!(reader.header().y_scale, Some(0.5));
+
+        let trace = reader.next().unwrap().unwrap();
+        assert_eq!(trace.leakage, array![1.5, -2.]);
+        assert_eq!(trace.value, vec![0x42]);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_trs_missing_tag() {
+        let bytes = vec![0x41, 0x01, 0x00, 0x5f, 0x00];
+        assert!(matches!(
+            TrsReader::<i8, _>::new(Cursor::new(bytes)),
+            Err(TrsError::MissingTag(0x42))
+        ));
+    }
+
+    #[test]
+    fn test_trs_truncated() {
+        let mut writer =
+            TrsWriter::new(Cursor::new(Vec::new()), TrsHeader::new::<i8>(4, 1)).unwrap();
+        for i in 0..3i8 {
+            writer.write_trace(array![i, 1, 2, 3].view(), &[0]).unwrap();
+        }
+        let mut bytes = writer.finish().unwrap().into_inner();
+        bytes.truncate(bytes.len() - 2);
+
+        // The reader stops after the first error
+        let read: Vec<_> = TrsReader::<i8, _>::new(Cursor::new(&bytes))
+            .unwrap()
+            .collect();
+        assert_eq!(read.len(), 3);
+        assert!(read[..2].iter().all(Result::is_ok));
+        assert!(matches!(read[2], Err(TrsError::IoError(_))));
+
+        // Files opened by path are checked against the header
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("traces.trs");
+        std::fs::write(&path, &bytes).unwrap();
+        assert!(matches!(
+            TrsReader::<i8, _>::open(&path),
+            Err(TrsError::FileTooShort { num_traces: 3, .. })
+        ));
+
+        // A header value longer than the file is not allocated
+        let bytes = vec![0x47, 0x84, 0xff, 0xff, 0xff, 0xff, 0x00];
+        assert!(matches!(
+            TrsReader::<i8, _>::new(Cursor::new(bytes)),
+            Err(TrsError::IoError(_))
+        ));
+
+        // Nor traces larger than the file
+        let bytes = [
+            0x41, 0x01, 0x01, 0x42, 0x04, 0xff, 0xff, 0xff, 0xff, 0x43, 0x01, 0x14, 0x44, 0x02,
+            0xff, 0xff, 0x5f, 0x00,
+        ];
+        std::fs::write(&path, bytes).unwrap();
+        assert!(matches!(
+            TrsReader::<f32, _>::open(&path),
+            Err(TrsError::FileTooShort { num_traces: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_trs_header_too_large() {
+        let mut header = TrsHeader::new::<i8>(4, 0x10000);
+        assert!(matches!(
+            TrsWriter::<i8, _>::new(Cursor::new(Vec::new()), header.clone()),
+            Err(TrsError::ValueTooLarge {
+                field: "data length",
+                ..
+            })
+        ));
+
+        header.data_length = 1;
+        header.title_length = 0x100;
+        assert!(matches!(
+            TrsWriter::<i8, _>::new(Cursor::new(Vec::new()), header),
+            Err(TrsError::ValueTooLarge {
+                field: "title length",
+                ..
+            })
+        ));
+    }
+}
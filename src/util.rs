@@ -24,11 +24,15 @@ pub fn read_array1_from_npy_file<T: Deserialize, R: Read>(npy: NpyFile<R>) -> Ar
     Array::from_vec(v)
 }
 
-/// Creates a [`ProgressBar`] with a predefined default style.
+/// Creates a [`ProgressBar`] with a predefined default style, showing the throughput and the
+/// remaining time.
+///
+/// It can be passed to the `*_with_progress` helpers (see [`crate::progress`]).
 #[cfg(feature = "progress_bar")]
 pub fn progress_bar(len: usize) -> ProgressBar {
     let progress_bar = ProgressBar::new(len as u64).with_style(
-        ProgressStyle::with_template("{elapsed_precise} {wide_bar} {pos}/{len} ({eta})").unwrap(),
+        ProgressStyle::with_template("{elapsed_precise} {wide_bar} {pos}/{len} ({per_sec}, {eta})")
+            .unwrap(),
     );
     progress_bar.enable_steady_tick(Duration::new(0, 100000000));
     progress_bar